@@ -0,0 +1,48 @@
+//! Parsing throughput, in MB/s of .fec file.
+//!
+//! Reading just the header, parsing every record, and the count-only fast
+//! path of [FecFile::count_by_line_code] are measured separately.
+//!
+//! Run with `cargo bench -p feco3`. Everything is parsed from memory,
+//! so the numbers don't depend on the disk.
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+
+use feco3::FecFile;
+
+/// An F3XA with ~120 itemizations.
+static FILING: &[u8] = include_bytes!("../../../test/fecs/1550548.fec");
+
+fn parse(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse");
+    // Only the header line is read, so count headers rather than bytes.
+    group.throughput(Throughput::Elements(1));
+    group.bench_function("header", |b| {
+        b.iter(|| {
+            let fec = FecFile::from_bytes(FILING.to_vec());
+            black_box(fec.into_header().unwrap())
+        })
+    });
+
+    group.throughput(Throughput::Bytes(FILING.len() as u64));
+    group.bench_function("records", |b| {
+        b.iter(|| {
+            let mut fec = FecFile::from_bytes(FILING.to_vec());
+            let mut n = 0;
+            for record in fec.records() {
+                black_box(record.unwrap());
+                n += 1;
+            }
+            n
+        })
+    });
+    group.bench_function("count_by_line_code", |b| {
+        b.iter(|| {
+            let mut fec = FecFile::from_bytes(FILING.to_vec());
+            black_box(fec.count_by_line_code().unwrap())
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, parse);
+criterion_main!(benches);
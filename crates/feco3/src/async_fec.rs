@@ -0,0 +1,210 @@
+//! Parse .fec files from a tokio [AsyncRead], without blocking the runtime.
+
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+
+use crate::cover::{parse_cover_line, Cover, CoverParseError};
+use crate::csv::{is_f99_line, CsvReader, Sep, BEGIN_TEXT, END_TEXT};
+use crate::fec::{cover_line_error, put_text_in_line};
+use crate::header::{parse_header, Header};
+use crate::record::{Record, RecordParseError, RecordSchema};
+use crate::schemas::{CoercingLineParser, LineParser, SchemaOverrides};
+use crate::Error;
+
+/// Like [FecFile](crate::FecFile), but for async sources.
+///
+/// Physical lines are read asynchronously, and then each one is parsed
+/// synchronously, which is cheap since it's already in memory.
+/// This means that in comma separated files (before version 6),
+/// a quoted field can't contain a newline.
+///
+/// Unlike [FecFile](crate::FecFile), gzipped sources aren't detected,
+/// and the first error is always returned, like [ErrorMode::FailFast](crate::ErrorMode::FailFast).
+pub struct AsyncFecFile<R> {
+    reader: BufReader<R>,
+    header: Option<Header>,
+    cover: Option<Cover>,
+    sep: Option<Sep>,
+    schema_overrides: SchemaOverrides,
+    parser: CoercingLineParser,
+    /// A line that was read ahead, to be returned next.
+    peeked: Option<Vec<u8>>,
+    /// How many itemization lines have been read.
+    rows_read: usize,
+}
+
+impl<R: AsyncRead + Unpin> AsyncFecFile<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader: BufReader::new(reader),
+            header: None,
+            cover: None,
+            sep: None,
+            schema_overrides: SchemaOverrides::new(),
+            parser: CoercingLineParser::new(),
+            peeked: None,
+            rows_read: 0,
+        }
+    }
+
+    /// See [FecFile::register_schema](crate::FecFile::register_schema).
+    pub fn register_schema(&mut self, version: &str, schema: RecordSchema) {
+        self.schema_overrides.insert(version, schema);
+    }
+
+    pub async fn get_header(&mut self) -> Result<&Header, Error> {
+        self.parse_header().await?;
+        Ok(self.header.as_ref().expect("header should be set"))
+    }
+
+    pub async fn get_cover(&mut self) -> Result<&Cover, Error> {
+        self.parse_cover().await?;
+        Ok(self.cover.as_ref().expect("cover should be set"))
+    }
+
+    /// The next itemization line, split into fields.
+    pub async fn next_line(&mut self) -> Option<Result<Vec<String>, Error>> {
+        if let Err(e) = self.parse_cover().await {
+            return Some(Err(e));
+        }
+        let line = self.next_split_line().await.transpose()?;
+        self.rows_read += 1;
+        Some(line)
+    }
+
+    /// Parse the next itemization line into a [Record].
+    ///
+    /// The header and cover are parsed first if they haven't been already.
+    /// Returns None once there are no more lines.
+    pub async fn next_record(&mut self) -> Option<Result<Record, Error>> {
+        let line = match self.next_line().await? {
+            Ok(line) => line,
+            Err(e) => return Some(Err(e)),
+        };
+        let fec_version = self.header.as_ref().expect("No header").schema_version();
+        let result = self.parser.parse_line_with_overrides(
+            fec_version,
+            &self.schema_overrides,
+            &mut line.iter(),
+        );
+        Some(result.map(|mut record| {
+            record.row_index = self.rows_read - 1;
+            record
+        }))
+    }
+
+    async fn parse_header(&mut self) -> Result<(), Error> {
+        if self.header.is_some() {
+            return Ok(());
+        }
+        let mut raw = self.next_physical_line().await?.unwrap_or_default();
+        // Legacy headers span every line up to a closing "/*".
+        if raw.windows(2).any(|w| w == b"/*") {
+            loop {
+                let line = self.next_physical_line().await?.unwrap_or_default();
+                raw.push(b'\n');
+                raw.extend_from_slice(&line);
+                if line.is_empty() || line.windows(2).any(|w| w == b"/*") {
+                    break;
+                }
+            }
+        }
+        raw.push(b'\n');
+        let mut parsing = parse_header(&mut raw.as_slice(), &self.schema_overrides, None)?;
+        if !parsing.unread.is_empty() {
+            // There was no header, so that was the first line of the body.
+            parsing.unread.pop();
+            self.peeked = Some(parsing.unread);
+        }
+        self.header = Some(parsing.header);
+        self.sep = Some(parsing.sep);
+        Ok(())
+    }
+
+    async fn parse_cover(&mut self) -> Result<(), Error> {
+        if self.cover.is_some() {
+            return Ok(());
+        }
+        self.parse_header().await?;
+        let line = match self.next_split_line().await {
+            Ok(None) => return Err(CoverParseError::Missing.into()),
+            Ok(Some(line)) => line,
+            Err(e) => return Err(cover_line_error(e)),
+        };
+        self.cover = Some(parse_cover_line(
+            self.header.as_ref().expect("No header"),
+            &self.schema_overrides,
+            &mut line.iter(),
+        )?);
+        Ok(())
+    }
+
+    /// The next non-empty line, split into fields,
+    /// with the text block of F99 lines attached.
+    async fn next_split_line(&mut self) -> Result<Option<Vec<String>>, Error> {
+        let raw = loop {
+            match self.next_physical_line().await? {
+                None => return Ok(None),
+                Some(raw) if raw.is_empty() => continue,
+                Some(raw) => break raw,
+            }
+        };
+        let sep = self.sep.expect("No sep");
+        let mut line = CsvReader::new(raw.as_slice(), &sep)
+            .next_line()
+            .unwrap_or_else(|| Ok(Vec::new()))?;
+        if is_f99_line(&line) {
+            if let Some(text) = self.read_text_block().await? {
+                let fec_version = self.header.as_ref().expect("No header").schema_version();
+                put_text_in_line(fec_version, &self.schema_overrides, &mut line, text);
+            }
+        }
+        Ok(Some(line))
+    }
+
+    /// If the next line is `[BEGINTEXT]`, read up to the `[ENDTEXT]` line
+    /// and return everything in between, verbatim.
+    async fn read_text_block(&mut self) -> Result<Option<String>, Error> {
+        match self.next_physical_line().await? {
+            Some(raw) if is_marker(&raw, BEGIN_TEXT) => {}
+            other => {
+                self.peeked = other;
+                return Ok(None);
+            }
+        }
+        let mut lines = Vec::new();
+        loop {
+            let raw = self.next_physical_line().await?.ok_or_else(|| {
+                RecordParseError::Malformed(format!("no {} after {}", END_TEXT, BEGIN_TEXT))
+            })?;
+            if is_marker(&raw, END_TEXT) {
+                break;
+            }
+            let line = String::from_utf8(raw)
+                .map_err(|e| RecordParseError::Malformed(format!("invalid UTF-8: {}", e)))?;
+            lines.push(line);
+        }
+        Ok(Some(lines.join("\n")))
+    }
+
+    /// The next line without its line ending, or None at the end of the source.
+    async fn next_physical_line(&mut self) -> Result<Option<Vec<u8>>, Error> {
+        if let Some(line) = self.peeked.take() {
+            return Ok(Some(line));
+        }
+        let mut line = Vec::new();
+        if self.reader.read_until(b'\n', &mut line).await? == 0 {
+            return Ok(None);
+        }
+        if line.ends_with(b"\n") {
+            line.pop();
+        }
+        if line.ends_with(b"\r") {
+            line.pop();
+        }
+        Ok(Some(line))
+    }
+}
+
+fn is_marker(raw: &[u8], marker: &str) -> bool {
+    raw.trim_ascii().eq_ignore_ascii_case(marker.as_bytes())
+}
@@ -0,0 +1,238 @@
+//! Configure a [FecFile] up front, instead of with setters.
+
+use std::io::Read;
+use std::path::Path;
+
+use crate::csv::Sep;
+use crate::fec::{DuplicateMode, ErrorMode, FecFile, RecordFilter};
+use crate::progress::ProgressCallback;
+use crate::record::{RecordSchema, ValueType};
+use crate::schemas::{FieldTransform, ParseMode};
+use crate::Error;
+
+/// Collects the options for a [FecFile], then creates it from a source.
+///
+/// Every option defaults to the same as a plain [FecFile::from_reader].
+/// See the matching setters on [FecFile] for what each one does.
+///
+/// ```no_run
+/// use feco3::{ErrorMode, FecFileBuilder};
+///
+/// let fec = FecFileBuilder::new()
+///     .line_filter(vec!["SA".to_string()])
+///     .error_mode(ErrorMode::Collect)
+///     .build_from_path("filing.fec".as_ref())
+///     .unwrap();
+/// ```
+#[derive(Default)]
+pub struct FecFileBuilder {
+    line_filter: Option<Vec<String>>,
+    error_mode: ErrorMode,
+    duplicate_mode: DuplicateMode,
+    parse_mode: ParseMode,
+    schemas: Vec<(String, RecordSchema)>,
+    aliases: Vec<(String, String)>,
+    field_types: Vec<(String, String, ValueType)>,
+    field_transforms: Vec<(String, String, FieldTransform)>,
+    record_filter: Option<RecordFilter>,
+    schema_fallback: bool,
+    multi_filing: bool,
+    keep_raw: bool,
+    windows_1252_fallback: bool,
+    progress_callback: Option<ProgressCallback>,
+    content_hash: bool,
+    mismatch_log_level: Option<log::LevelFilter>,
+    separator: Option<Sep>,
+    lenient_numbers: bool,
+    source_id: Option<String>,
+}
+
+impl FecFileBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// See [FecFile::set_line_filter].
+    pub fn line_filter(mut self, codes: Vec<String>) -> Self {
+        self.line_filter = Some(codes);
+        self
+    }
+
+    /// See [FecFile::set_error_mode].
+    pub fn error_mode(mut self, mode: ErrorMode) -> Self {
+        self.error_mode = mode;
+        self
+    }
+
+    /// See [FecFile::set_duplicate_mode].
+    pub fn duplicate_mode(mut self, mode: DuplicateMode) -> Self {
+        self.duplicate_mode = mode;
+        self
+    }
+
+    /// See [FecFile::set_parse_mode].
+    pub fn parse_mode(mut self, mode: ParseMode) -> Self {
+        self.parse_mode = mode;
+        self
+    }
+
+    /// See [FecFile::set_lenient_numbers].
+    pub fn lenient_numbers(mut self, lenient: bool) -> Self {
+        self.lenient_numbers = lenient;
+        self
+    }
+
+    /// See [FecFile::register_schema]. Can be called multiple times.
+    pub fn register_schema(mut self, version: &str, schema: RecordSchema) -> Self {
+        self.schemas.push((version.to_string(), schema));
+        self
+    }
+
+    /// See [FecFile::alias_line_code]. Can be called multiple times.
+    pub fn alias_line_code(mut self, alias: &str, canonical: &str) -> Self {
+        self.aliases
+            .push((alias.to_string(), canonical.to_string()));
+        self
+    }
+
+    /// See [FecFile::set_field_type]. Can be called multiple times.
+    pub fn field_type(mut self, line_code: &str, field_name: &str, typ: ValueType) -> Self {
+        self.field_types
+            .push((line_code.to_string(), field_name.to_string(), typ));
+        self
+    }
+
+    /// See [FecFile::add_field_transform]. Can be called multiple times.
+    pub fn field_transform(
+        mut self,
+        line_code: &str,
+        field_name: &str,
+        transform: FieldTransform,
+    ) -> Self {
+        self.field_transforms
+            .push((line_code.to_string(), field_name.to_string(), transform));
+        self
+    }
+
+    /// See [FecFile::set_record_filter].
+    pub fn record_filter(mut self, filter: RecordFilter) -> Self {
+        self.record_filter = Some(filter);
+        self
+    }
+
+    /// See [FecFile::set_schema_fallback].
+    pub fn schema_fallback(mut self, fallback: bool) -> Self {
+        self.schema_fallback = fallback;
+        self
+    }
+
+    /// See [FecFile::set_multi_filing].
+    pub fn multi_filing(mut self, multi_filing: bool) -> Self {
+        self.multi_filing = multi_filing;
+        self
+    }
+
+    /// See [FecFile::set_keep_raw].
+    pub fn keep_raw(mut self, keep_raw: bool) -> Self {
+        self.keep_raw = keep_raw;
+        self
+    }
+
+    /// See [FecFile::set_windows_1252_fallback].
+    pub fn windows_1252_fallback(mut self, fallback: bool) -> Self {
+        self.windows_1252_fallback = fallback;
+        self
+    }
+
+    /// See [FecFile::set_progress_callback].
+    pub fn progress_callback(mut self, callback: ProgressCallback) -> Self {
+        self.progress_callback = Some(callback);
+        self
+    }
+
+    /// See [FecFile::enable_content_hash].
+    pub fn content_hash(mut self, enabled: bool) -> Self {
+        self.content_hash = enabled;
+        self
+    }
+
+    /// See [FecFile::set_source_id].
+    pub fn source_id(mut self, source_id: &str) -> Self {
+        self.source_id = Some(source_id.to_string());
+        self
+    }
+
+    /// See [FecFile::set_separator].
+    pub fn separator(mut self, sep: Sep) -> Self {
+        self.separator = Some(sep);
+        self
+    }
+
+    /// See [FecFile::set_mismatch_log_level].
+    pub fn mismatch_log_level(mut self, level: log::LevelFilter) -> Self {
+        self.mismatch_log_level = Some(level);
+        self
+    }
+
+    pub fn build_from_reader(self, reader: Box<dyn Read + Send>) -> FecFile {
+        let mut fec = FecFile::from_reader(reader);
+        if let Some(codes) = self.line_filter {
+            fec.set_line_filter(codes);
+        }
+        fec.set_error_mode(self.error_mode);
+        fec.set_duplicate_mode(self.duplicate_mode);
+        fec.set_parse_mode(self.parse_mode);
+        fec.set_lenient_numbers(self.lenient_numbers);
+        for (version, schema) in self.schemas {
+            fec.register_schema(&version, schema);
+        }
+        for (alias, canonical) in self.aliases {
+            fec.alias_line_code(&alias, &canonical);
+        }
+        for (line_code, field_name, typ) in self.field_types {
+            fec.set_field_type(&line_code, &field_name, typ);
+        }
+        for (line_code, field_name, transform) in self.field_transforms {
+            fec.add_field_transform(&line_code, &field_name, transform);
+        }
+        if let Some(filter) = self.record_filter {
+            fec.set_record_filter(filter);
+        }
+        fec.set_schema_fallback(self.schema_fallback);
+        fec.set_multi_filing(self.multi_filing);
+        fec.set_keep_raw(self.keep_raw);
+        fec.set_windows_1252_fallback(self.windows_1252_fallback);
+        if let Some(callback) = self.progress_callback {
+            fec.set_progress_callback(callback);
+        }
+        if self.content_hash {
+            fec.enable_content_hash();
+        }
+        if let Some(level) = self.mismatch_log_level {
+            fec.set_mismatch_log_level(level);
+        }
+        if let Some(sep) = self.separator {
+            fec.set_separator(sep);
+        }
+        if let Some(source_id) = self.source_id {
+            fec.set_source_id(&source_id);
+        }
+        fec
+    }
+
+    pub fn build_from_path(self, path: &Path) -> Result<FecFile, Error> {
+        let file = std::fs::File::open(path)?;
+        Ok(self.build_from_reader(Box::new(file)))
+    }
+
+    pub fn build_from_bytes(self, bytes: Vec<u8>) -> FecFile {
+        self.build_from_reader(Box::new(std::io::Cursor::new(bytes)))
+    }
+
+    /// See [FecFile::from_url_with_retries].
+    #[cfg(feature = "http")]
+    pub fn build_from_url(self, url: &str, max_retries: u32) -> Result<FecFile, Error> {
+        let reader = crate::http::ResumingReader::open(url, max_retries)?;
+        Ok(self.build_from_reader(Box::new(reader)))
+    }
+}
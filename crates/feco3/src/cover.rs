@@ -3,8 +3,18 @@
 //! This is a single line with summary information about the file.
 //!
 //! See the test case .fec files for examples.
-use crate::record::Record;
-use crate::schemas::{LineParser, LiteralLineParser};
+use std::collections::BTreeMap;
+use std::io::Read;
+
+use chrono::NaiveDate;
+use serde::Serialize;
+
+use crate::csv::CsvReader;
+use crate::fec::cover_line_error;
+use crate::header::{parse_header, Header};
+
+use crate::record::{parse_date, Record};
+use crate::schemas::{LineParser, LiteralLineParser, SchemaOverrides};
 use crate::Error;
 
 /// The cover line in an FecFile.
@@ -12,34 +22,237 @@ use crate::Error;
 /// See
 /// [https://github.com/NickCrews/feco3/wiki](https://github.com/NickCrews/feco3/wiki)
 /// for more info.
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize)]
 pub struct Cover {
     /// What form is this .fec file, eg "F3X"
     pub form_type: String,
     /// Who filed this .fec file, eg "C00101766"
     pub filer_committee_id: String,
+    /// Which report this is, eg "Q1" or "M10" or "12G".
+    ///
+    /// Not all forms have this, eg F99 doesn't.
+    pub report_code: Option<String>,
+    /// The first day of the period this report covers.
+    pub coverage_from_date: Option<NaiveDate>,
+    /// The last day of the period this report covers.
+    pub coverage_through_date: Option<NaiveDate>,
+    /// The free-form text of an F99 (miscellaneous text) filing,
+    /// from between its `[BEGINTEXT]` and `[ENDTEXT]` markers.
+    pub text: Option<String>,
+    /// The report ID from the [Header], eg "FEC-1531171".
+    ///
+    /// For amendments this is the filing being amended,
+    /// see [Cover::amends_report_id].
+    pub report_id: Option<String>,
+    /// How many lines of some line codes the cover line says follow,
+    /// eg {"F8II": 2} from the "num_creditors_part_ii" of an F8.
+    ///
+    /// Only a few forms declare any, so this is usually empty.
+    /// See [FecFile::check_schedule_counts](crate::FecFile::check_schedule_counts).
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub schedule_counts: BTreeMap<String, u64>,
+}
+
+/// The cover fields that count the lines of a line code,
+/// and the line code they count.
+///
+/// Most forms only declare dollar totals, which can't be checked
+/// against the lines without parsing them.
+const DECLARED_COUNTS: &[(&str, &str)] = &[
+    // Part II of Form 8 lists each creditor on its own line.
+    ("num_creditors_part_ii", "F8II"),
+];
+
+/// The ways that parsing a [Cover] can fail.
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum CoverParseError {
+    /// The file ended right after the header.
+    #[error("no cover line")]
+    Missing,
+    /// The cover line doesn't have a field we need.
+    #[error("no '{0}' in cover line")]
+    MissingField(String),
+    /// The cover line couldn't be split into fields.
+    #[error("{0}")]
+    Malformed(String),
+}
+
+lazy_static! {
+    /// Committee IDs like "C00401224", presidential candidate IDs like
+    /// "P80001571", and House/Senate candidate IDs like "H0AK00097",
+    /// which have the election year digit and the state in the middle.
+    static ref FILER_ID_REGEX: regex::Regex =
+        regex::Regex::new(r"^([CP]\d{8}|[HS]\d[A-Z]{2}\d{5})$").unwrap();
+}
+
+impl Cover {
+    /// Whether [Cover::filer_committee_id] looks like a real FEC ID.
+    ///
+    /// Committees have IDs like "C00401224". Forms filed by candidates
+    /// use the candidate's ID instead, like "P80001571" or "S0AK00196".
+    /// Test filings and malformed data often fail this check.
+    pub fn is_valid_committee_id(&self) -> bool {
+        FILER_ID_REGEX.is_match(&self.filer_committee_id)
+    }
+
+    /// The first and last day this report covers, if it has both.
+    pub fn coverage_range(&self) -> Option<(NaiveDate, NaiveDate)> {
+        Some((self.coverage_from_date?, self.coverage_through_date?))
+    }
+
+    /// Whether this filing amends an earlier one.
+    ///
+    /// Amendments have form types ending in "A", eg "F3XA" amends an
+    /// "F3XN" (new) filing. Terminations ("F3XT") aren't amendments.
+    pub fn is_amendment(&self) -> bool {
+        self.form_type.len() > 2 && self.form_type.to_uppercase().ends_with('A')
+    }
+
+    /// The report ID of the filing this one amends, eg "FEC-1531171",
+    /// or None if this isn't an amendment.
+    ///
+    /// Follow these back to find the original, or group filings by their
+    /// original to find the latest version of each report.
+    pub fn amends_report_id(&self) -> Option<String> {
+        if !self.is_amendment() {
+            return None;
+        }
+        self.report_id.clone()
+    }
+
+    /// Whether `date` is within [Cover::coverage_range], inclusive.
+    ///
+    /// Always false if the cover doesn't have both dates.
+    pub fn covers(&self, date: NaiveDate) -> bool {
+        self.coverage_range()
+            .is_some_and(|(from, through)| from <= date && date <= through)
+    }
 }
 
 pub fn parse_cover_line<'a>(
-    fec_version: &str,
+    header: &Header,
+    overrides: &SchemaOverrides,
     line: &mut impl Iterator<Item = &'a String>,
 ) -> Result<Cover, Error> {
     let mut cover = Cover::default();
+    let fec_version = header.schema_version();
     let line = line.collect::<Vec<&String>>();
     log::debug!("parsing cover line {} {:?}", fec_version, line);
-    let record = LiteralLineParser.parse_line(fec_version, &mut line.into_iter())?;
+    let record = LiteralLineParser.parse_line_with_overrides(
+        fec_version,
+        overrides,
+        &mut line.into_iter(),
+    )?;
     cover.form_type = record.record_code.clone();
     cover.filer_committee_id = get(&record, "filer_committee_id_number")?;
+    cover.report_code = get_optional(&record, "report_code");
+    cover.coverage_from_date = get_optional_date(&record, "coverage_from_date");
+    cover.coverage_through_date = get_optional_date(&record, "coverage_through_date");
+    cover.text = get_optional(&record, "text");
+    cover.report_id = header.report_id.clone();
+    for (field_name, line_code) in DECLARED_COUNTS {
+        let raw = get_optional(&record, field_name);
+        match raw.as_deref().map(str::parse) {
+            None => {}
+            Some(Ok(count)) => {
+                cover.schedule_counts.insert(line_code.to_string(), count);
+            }
+            Some(Err(_)) => {
+                log::warn!(
+                    "invalid count for '{}' in cover line: {:?}",
+                    field_name,
+                    raw
+                );
+            }
+        }
+    }
+    if !cover.is_valid_committee_id() {
+        log::warn!(
+            "filer committee ID '{}' doesn't look like an FEC ID",
+            cover.filer_committee_id
+        );
+    }
     log::debug!("parsed cover line {:?}", cover);
     Ok(cover)
 }
 
-fn get(record: &Record, field_name: &str) -> Result<String, Error> {
+/// Parse just the header and cover of a .fec file, and stop.
+///
+/// This is much lighter than a [FecFile](crate::FecFile) when you only
+/// need eg the form type and committee of a lot of files.
+/// `reader` is read one byte at a time, so nothing past the end of the
+/// cover line is consumed. Wrap slow sources in a [std::io::BufReader]
+/// if that doesn't matter to you.
+///
+/// Gzipped sources aren't detected, and the text block of F99 filings
+/// isn't read, so [Cover::text] is always None.
+pub fn parse_cover_from_reader(mut reader: impl Read) -> Result<(Header, Cover), Error> {
+    let overrides = SchemaOverrides::new();
+    let parsing = parse_header(&mut reader, &overrides, None)?;
+    let line = if !parsing.unread.is_empty() {
+        parsing.unread
+    } else {
+        // Skip any blank lines between the header and the cover.
+        loop {
+            let line = read_line_unbuffered(&mut reader)?.ok_or(CoverParseError::Missing)?;
+            if !line.is_empty() {
+                break line;
+            }
+        }
+    };
+    let fields = CsvReader::new(line.as_slice(), &parsing.sep)
+        .next_line()
+        .ok_or(CoverParseError::Missing)?
+        .map_err(cover_line_error)?;
+    let cover = parse_cover_line(&parsing.header, &overrides, &mut fields.iter())?;
+    Ok((parsing.header, cover))
+}
+
+/// Read up to and including the next '\n', without reading any further.
+///
+/// Returns the line without its line ending, or None at the end of `reader`.
+fn read_line_unbuffered(reader: &mut impl Read) -> std::io::Result<Option<Vec<u8>>> {
+    let mut line = Vec::new();
+    let mut byte = [0u8];
+    loop {
+        if reader.read(&mut byte)? == 0 {
+            if line.is_empty() {
+                return Ok(None);
+            }
+            break;
+        }
+        if byte[0] == b'\n' {
+            break;
+        }
+        line.push(byte[0]);
+    }
+    if line.ends_with(b"\r") {
+        line.pop();
+    }
+    Ok(Some(line))
+}
+
+fn get(record: &Record, field_name: &str) -> Result<String, CoverParseError> {
     Ok(record
         .get_value(field_name)
-        .ok_or(Error::CoverParseError(format!(
-            "no '{}' in cover line",
-            field_name
-        )))?
+        .ok_or_else(|| CoverParseError::MissingField(field_name.to_string()))?
         .to_string())
 }
+
+/// Get a field that might not be in the schema, or might be empty.
+fn get_optional(record: &Record, field_name: &str) -> Option<String> {
+    let value = record.get_value(field_name)?.to_string();
+    match value.trim() {
+        "" => None,
+        s => Some(s.to_string()),
+    }
+}
+
+fn get_optional_date(record: &Record, field_name: &str) -> Option<NaiveDate> {
+    let raw = get_optional(record, field_name)?;
+    let date = parse_date(&raw);
+    if date.is_none() {
+        log::warn!("invalid date for '{}' in cover line: {}", field_name, raw);
+    }
+    date
+}
@@ -1,13 +1,26 @@
 //! A wrapper around [csv::Reader] that returns raw Vec<&str> records.
 
-use std::io::Read;
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader, Read};
+use std::sync::{Arc, Mutex};
 
-use csv::ReaderBuilder;
+use csv::{ByteRecord, ReaderBuilder};
 
-#[derive(Debug, Clone)]
+use crate::{form::FormType, record::RecordParseError, Error};
+
+/// The field separator used in a .fec file.
+///
+/// Files from version 6 onwards use ASCII 28 (the "file separator" character),
+/// older files use commas.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Sep {
     Comma,
     Ascii28,
+    /// Any other byte, eg b'\t' for tab-delimited exports from third-party
+    /// tools. These are never detected, only set explicitly with
+    /// [FecFile::set_separator](crate::FecFile::set_separator).
+    /// Fields aren't quoted.
+    Other(u8),
 }
 
 impl Sep {
@@ -17,6 +30,7 @@ impl Sep {
         match self {
             Sep::Comma => b',',
             Sep::Ascii28 => b'\x1c',
+            Sep::Other(byte) => *byte,
         }
     }
 
@@ -31,20 +45,58 @@ impl Sep {
     }
 }
 
+/// Marks the start of the free-form text that follows an F99 line.
+pub(crate) const BEGIN_TEXT: &str = "[BEGINTEXT]";
+/// Marks the end of the free-form text that follows an F99 line.
+pub(crate) const END_TEXT: &str = "[ENDTEXT]";
+
 /// A convenience wrapper around a csv::Reader.
 pub struct CsvReader<R: Read> {
-    records: csv::StringRecordsIntoIter<R>,
+    records: csv::ByteRecordsIntoIter<QuoteResync<R>>,
+    sep: Sep,
+    /// A record that was read ahead, to be returned next.
+    peeked: Option<csv::Result<ByteRecord>>,
+    /// Decode fields that aren't valid UTF-8 as Windows-1252.
+    windows_1252_fallback: bool,
+    /// The byte offset of the start of the last line read.
+    last_offset: Option<u64>,
+    /// The text block that followed the last line read, if it was an F99.
+    text_block: Option<String>,
+    /// The byte offsets of lines with unterminated quotes, see [QuoteResync].
+    broken: BrokenLines,
+    /// Whether the last line read was cut off by the end of the source.
+    last_line_unterminated: bool,
 }
 
 impl<R: Read> CsvReader<R> {
+    /// Read lines from `src`, split by `sep`.
+    ///
+    /// Comma separated files quote fields RFC 4180 style, so quoted fields
+    /// can contain commas and newlines. ASCII 28 separated files never
+    /// quote, and a `"` is just a character in the field
+    /// (eg a nickname like `"Bud" Smith`), so quoting is turned off for them.
+    ///
+    /// A comma separated line with an unterminated quote would otherwise
+    /// swallow the lines after it, so it is returned as an error instead,
+    /// and reading picks up again at the next line that starts a record.
     pub fn new(src: R, sep: &Sep) -> Self {
+        let broken = BrokenLines::default();
+        let resync = QuoteResync::new(src, *sep == Sep::Comma, broken.clone());
         let reader = ReaderBuilder::new()
             .delimiter(sep.to_byte())
+            .quoting(*sep == Sep::Comma)
             .has_headers(false)
             .flexible(true)
-            .from_reader(src);
+            .from_reader(resync);
         Self {
-            records: reader.into_records(),
+            records: reader.into_byte_records(),
+            sep: *sep,
+            peeked: None,
+            windows_1252_fallback: false,
+            last_offset: None,
+            text_block: None,
+            broken,
+            last_line_unterminated: false,
         }
     }
 
@@ -56,13 +108,346 @@ impl<R: Read> CsvReader<R> {
     ///
     /// The Ok value is a Vec<&str> of the fields in the line.
     /// The caller is responsible for converting the fields to the correct types.
-    pub fn next_line(&mut self) -> Option<Result<Vec<String>, String>> {
-        let record_or_err = self.records.next()?;
+    ///
+    /// F99 lines are followed by free-form text on the following physical
+    /// lines, between `[BEGINTEXT]` and `[ENDTEXT]`. That whole block is
+    /// consumed along with the F99 line, instead of being returned as
+    /// lines of its own. See [CsvReader::take_text_block].
+    pub fn next_line(&mut self) -> Option<Result<Vec<String>, Error>> {
+        let record_or_err = self.next_record()?;
         log::debug!("raw_record: {:?}", record_or_err);
+        self.text_block = None;
         let strings: Vec<String> = match record_or_err {
-            Err(e) => return Some(Err(e.to_string())),
-            Ok(record) => record.iter().map(|s| s.to_string()).collect(),
+            Err(e) => return Some(Err(convert_error(e))),
+            Ok(record) => {
+                self.last_offset = record.position().map(|p| p.byte());
+                // Checked before any read ahead for a text block.
+                self.last_line_unterminated = self.records.reader().get_ref().ended_mid_line();
+                match self.decode(&record) {
+                    Ok(strings) => strings,
+                    Err(e) => return Some(Err(e)),
+                }
+            }
         };
+        if is_f99_line(&strings) {
+            match self.read_text_block() {
+                Ok(text) => self.text_block = text,
+                Err(e) => return Some(Err(e)),
+            }
+        }
         Some(Ok(strings))
     }
+
+    fn next_record(&mut self) -> Option<csv::Result<ByteRecord>> {
+        if let Some(peeked) = self.peeked.take() {
+            return Some(peeked);
+        }
+        let record = match self.records.next()? {
+            Ok(record) => record,
+            Err(e) => return Some(Err(e)),
+        };
+        let offset = record.position().map(|p| p.byte());
+        let mut broken = self.broken.lock().unwrap();
+        if offset.is_some() && broken.front().copied() == offset {
+            broken.pop_front();
+            let error: Error = RecordParseError::Malformed("unterminated quote".to_string()).into();
+            let io_error = std::io::Error::new(std::io::ErrorKind::InvalidData, error);
+            return Some(Err(io_error.into()));
+        }
+        Some(Ok(record))
+    }
+
+    /// If the next line is `[BEGINTEXT]`, read up to the `[ENDTEXT]` line
+    /// and return everything in between.
+    ///
+    /// The text isn't delimited, so any separators in it are put back,
+    /// and blank lines (which the csv reader skips) are restored from
+    /// the line numbers. Comma separated files may still lose quotes.
+    fn read_text_block(&mut self) -> Result<Option<String>, Error> {
+        match self.next_record() {
+            Some(Ok(record)) if is_marker(&record, BEGIN_TEXT) => {}
+            other => {
+                self.peeked = other;
+                return Ok(None);
+            }
+        }
+        let sep = (self.sep.to_byte() as char).to_string();
+        let mut lines: Vec<String> = Vec::new();
+        let mut last_line_number = None;
+        loop {
+            let record = match self.next_record() {
+                None => {
+                    return Err(RecordParseError::Malformed(format!(
+                        "no {} after {}",
+                        END_TEXT, BEGIN_TEXT
+                    ))
+                    .into())
+                }
+                Some(Err(e)) => return Err(convert_error(e)),
+                Some(Ok(record)) => record,
+            };
+            // A record's position includes the blank lines before it, so the
+            // blank lines before the previous text line only show up now.
+            let line_number = record.position().map(|p| p.line());
+            if let (Some(last), Some(current)) = (last_line_number, line_number) {
+                let previous = lines.len() - 1;
+                for _ in last + 1..current {
+                    lines.insert(previous, String::new());
+                }
+            }
+            last_line_number = line_number;
+            if is_marker(&record, END_TEXT) {
+                break;
+            }
+            lines.push(self.decode(&record)?.join(&sep));
+        }
+        Ok(Some(lines.join("\n")))
+    }
+}
+
+pub(crate) fn is_f99_line(line: &[String]) -> bool {
+    line.first()
+        .is_some_and(|code| code.trim().eq_ignore_ascii_case("F99"))
+}
+
+fn is_marker(record: &ByteRecord, marker: &str) -> bool {
+    record.len() == 1
+        && record[0]
+            .trim_ascii()
+            .eq_ignore_ascii_case(marker.as_bytes())
+}
+
+impl<R: Read> CsvReader<R> {
+    /// If true, fields that aren't valid UTF-8 are decoded as Windows-1252
+    /// (a superset of Latin-1) instead of failing the line.
+    pub fn set_windows_1252_fallback(&mut self, fallback: bool) {
+        self.windows_1252_fallback = fallback;
+    }
+
+    fn decode(&self, record: &ByteRecord) -> Result<Vec<String>, Error> {
+        record
+            .iter()
+            .enumerate()
+            .map(|(i, field)| match std::str::from_utf8(field) {
+                Ok(s) => Ok(s.to_string()),
+                Err(_) if self.windows_1252_fallback => {
+                    let (s, _) = encoding_rs::WINDOWS_1252.decode_without_bom_handling(field);
+                    Ok(s.into_owned())
+                }
+                Err(e) => Err(RecordParseError::Malformed(format!(
+                    "invalid UTF-8 in field {}: {}",
+                    i, e
+                ))
+                .into()),
+            })
+            .collect()
+    }
+
+    /// The text between `[BEGINTEXT]` and `[ENDTEXT]` that followed the
+    /// last line read, if it was an F99 line with a text block.
+    pub fn take_text_block(&mut self) -> Option<String> {
+        self.text_block.take()
+    }
+
+    /// The byte offset of the start of the last line read, relative to
+    /// the start of `src`.
+    pub fn last_offset(&self) -> Option<u64> {
+        self.last_offset
+    }
+
+    /// Whether the last line read was the end of `src`, with no newline
+    /// after it, as when a download is cut off partway through a line.
+    ///
+    /// Plenty of complete files have no newline at the end either, so
+    /// this alone doesn't mean the line is incomplete.
+    pub fn last_line_unterminated(&self) -> bool {
+        self.last_line_unterminated
+    }
+}
+
+/// The byte offsets of lines with unterminated quotes.
+type BrokenLines = Arc<Mutex<VecDeque<u64>>>;
+
+/// Wraps the source of a comma separated file, to stop a line with an
+/// unterminated quote from swallowing the lines after it.
+///
+/// Lines are read one at a time, tracking quotes the way the csv reader
+/// does. A quoted field may span lines, but if the quote is still open
+/// when the next line looks like the start of a record (or at EOF),
+/// the quote is taken to be unterminated. Then the broken line is passed
+/// on with its quotes and newlines blanked out, so it is read as one
+/// record without throwing off the byte offsets, and its offset is
+/// noted so that [CsvReader] returns an error for it instead.
+///
+/// Returning an io::Error from here wouldn't do, since the csv reader
+/// stops for good after one.
+struct QuoteResync<R: Read> {
+    inner: BufReader<R>,
+    /// If false, just pass the bytes through.
+    enabled: bool,
+    /// The bytes of the current record, waiting to be read out.
+    pending: Vec<u8>,
+    /// How many of the pending bytes have been read out.
+    pos: usize,
+    /// A line that was read ahead while looking for a closing quote.
+    lookahead: Option<Vec<u8>>,
+    /// The byte offset of the start of the pending bytes.
+    offset: u64,
+    broken: BrokenLines,
+    /// Whether the end of the source has been reached.
+    at_eof: bool,
+    /// Whether the last byte read out was a '\n'.
+    ended_with_newline: bool,
+}
+
+/// Where the csv reader would be within a record.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum QuoteState {
+    StartOfField,
+    Unquoted,
+    Quoted,
+    /// A `"` inside a quoted field, that either closes it or escapes
+    /// the next `"`.
+    QuoteInQuoted,
+}
+
+impl QuoteState {
+    /// The state after `line`, a physical line up to and including '\n'.
+    fn after(mut self, line: &[u8]) -> Self {
+        for &b in line {
+            self = match (self, b) {
+                (QuoteState::Quoted, b'"') => QuoteState::QuoteInQuoted,
+                (QuoteState::Quoted, _) => QuoteState::Quoted,
+                (QuoteState::QuoteInQuoted, b'"') => QuoteState::Quoted,
+                (QuoteState::StartOfField, b'"') => QuoteState::Quoted,
+                (_, b',' | b'\n') => QuoteState::StartOfField,
+                _ => QuoteState::Unquoted,
+            };
+        }
+        self
+    }
+}
+
+impl<R: Read> QuoteResync<R> {
+    fn new(inner: R, enabled: bool, broken: BrokenLines) -> Self {
+        Self {
+            inner: BufReader::new(inner),
+            enabled,
+            pending: Vec::new(),
+            pos: 0,
+            lookahead: None,
+            offset: 0,
+            broken,
+            at_eof: false,
+            ended_with_newline: false,
+        }
+    }
+
+    /// Whether the source ended without a newline after the last line.
+    fn ended_mid_line(&self) -> bool {
+        self.at_eof && !self.ended_with_newline
+    }
+
+    fn read_line(&mut self) -> std::io::Result<Vec<u8>> {
+        if let Some(line) = self.lookahead.take() {
+            return Ok(line);
+        }
+        let mut line = Vec::new();
+        self.inner.read_until(b'\n', &mut line)?;
+        Ok(line)
+    }
+
+    /// Read the next record into `pending`, blanking it out and noting
+    /// it in `broken` if it has an unterminated quote.
+    fn fill(&mut self) -> std::io::Result<()> {
+        self.offset += self.pending.len() as u64;
+        self.pending = self.read_line()?;
+        self.pos = 0;
+        let mut state = QuoteState::StartOfField.after(&self.pending);
+        while state == QuoteState::Quoted {
+            let line = self.read_line()?;
+            if line.is_empty() || looks_like_record_start(&line) {
+                self.lookahead = Some(line).filter(|l| !l.is_empty());
+                break;
+            }
+            state = state.after(&line);
+            self.pending.extend(line);
+        }
+        if state != QuoteState::Quoted {
+            return Ok(());
+        }
+        self.broken.lock().unwrap().push_back(self.offset);
+        let end = self.pending.len() - usize::from(self.pending.ends_with(b"\n"));
+        for b in &mut self.pending[..end] {
+            if matches!(b, b'"' | b'\r' | b'\n') {
+                *b = b' ';
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for QuoteResync<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = match self.enabled {
+            false => self.inner.read(buf)?,
+            true => {
+                if self.pos == self.pending.len() {
+                    self.fill()?;
+                }
+                let n = buf.len().min(self.pending.len() - self.pos);
+                buf[..n].copy_from_slice(&self.pending[self.pos..self.pos + n]);
+                self.pos += n;
+                n
+            }
+        };
+        match n {
+            0 if !buf.is_empty() => self.at_eof = true,
+            0 => {}
+            n => self.ended_with_newline = buf[n - 1] == b'\n',
+        }
+        Ok(n)
+    }
+}
+
+/// Whether `line` looks like the start of a record, ie it starts with
+/// a line code like "SA11AI" or "F3XN" and then a comma.
+///
+/// Line codes (other than "HDR" and "TEXT") have a digit in them, which
+/// keeps eg the "SAN DIEGO" in an address from counting.
+fn looks_like_record_start(line: &[u8]) -> bool {
+    let code = match line.iter().position(|&b| b == b',') {
+        Some(i) => String::from_utf8_lossy(&line[..i]),
+        None => return false,
+    };
+    let code = code.trim().trim_matches('"');
+    if code.is_empty() || code.len() > 8 || !code.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return false;
+    }
+    let upper = code.to_ascii_uppercase();
+    if upper == "HDR" || upper == "TEXT" {
+        return true;
+    }
+    code.chars().any(|c| c.is_ascii_digit())
+        && !matches!(FormType::from_code(code), FormType::Other(_))
+}
+
+/// Convert a [csv::Error] into our [Error].
+///
+/// I/O errors become [Error::IoError], unless the source smuggled one of
+/// our own errors through the io::Error (eg an [Error::HttpError]),
+/// in which case that is unwrapped and returned as-is.
+fn convert_error(e: csv::Error) -> Error {
+    if !e.is_io_error() {
+        return RecordParseError::Malformed(e.to_string()).into();
+    }
+    let io_error = match e.into_kind() {
+        csv::ErrorKind::Io(io_error) => io_error,
+        kind => return RecordParseError::Malformed(format!("{:?}", kind)).into(),
+    };
+    if io_error.get_ref().is_some_and(|inner| inner.is::<Error>()) {
+        let inner = io_error.into_inner().expect("checked above");
+        return *inner.downcast::<Error>().expect("checked above");
+    }
+    Error::IoError(io_error)
 }
@@ -0,0 +1,31 @@
+//! Transparently decompress gzipped sources.
+//!
+//! Detection is based on the magic bytes at the start of the stream,
+//! not on a file extension, so this works for any source of bytes.
+
+use std::io::{Cursor, Read};
+
+use flate2::read::MultiGzDecoder;
+
+/// The first two bytes of every gzip stream.
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// If `src` is gzipped, wrap it in a decoder. Otherwise return it as-is.
+///
+/// This reads the first two bytes of `src` to sniff the format,
+/// then chains them back in front of the rest of the stream,
+/// so nothing is lost either way.
+pub fn maybe_gunzip(mut src: Box<dyn Read + Send>) -> std::io::Result<Box<dyn Read + Send>> {
+    let mut prefix = Vec::with_capacity(GZIP_MAGIC.len());
+    (&mut src)
+        .take(GZIP_MAGIC.len() as u64)
+        .read_to_end(&mut prefix)?;
+    let is_gzip = prefix == GZIP_MAGIC;
+    let chained = Cursor::new(prefix).chain(src);
+    if is_gzip {
+        log::debug!("detected gzipped source");
+        Ok(Box::new(MultiGzDecoder::new(chained)))
+    } else {
+        Ok(Box::new(chained))
+    }
+}
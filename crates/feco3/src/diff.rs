@@ -0,0 +1,137 @@
+//! Compare two filings, eg an original and its amendment.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::record::{Record, Value};
+use crate::{Error, FecFile};
+
+/// What changed between two filings, see [diff_filings].
+#[derive(Debug, Clone, Default)]
+pub struct FilingDiff {
+    /// Records only in the second filing, in the order they appear there.
+    pub added: Vec<Record>,
+    /// Records only in the first filing, in the order they appear there.
+    pub removed: Vec<Record>,
+    /// Records in both filings whose values differ,
+    /// in the order they appear in the second filing.
+    pub changed: Vec<RecordChange>,
+}
+
+impl FilingDiff {
+    /// True if every record is the same in both filings.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// A record whose values differ between two filings.
+#[derive(Debug, Clone)]
+pub struct RecordChange {
+    /// The [Record::transaction_id] the records were matched by.
+    pub transaction_id: String,
+    /// The record in the first filing.
+    pub old: Record,
+    /// The record in the second filing.
+    pub new: Record,
+    /// The fields that differ, in the order of the new record's fields.
+    pub fields: Vec<FieldChange>,
+}
+
+/// A field whose value differs between two versions of a record.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldChange {
+    /// The name of the field, eg "contribution_amount".
+    pub field: String,
+    /// The value in the old record, or None if it didn't have the field.
+    pub old: Option<Value>,
+    /// The value in the new record, or None if it doesn't have the field.
+    pub new: Option<Value>,
+}
+
+/// Compare the itemizations of two filings, eg an original filing `a`
+/// and the amendment `b` that replaces it.
+///
+/// Records are matched up by their [Record::transaction_id], and a
+/// matched pair is changed if any value differs, compared field by field
+/// by name. Records without a transaction ID can't be matched, so they
+/// are left out. If a transaction ID appears several times in a filing,
+/// only its first record is used.
+///
+/// Both files are read from where they are to the end, and every record
+/// of `a` is held in memory. The line filter and record filter of each
+/// file apply.
+pub fn diff_filings(a: &mut FecFile, b: &mut FecFile) -> Result<FilingDiff, Error> {
+    let mut old_records = Vec::new();
+    let mut old_index = HashMap::new();
+    for record in a.records() {
+        let record = record?;
+        let Some(id) = record.transaction_id() else {
+            continue;
+        };
+        if old_index.contains_key(id) {
+            continue;
+        }
+        old_index.insert(id.to_string(), old_records.len());
+        old_records.push(Some(record));
+    }
+
+    let mut diff = FilingDiff::default();
+    let mut seen = HashSet::new();
+    for record in b.records() {
+        let record = record?;
+        let Some(id) = record.transaction_id() else {
+            continue;
+        };
+        if !seen.insert(id.to_string()) {
+            continue;
+        }
+        let old = old_index.get(id).and_then(|i| old_records[*i].take());
+        match old {
+            None => diff.added.push(record),
+            Some(old) => {
+                let fields = diff_fields(&old, &record);
+                if !fields.is_empty() {
+                    diff.changed.push(RecordChange {
+                        transaction_id: id.to_string(),
+                        old,
+                        new: record,
+                        fields,
+                    });
+                }
+            }
+        }
+    }
+    diff.removed = old_records.into_iter().flatten().collect();
+    Ok(diff)
+}
+
+/// The fields whose values differ between `old` and `new`.
+fn diff_fields(old: &Record, new: &Record) -> Vec<FieldChange> {
+    let old_values = old
+        .typed_fields()
+        .map(|(name, _, value)| (name, value))
+        .collect::<HashMap<_, _>>();
+    let mut changes = Vec::new();
+    let mut new_names = HashSet::new();
+    for (name, _, value) in new.typed_fields() {
+        let old_value = old_values.get(&name).copied();
+        if old_value != Some(value) {
+            changes.push(FieldChange {
+                field: name.to_string(),
+                old: old_value.cloned(),
+                new: Some(value.clone()),
+            });
+        }
+        new_names.insert(name);
+    }
+    for (name, _, value) in old.typed_fields() {
+        if !new_names.contains(&name) {
+            changes.push(FieldChange {
+                field: name.to_string(),
+                old: Some(value.clone()),
+                new: None,
+            });
+        }
+    }
+    changes
+}
@@ -1,12 +1,61 @@
+use std::collections::{HashMap, HashSet};
 use std::fs::File;
-use std::io::Read;
+use std::io::{Cursor, Read, Seek, SeekFrom};
 use std::mem::take;
 use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
 
-use crate::cover::{parse_cover_line, Cover};
+use crate::builder::FecFileBuilder;
+use crate::cover::{parse_cover_line, Cover, CoverParseError};
 use crate::csv::{CsvReader, Sep};
-use crate::header::{parse_header, Header};
+use crate::decompress::maybe_gunzip;
+use crate::header::{parse_header, Header, HeaderParseError};
+use crate::progress::{CountingReader, HashingReader, ProgressCallback, ProgressReader};
+use crate::record::{Record, RecordParseError, RecordSchema, Value, ValueType};
+use crate::schemas::{
+    lookup_schema_with_overrides, CoercingLineParser, FieldTransform, LineParser, ParseMode,
+    SchemaOverrides,
+};
+use crate::stats::{CountMismatch, ParseStats, ValidationReport};
 use crate::Error;
+use sha2::{Digest, Sha256};
+
+/// What to do when a single itemization row fails to parse.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ErrorMode {
+    /// Return the error from [FecFile::next_record]. This is the default.
+    #[default]
+    FailFast,
+    /// Skip the row, and save the error to [FecFile::errors].
+    Collect,
+}
+
+/// What to do with a record whose transaction ID was already seen
+/// in the same filing. Amendments sometimes repeat itemizations.
+///
+/// See [Record::transaction_id].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DuplicateMode {
+    /// Don't track transaction IDs. This is the default.
+    #[default]
+    Allow,
+    /// Return duplicates with [Record::is_duplicate] set.
+    Flag,
+    /// Don't return duplicates from [FecFile::next_record].
+    Skip,
+}
+
+/// An itemization row that failed to parse, saved in [ErrorMode::Collect].
+#[derive(Debug)]
+pub struct RowError {
+    /// The index of the row among the itemization rows, starting at 0.
+    /// The header and cover lines aren't counted.
+    pub row_index: usize,
+    /// The fields of the row, joined by the file's separator.
+    /// Empty if the row couldn't be split into fields at all.
+    pub raw: Vec<u8>,
+    pub error: Error,
+}
 
 /// A FEC file, the low-level core data structure of this crate.
 ///
@@ -25,11 +74,56 @@ pub struct FecFile {
     header: Option<Header>,
     cover: Option<Cover>,
     sep: Option<Sep>,
+    /// See [FecFile::set_separator].
+    sep_override: Option<Sep>,
     /// After reading the header, this contains the CSV reader
     /// that will be used to read the rest of the file.
     csv_reader: Option<CsvReader<Box<dyn Read + Send>>>,
+    /// If set, only records whose line code starts with one of these
+    /// prefixes are returned from [FecFile::next_record].
+    line_filter: Option<Vec<String>>,
+    /// See [FecFile::set_record_filter].
+    record_filter: Option<RecordFilter>,
+    error_mode: ErrorMode,
+    errors: Vec<RowError>,
+    duplicate_mode: DuplicateMode,
+    /// The transaction IDs seen so far in the current filing,
+    /// unless the [DuplicateMode] is [DuplicateMode::Allow].
+    seen_transaction_ids: HashSet<String>,
+    /// How many itemization rows have been read from the csv_reader.
+    rows_read: usize,
+    /// How many bytes the header took up, to offset the csv_reader positions.
+    header_len: u64,
+    /// If true, a "HDR" line in the itemizations starts a new filing.
+    multi_filing: bool,
+    /// Which filing in the stream we're in, starting at 0.
+    filing_index: usize,
+    schema_overrides: SchemaOverrides,
+    parser: CoercingLineParser,
+    stats: ParseStats,
+    /// See [FecFile::set_windows_1252_fallback].
+    windows_1252_fallback: bool,
+    /// Whether to keep the raw fields on each [Record].
+    keep_raw: bool,
+    /// Reopens the source from the start, if it is seekable.
+    rewinder: Option<Rewinder>,
+    /// See [FecFile::enable_content_hash].
+    hasher: Option<Arc<Mutex<Sha256>>>,
+    /// See [FecFile::set_source_id].
+    source_id: Option<Arc<str>>,
+    /// See [FecFile::set_mismatch_log_level].
+    mismatch_log_level: log::LevelFilter,
+    /// Whether the summary of schema mismatches has been logged yet.
+    mismatches_logged: bool,
 }
 
+/// Decides whether [FecFile::next_record] returns a record,
+/// see [FecFile::set_record_filter].
+pub type RecordFilter = Box<dyn Fn(&Record) -> bool + Send + Sync>;
+
+/// See [FecFile::rewind].
+type Rewinder = Box<dyn FnMut() -> std::io::Result<Box<dyn Read + Send>> + Send>;
+
 impl FecFile {
     pub fn from_reader(reader: Box<dyn Read + Send>) -> Self {
         Self {
@@ -37,26 +131,161 @@ impl FecFile {
             header: None,
             cover: None,
             sep: None,
+            sep_override: None,
             csv_reader: None,
+            line_filter: None,
+            record_filter: None,
+            error_mode: ErrorMode::default(),
+            errors: Vec::new(),
+            duplicate_mode: DuplicateMode::default(),
+            seen_transaction_ids: HashSet::new(),
+            rows_read: 0,
+            header_len: 0,
+            multi_filing: false,
+            filing_index: 0,
+            schema_overrides: SchemaOverrides::new(),
+            parser: CoercingLineParser::new(),
+            stats: ParseStats::default(),
+            windows_1252_fallback: false,
+            keep_raw: false,
+            rewinder: None,
+            hasher: None,
+            source_id: None,
+            mismatch_log_level: log::LevelFilter::Debug,
+            mismatches_logged: false,
         }
     }
 
+    /// Like [FecFile::from_reader], but the file can be read again
+    /// from the start with [FecFile::rewind].
+    ///
+    /// "The start" is wherever `reader` is positioned when passed in.
+    pub fn from_seekable<R: Read + Seek + Send + 'static>(mut reader: R) -> Result<Self, Error> {
+        let start = reader.stream_position()?;
+        let shared = Arc::new(Mutex::new(reader));
+        let mut fec = Self::from_reader(Box::new(SharedReader(shared.clone())));
+        fec.rewinder = Some(Box::new(move || {
+            shared.lock().unwrap().seek(SeekFrom::Start(start))?;
+            Ok(Box::new(SharedReader(shared.clone())))
+        }));
+        Ok(fec)
+    }
+
+    /// Parse an in-memory .fec file, eg from a test or a WASM caller.
+    pub fn from_bytes(bytes: Vec<u8>) -> Self {
+        Self::from_seekable(Cursor::new(bytes)).expect("a new cursor is at position 0")
+    }
+
+    /// Parse a .fec file piped in on stdin, eg `curl ... | my_tool`.
+    ///
+    /// Stdin can't seek, so [FecFile::rewind] isn't supported.
+    /// Gzipped input is still detected.
+    pub fn from_stdin() -> Self {
+        Self::from_reader(Box::new(std::io::stdin()))
+    }
+
+    /// Configure a FecFile with a [FecFileBuilder].
+    pub fn builder() -> FecFileBuilder {
+        FecFileBuilder::new()
+    }
+
     pub fn from_path(path: &PathBuf) -> Result<Self, Error> {
         let file = File::open(path)?;
-        Ok(Self::from_reader(Box::new(file)))
+        Self::from_seekable(file)
     }
 
-    pub fn from_https(url: &str) -> Result<Self, Error> {
-        log::debug!("fetching {}", url);
-        let resp = ureq::get(url)
-            .set("User-Agent", "Mozilla/5.0")
-            .call()
-            .map_err(|e| Error::HttpError(e.to_string()))?;
-        if resp.status() >= 400 {
-            return Err(Error::HttpError(resp.status_text().to_string()));
+    /// Like [FecFile::from_path], but memory-map the file instead of
+    /// reading it.
+    ///
+    /// This avoids copying the file through a read buffer, which speeds
+    /// up making several passes over the same file with [FecFile::rewind].
+    /// The mapping is owned by the FecFile, and unmapped when it's dropped.
+    #[cfg(feature = "mmap")]
+    pub fn from_mmap(path: &PathBuf) -> Result<Self, Error> {
+        let file = File::open(path)?;
+        // SAFETY: The mapping is only ever read. If another process
+        // truncates the file while it's mapped, reads past the new end
+        // fault, which is the usual caveat of memory-mapping.
+        let mmap = unsafe { memmap2::Mmap::map(&file)? };
+        Self::from_seekable(Cursor::new(mmap))
+    }
+
+    /// Parse the entry `entry_name` of the .zip archive at `path`.
+    ///
+    /// The entry is decompressed into memory first, see
+    /// [crate::zip::read_entry]. To find the entries, see
+    /// [crate::zip::list_fec_entries].
+    #[cfg(feature = "zip")]
+    pub fn from_zip(path: &std::path::Path, entry_name: &str) -> Result<Self, Error> {
+        let bytes = crate::zip::read_entry(path, entry_name)?;
+        Ok(Self::from_bytes(bytes))
+    }
+
+    /// Go back to the start of the file, so the header, cover, and
+    /// records can be read again.
+    ///
+    /// Only works for files created with [FecFile::from_seekable],
+    /// [FecFile::from_path], or [FecFile::from_bytes], otherwise (eg for
+    /// [FecFile::from_stdin]) this fails with an
+    /// [std::io::ErrorKind::Unsupported] error.
+    /// Settings like the line filter are kept, but collected
+    /// [FecFile::errors] are cleared, and any progress callback is dropped.
+    pub fn rewind(&mut self) -> Result<(), Error> {
+        let rewinder = self.rewinder.as_mut().ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "can only rewind seekable sources",
+            )
+        })?;
+        self.reader = Some(rewinder()?);
+        if let Some(hasher) = &self.hasher {
+            hasher.lock().unwrap().reset();
+            let reader = take(&mut self.reader).expect("no reader");
+            self.reader = Some(Box::new(HashingReader::new(reader, hasher.clone())));
         }
-        let reader = resp.into_reader();
-        Ok(Self::from_reader(reader))
+        self.header = None;
+        self.cover = None;
+        self.sep = None;
+        self.csv_reader = None;
+        self.errors.clear();
+        self.seen_transaction_ids.clear();
+        self.stats = ParseStats::default();
+        self.rows_read = 0;
+        self.header_len = 0;
+        self.filing_index = 0;
+        self.mismatches_logged = false;
+        Ok(())
+    }
+
+    /// Stream a .fec file from a http:// or https:// URL.
+    ///
+    /// Redirects are followed (the FEC's bulk downloads redirect to S3).
+    /// Only the response headers are read here, the body is
+    /// streamed lazily as the file is parsed.
+    ///
+    /// If the connection drops partway through, we reconnect and resume
+    /// where we left off, up to 3 times. See [FecFile::from_url_with_retries].
+    #[cfg(feature = "http")]
+    pub fn from_url(url: &str) -> Result<Self, Error> {
+        Self::from_url_with_retries(url, crate::http::DEFAULT_MAX_RETRIES)
+    }
+
+    /// Like [FecFile::from_url], but reconnect up to `max_retries` times.
+    ///
+    /// Reconnecting uses an HTTP Range request to resume from the last byte
+    /// read, with an exponential backoff between attempts.
+    /// Once the retries are exhausted, the error surfaces as an
+    /// [Error::HttpError] from whatever method was reading the file.
+    #[cfg(feature = "http")]
+    pub fn from_url_with_retries(url: &str, max_retries: u32) -> Result<Self, Error> {
+        let reader = crate::http::ResumingReader::open(url, max_retries)?;
+        Ok(Self::from_reader(Box::new(reader)))
+    }
+
+    /// Alias for [FecFile::from_url].
+    #[cfg(feature = "http")]
+    pub fn from_https(url: &str) -> Result<Self, Error> {
+        Self::from_url(url)
     }
 
     pub fn get_header(&mut self) -> Result<&Header, Error> {
@@ -64,58 +293,817 @@ impl FecFile {
         Ok(self.header.as_ref().expect("header should be set"))
     }
 
+    /// Parse the header and return it, dropping the rest of the file.
+    ///
+    /// Only the header lines are read, so this is cheap even for huge files.
+    pub fn into_header(mut self) -> Result<Header, Error> {
+        self.parse_header()?;
+        Ok(self.header.take().expect("header should be set"))
+    }
+
     // TODO: should this not return a reference?
     pub fn get_cover(&mut self) -> Result<&Cover, Error> {
         self.parse_cover()?;
         Ok(self.cover.as_ref().expect("cover should be set"))
     }
 
-    // panics if the header hasn't been parsed yet
-    fn fec_version(&self) -> String {
-        self.header.as_ref().expect("No header").fec_version.clone()
+    /// The separator detected in the header, or None if the header
+    /// hasn't been parsed yet.
+    pub fn separator(&self) -> Option<Sep> {
+        self.sep
+    }
+
+    /// Split the lines of the file with `sep`, instead of detecting the
+    /// separator from the header.
+    ///
+    /// This is an escape hatch for non-standard inputs, eg tab-delimited
+    /// exports from third-party tools, with [Sep::Other]. It has to be set
+    /// before the header is parsed.
+    pub fn set_separator(&mut self, sep: Sep) {
+        self.sep_override = Some(sep);
+    }
+
+    /// The FEC file format version from the header, eg "8.3",
+    /// or None if the header hasn't been parsed yet.
+    pub fn version(&self) -> Option<&str> {
+        self.header.as_ref().map(|h| h.fec_version.as_str())
     }
 
     pub fn next_line(&mut self) -> Option<Result<Vec<String>, Error>> {
-        match self.parse_cover() {
-            Err(e) => return Some(Err(e)),
-            Ok(_) => (),
+        if let Err(e) = self.parse_cover() {
+            return Some(Err(e));
         }
         let p = self.csv_reader.as_mut().expect("No row parser");
-        match p.next_line() {
-            None => return None,
-            Some(Ok(line)) => Some(Ok(line)),
-            Some(Err(e)) => return Some(Err(Error::RecordParseError(e.to_string()))),
+        let mut result = p.next_line()?;
+        self.rows_read += 1;
+        if let Ok(line) = &mut result {
+            self.attach_text_block(line);
         }
+        Some(result)
     }
 
-    pub fn lines(&mut self) -> LineIter {
+    /// Put the free-form text that followed an F99 line into its "text" field.
+    fn attach_text_block(&mut self, line: &mut Vec<String>) {
+        let p = self.csv_reader.as_mut().expect("No row parser");
+        let text = match p.take_text_block() {
+            Some(text) => text,
+            None => return,
+        };
+        let fec_version = self.header.as_ref().expect("No header").schema_version();
+        put_text_in_line(fec_version, &self.schema_overrides, line, text);
+    }
+
+    pub fn lines(&mut self) -> LineIter<'_> {
         LineIter { fec_file: self }
     }
 
+    /// Parse the next itemization line into a [Record].
+    ///
+    /// The header and cover are parsed first if they haven't been already.
+    /// Returns None once there are no more lines.
+    ///
+    /// If a row fails to parse, what happens depends on the [ErrorMode].
+    /// If the file ends partway through the last line, so that it is missing
+    /// fields or fails to parse, its error is [Error::TruncatedFile].
+    pub fn next_record(&mut self) -> Option<Result<Record, Error>> {
+        // Errors in the header or cover are never collected.
+        if let Err(e) = self.parse_cover() {
+            return Some(Err(e));
+        }
+        loop {
+            let Some(line) = self.next_line() else {
+                self.log_mismatches();
+                return None;
+            };
+            let line = match line {
+                Ok(line) if self.multi_filing && is_header_line(&line) => {
+                    match self.start_next_filing(&line) {
+                        Ok(()) => continue,
+                        Err(e) => return Some(Err(e)),
+                    }
+                }
+                Ok(line) if self.passes_line_filter(&line) => line,
+                Ok(_) => continue,
+                Err(e) => match self.handle_row_error(self.blame_truncation(e), &[]) {
+                    Some(e) => return Some(Err(e)),
+                    None => continue,
+                },
+            };
+            let fec_version = self.header.as_ref().expect("No header").schema_version();
+            let overrides = &self.schema_overrides;
+            let result =
+                self.parser
+                    .parse_line_with_overrides(fec_version, overrides, &mut line.iter());
+            let coercion_failures = self.parser.take_coercion_failures();
+            let result = match result {
+                Ok(record) if record.missing_fields > 0 && self.last_line_unterminated() => {
+                    Err(Error::TruncatedFile)
+                }
+                result => result.map_err(|e| self.blame_truncation(e)),
+            };
+            match result {
+                Ok(mut record) => {
+                    if self
+                        .record_filter
+                        .as_ref()
+                        .is_some_and(|keep| !keep(&record))
+                    {
+                        continue;
+                    }
+                    if self.is_duplicate(&record) {
+                        if self.duplicate_mode == DuplicateMode::Skip {
+                            log::debug!("skipping duplicate row {}", self.rows_read - 1);
+                            continue;
+                        }
+                        record.is_duplicate = true;
+                    }
+                    self.stats.add(&record, coercion_failures);
+                    record.row_index = self.rows_read - 1;
+                    record.byte_offset = self.last_line_offset();
+                    record.source_id = self.source_id.clone();
+                    if self.keep_raw {
+                        record.raw = Some(line);
+                    }
+                    return Some(Ok(record));
+                }
+                Err(e) => {
+                    if let Some(e) = self.handle_row_error(e, &line) {
+                        return Some(Err(e));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Skip ahead so the next record read is from itemization row `n`.
+    ///
+    /// Rows are counted from 0 like [Record::row_index], which is the
+    /// index among all the records when there is no line filter and no
+    /// row fails to parse. The skipped rows are only split into fields,
+    /// not parsed into [Record]s, so this is cheap. Combined with
+    /// [Iterator::take] on [FecFile::records], this lets several workers
+    /// each parse one shard of a file.
+    ///
+    /// The cover is parsed first if it hasn't been already.
+    /// If we are already past row `n`, this does nothing.
+    pub fn skip_to(&mut self, n: usize) -> Result<(), Error> {
+        self.parse_cover()?;
+        while self.rows_read < n {
+            match self.next_line() {
+                None => break,
+                Some(Ok(line)) if self.multi_filing && is_header_line(&line) => {
+                    self.start_next_filing(&line)?
+                }
+                Some(Ok(_)) => {}
+                Some(Err(e)) => {
+                    if let Some(e) = self.handle_row_error(e, &[]) {
+                        return Err(e);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Use `schema` for lines with code `schema.code` in files of `version`,
+    /// eg "8.4", instead of the built-in schema.
+    ///
+    /// This also works for line codes that have no built-in schema at all,
+    /// for when the FEC releases a new form before this crate catches up.
+    pub fn register_schema(&mut self, version: &str, schema: RecordSchema) {
+        self.schema_overrides.insert(version, schema);
+    }
+
+    /// Parse lines with code `alias` as if their code were `canonical`,
+    /// eg to write "SA11AI" and "SA11AII" lines to one "SA11" file.
+    ///
+    /// [Record::record_code] is still the code in the file, but
+    /// [Record::schema] has code `canonical`, which is what the writers
+    /// group by. See [SchemaOverrides::insert_alias]. By default,
+    /// every code is kept as-is.
+    pub fn alias_line_code(&mut self, alias: &str, canonical: &str) {
+        self.schema_overrides.insert_alias(alias, canonical);
+    }
+
+    /// Register every schema in the `.json` files of `dir`,
+    /// like [FecFile::register_schema].
+    ///
+    /// See [crate::schemas::load_from_dir] for the format.
+    pub fn register_schemas_from_dir(&mut self, dir: &std::path::Path) -> Result<(), Error> {
+        let loaded = crate::schemas::load_from_dir(dir)?;
+        self.schema_overrides.extend(loaded);
+        Ok(())
+    }
+
+    /// Parse the field `field_name` of `line_code` lines as `typ`,
+    /// instead of the type in its schema.
+    ///
+    /// For example, force a text field that really holds dates to be
+    /// parsed as [ValueType::Date]. Values that don't parse as `typ`
+    /// become nulls. The line code must match exactly, eg "SA11AI".
+    /// For a code passed to [FecFile::alias_line_code], either the code
+    /// in the file or the canonical code works, and the former wins.
+    /// The [Record::schema] of those lines has `typ` for the field too,
+    /// so writers make columns of that type.
+    pub fn set_field_type(&mut self, line_code: &str, field_name: &str, typ: ValueType) {
+        self.parser.set_field_type(line_code, field_name, typ);
+    }
+
+    /// Pass every value of the field `field_name` of `line_code` lines
+    /// through `transform`, after it is parsed to its type.
+    ///
+    /// For example, trim the padding off names or uppercase state codes
+    /// while parsing, instead of in a separate pass over the records.
+    /// The line code must match like in [FecFile::set_field_type].
+    /// Transforms for the same field run in the order they were added.
+    pub fn add_field_transform(
+        &mut self,
+        line_code: &str,
+        field_name: &str,
+        transform: FieldTransform,
+    ) {
+        self.parser
+            .add_field_transform(line_code, field_name, transform);
+    }
+
+    /// Tag every [Record] with where it came from, in [Record::source_id],
+    /// eg the file name or filing ID, so records from many files can be
+    /// merged into one output and still be traced back.
+    pub fn set_source_id(&mut self, source_id: &str) {
+        self.source_id = Some(Arc::from(source_id));
+    }
+
+    /// The ID set with [FecFile::set_source_id], if any.
+    pub fn source_id(&self) -> Option<&str> {
+        self.source_id.as_deref()
+    }
+
+    /// If true, keep the raw fields of each line in [Record::raw],
+    /// eg to audit how values were parsed. Off by default.
+    pub fn set_keep_raw(&mut self, keep_raw: bool) {
+        self.keep_raw = keep_raw;
+    }
+
+    /// The level to log lines with more or fewer values than their schema at.
+    ///
+    /// Rather than a message per line, which floods the logs for files that
+    /// legitimately have trailing columns, one summary is logged when the
+    /// last record has been read, eg
+    /// "lines that didn't match their schema: SA11AI: 3 of 10 had more values".
+    /// [log::LevelFilter::Off] turns the summary off. Defaults to
+    /// [log::LevelFilter::Debug]. The same counts are in [FecFile::stats].
+    pub fn set_mismatch_log_level(&mut self, level: log::LevelFilter) {
+        self.mismatch_log_level = level;
+    }
+
+    fn log_mismatches(&mut self) {
+        if self.mismatches_logged {
+            return;
+        }
+        self.mismatches_logged = true;
+        let Some(level) = self.mismatch_log_level.to_level() else {
+            return;
+        };
+        let mut by_code = self.stats.by_line_code.iter().collect::<Vec<_>>();
+        by_code.sort_by_key(|(code, _)| code.as_str());
+        let mut summaries = Vec::new();
+        for (code, stats) in by_code {
+            if stats.more_fields > 0 {
+                summaries.push(format!(
+                    "{}: {} of {} had more values",
+                    code, stats.more_fields, stats.records
+                ));
+            }
+            if stats.fewer_fields > 0 {
+                summaries.push(format!(
+                    "{}: {} of {} had fewer values",
+                    code, stats.fewer_fields, stats.records
+                ));
+            }
+        }
+        if !summaries.is_empty() {
+            log::log!(
+                level,
+                "lines that didn't match their schema: {}",
+                summaries.join(", ")
+            );
+        }
+    }
+
+    /// If true, fields that aren't valid UTF-8 are decoded as Windows-1252
+    /// (a superset of Latin-1), which is what most non-UTF-8 .fec files use.
+    ///
+    /// Otherwise, lines with invalid UTF-8 fail to parse, see [ErrorMode].
+    /// Off by default, since a file that is mostly UTF-8 with the odd
+    /// corrupt byte would silently get mojibake instead of an error.
+    pub fn set_windows_1252_fallback(&mut self, fallback: bool) {
+        self.windows_1252_fallback = fallback;
+        if let Some(csv_reader) = &mut self.csv_reader {
+            csv_reader.set_windows_1252_fallback(fallback);
+        }
+    }
+
+    /// Count the remaining itemization lines, by line code.
+    ///
+    /// This is much faster than iterating over [FecFile::records], since
+    /// no schemas are looked up and no values are parsed.
+    /// The cover is still parsed first, and the line filter still applies.
+    /// In multi-filing mode, the header lines of later filings are counted
+    /// under "HDR" like any other line.
+    pub fn count_by_line_code(&mut self) -> Result<HashMap<String, u64>, Error> {
+        let mut counts = HashMap::new();
+        while let Some(line) = self.next_line() {
+            let line = line?;
+            if !self.passes_line_filter(&line) {
+                continue;
+            }
+            if let Some(code) = line.into_iter().next() {
+                *counts.entry(code).or_insert(0) += 1;
+            }
+        }
+        Ok(counts)
+    }
+
+    /// The distinct line codes of the itemization lines, in the order
+    /// they first appear.
+    ///
+    /// Like [FecFile::count_by_line_code], no schemas are looked up and no
+    /// values are parsed, and the line filter still applies.
+    /// Since this needs every line, a file that has already been partly
+    /// read is rewound first, and afterwards the file is rewound again so
+    /// the records can still be read. Both need a seekable source, see
+    /// [FecFile::rewind]. Other sources can only be scanned while fresh,
+    /// and the scan uses them up.
+    pub fn scan_line_codes(&mut self) -> Result<Vec<String>, Error> {
+        if self.rows_read > 0 || self.filing_index > 0 {
+            self.rewind()?;
+        }
+        let mut codes = Vec::new();
+        let mut seen = HashSet::new();
+        while let Some(line) = self.next_line() {
+            let line = line?;
+            if !self.passes_line_filter(&line) {
+                continue;
+            }
+            if let Some(code) = line.into_iter().next() {
+                if seen.insert(code.clone()) {
+                    codes.push(code);
+                }
+            }
+        }
+        if self.rewinder.is_some() {
+            self.rewind()?;
+        }
+        Ok(codes)
+    }
+
+    /// If true, files with a version that has no built-in schema at all,
+    /// eg a freshly released "9.0", use the schemas of the latest known
+    /// version instead of failing with [Error::SchemaError].
+    ///
+    /// A warning is logged for each line code that falls back.
+    /// Schemas registered with [FecFile::register_schema] still win.
+    /// Off by default.
+    pub fn set_schema_fallback(&mut self, fallback: bool) {
+        self.schema_overrides.fallback_to_latest = fallback;
+    }
+
+    /// Set how strictly itemization lines must match their schemas.
+    ///
+    /// See [ParseMode]. In [ParseMode::Strict], lines with extra or missing
+    /// values, or values that don't parse as their type, fail to parse,
+    /// and then the [ErrorMode] decides what happens.
+    pub fn set_parse_mode(&mut self, mode: ParseMode) {
+        self.parser.set_mode(mode);
+    }
+
+    /// If true, numeric fields may have a leading "$" and thousands
+    /// separators, eg "$1,234.56", as in some hand-edited or third-party
+    /// files. Otherwise they don't parse, and become nulls.
+    ///
+    /// Only applies in [ParseMode::Lenient]. Off by default.
+    pub fn set_lenient_numbers(&mut self, lenient: bool) {
+        self.parser.set_lenient_numbers(lenient);
+    }
+
+    /// Set what happens when an itemization row fails to parse.
+    ///
+    /// See [ErrorMode]. Errors in the header or cover always fail.
+    pub fn set_error_mode(&mut self, mode: ErrorMode) {
+        self.error_mode = mode;
+    }
+
+    /// Set what happens to records with a transaction ID that was already
+    /// seen in the same filing. See [DuplicateMode].
+    ///
+    /// Tracking the IDs takes memory for every record, so it's off
+    /// by default.
+    pub fn set_duplicate_mode(&mut self, mode: DuplicateMode) {
+        self.duplicate_mode = mode;
+    }
+
+    /// How the records parsed so far deviated from their schemas,
+    /// per line code.
+    ///
+    /// Lines that failed to parse, or were skipped by the line filter,
+    /// aren't counted.
+    pub fn stats(&self) -> &ParseStats {
+        &self.stats
+    }
+
+    /// Parse `raw` as `typ` like the fields of lines with code `line_code`,
+    /// eg for writers that type fields the schema leaves as strings.
+    ///
+    /// A value that doesn't parse is a null, and counted in [FecFile::stats].
+    pub(crate) fn coerce_value(
+        &mut self,
+        line_code: &str,
+        typ: ValueType,
+        raw: Option<&String>,
+    ) -> Value {
+        self.parser.parse_lenient(typ, raw).unwrap_or_else(|_| {
+            self.stats.add_coercion_failure(line_code);
+            typ.null()
+        })
+    }
+
+    /// Parse every remaining record, only to check for problems.
+    ///
+    /// Rows are parsed as in [ErrorMode::Collect], whatever the current
+    /// [ErrorMode] is, and the records themselves are thrown away.
+    /// Errors in the header or cover, or from the source itself,
+    /// can't be skipped over, so those are returned as an Err instead.
+    /// The errors are moved out of [FecFile::errors] into the report.
+    ///
+    /// Call this on a fresh file, otherwise the counts and errors only
+    /// cover the records not yet read, while [ValidationReport::stats]
+    /// is [FecFile::stats], which covers every record.
+    pub fn validate(&mut self) -> Result<ValidationReport, Error> {
+        let error_mode = self.error_mode;
+        self.error_mode = ErrorMode::Collect;
+        let errors_before = self.errors.len();
+        let mut records = 0;
+        let result = self.records().try_for_each(|record| {
+            record?;
+            records += 1;
+            Ok::<(), Error>(())
+        });
+        self.error_mode = error_mode;
+        result?;
+        Ok(ValidationReport {
+            records,
+            errors: self.errors.split_off(errors_before),
+            stats: self.stats.clone(),
+        })
+    }
+
+    /// Compare the number of lines of each line code to the count the
+    /// file declares, returning every line code where they differ,
+    /// sorted by line code.
+    ///
+    /// A mismatch usually means the filing was truncated or malformed.
+    /// Legacy headers declare counts in [Header::schedule_counts], and
+    /// some cover lines in [Cover::schedule_counts]; files that declare
+    /// neither always give an empty list. Only declared line codes are
+    /// checked, and they are compared case-insensitively.
+    ///
+    /// Every line of the filing is counted, whatever the line filter.
+    /// In multi-filing mode, only the lines of the current filing are.
+    /// This reads the rest of the file, so call it on a fresh one.
+    pub fn check_schedule_counts(&mut self) -> Result<Vec<CountMismatch>, Error> {
+        let mut declared = self.get_header()?.schedule_counts.clone();
+        declared.extend(self.get_cover()?.schedule_counts.clone());
+        if declared.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut actual = HashMap::new();
+        while let Some(line) = self.next_line() {
+            let line = line?;
+            if self.multi_filing && is_header_line(&line) {
+                break;
+            }
+            if let Some(code) = line.into_iter().next() {
+                *actual.entry(code.to_uppercase()).or_insert(0) += 1;
+            }
+        }
+        let mut mismatches = Vec::new();
+        for (line_code, declared) in declared {
+            let actual = actual.get(&line_code).copied().unwrap_or(0);
+            if actual != declared {
+                mismatches.push(CountMismatch {
+                    line_code,
+                    declared,
+                    actual,
+                });
+            }
+        }
+        Ok(mismatches)
+    }
+
+    /// Whether `record`'s transaction ID was already seen, remembering it
+    /// if not. Always false in [DuplicateMode::Allow].
+    fn is_duplicate(&mut self, record: &Record) -> bool {
+        if self.duplicate_mode == DuplicateMode::Allow {
+            return false;
+        }
+        match record.transaction_id() {
+            Some(id) => !self.seen_transaction_ids.insert(id.to_string()),
+            None => false,
+        }
+    }
+
+    /// The row errors that have been skipped so far in [ErrorMode::Collect].
+    pub fn errors(&self) -> &[RowError] {
+        &self.errors
+    }
+
+    /// In [ErrorMode::Collect], save the error and return None.
+    /// Otherwise, give the error back to be returned.
+    fn handle_row_error(&mut self, error: Error, line: &[String]) -> Option<Error> {
+        // A broken source isn't the row's fault, and would just fail again.
+        let is_io = matches!(error, Error::IoError(_) | Error::HttpError(_));
+        if self.error_mode == ErrorMode::FailFast || is_io {
+            return Some(error);
+        }
+        log::debug!("skipping row {}: {}", self.rows_read - 1, error);
+        let sep = self.sep.as_ref().expect("No sep").to_byte();
+        let raw = line
+            .iter()
+            .map(|field| field.as_bytes())
+            .collect::<Vec<_>>()
+            .join(&sep);
+        self.errors.push(RowError {
+            row_index: self.rows_read - 1,
+            raw,
+            error,
+        });
+        None
+    }
+
+    /// Only return records whose line code starts with one of `codes`.
+    ///
+    /// For example, `vec!["SA".to_string()]` will keep "SA11AI", "SA17", etc.
+    /// Matching is case-insensitive. Lines that don't match are skipped
+    /// by [FecFile::next_record] without parsing their values.
+    /// This doesn't affect the header or cover, or [FecFile::next_line].
+    pub fn set_line_filter(&mut self, codes: Vec<String>) {
+        self.line_filter = Some(codes);
+    }
+
+    /// Only return records for which `filter` returns true, eg
+    /// contributions over $2,000 or from a given state.
+    ///
+    /// Unlike [FecFile::set_line_filter], this runs after each line is
+    /// parsed, so the line filter is applied first to skip parsing lines
+    /// that can't match. Records that are filtered out don't count towards
+    /// [FecFile::stats] or as seen by the [DuplicateMode].
+    pub fn set_record_filter(&mut self, filter: RecordFilter) {
+        self.record_filter = Some(filter);
+    }
+
+    fn passes_line_filter(&self, line: &[String]) -> bool {
+        let prefixes = match &self.line_filter {
+            None => return true,
+            Some(prefixes) => prefixes,
+        };
+        let code = match line.first() {
+            // Let the parser complain about the empty line.
+            None => return true,
+            Some(code) => code.as_bytes(),
+        };
+        prefixes.iter().any(|prefix| {
+            let prefix = prefix.as_bytes();
+            code.len() >= prefix.len() && code[..prefix.len()].eq_ignore_ascii_case(prefix)
+        })
+    }
+
+    /// Call `callback` periodically with the number of bytes read so far.
+    ///
+    /// Bytes are counted from the underlying source, so for a gzipped
+    /// source this is the compressed size. The total size isn't known
+    /// here (eg for HTTP streams), so that is left up to the caller.
+    ///
+    /// This must be called before the header is parsed.
+    pub fn set_progress_callback(&mut self, callback: ProgressCallback) {
+        assert!(
+            self.header.is_none(),
+            "progress callback must be set before parsing"
+        );
+        let reader = take(&mut self.reader).expect("no reader");
+        self.reader = Some(Box::new(ProgressReader::new(reader, callback)));
+    }
+
+    /// Compute a SHA-256 hash of every byte read from the source.
+    ///
+    /// Get it with [FecFile::content_hash] once every record has been read.
+    /// Like [FecFile::set_progress_callback], this hashes the underlying
+    /// source, so a gzipped source has the hash of the .gz file.
+    ///
+    /// This must be called before the header is parsed.
+    pub fn enable_content_hash(&mut self) {
+        assert!(
+            self.header.is_none(),
+            "content hash must be enabled before parsing"
+        );
+        let hasher = self.hasher.insert(Arc::new(Mutex::new(Sha256::new())));
+        let reader = take(&mut self.reader).expect("no reader");
+        self.reader = Some(Box::new(HashingReader::new(reader, hasher.clone())));
+    }
+
+    /// The SHA-256 of the bytes read so far, as lowercase hex.
+    ///
+    /// Once every record has been read, this is the hash of the whole
+    /// source, so it can be compared to a known hash to detect a truncated
+    /// download, or used to dedupe identical filings.
+    /// None unless [FecFile::enable_content_hash] was called.
+    pub fn content_hash(&self) -> Option<String> {
+        let hasher = self.hasher.as_ref()?.lock().unwrap().clone();
+        let digest = hasher.finalize();
+        Some(digest.iter().map(|b| format!("{:02x}", b)).collect())
+    }
+
+    /// Treat the stream as a sequence of concatenated filings.
+    ///
+    /// Some bulk dumps concatenate many .fec files together. In this mode,
+    /// when [FecFile::next_record] comes across a "HDR" line, the header
+    /// and cover of the next filing are parsed and [FecFile::filing_index]
+    /// is incremented. [FecFile::get_header] and [FecFile::get_cover]
+    /// then return those of the current filing.
+    ///
+    /// Only non-legacy headers (version 6+) are detected, and every
+    /// filing must use the same separator as the first.
+    pub fn set_multi_filing(&mut self, multi_filing: bool) {
+        self.multi_filing = multi_filing;
+    }
+
+    /// The index of the filing that the most recent record came from,
+    /// starting at 0. Always 0 unless [FecFile::set_multi_filing] is on.
+    pub fn filing_index(&self) -> usize {
+        self.filing_index
+    }
+
+    /// Iterate over `(filing_index, record)` pairs.
+    ///
+    /// See [FecFile::set_multi_filing].
+    pub fn filing_records(&mut self) -> FilingRecordIter<'_> {
+        FilingRecordIter { fec_file: self }
+    }
+
+    /// Replace the header and cover with those of the next filing.
+    fn start_next_filing(&mut self, header_line: &[String]) -> Result<(), Error> {
+        let sep = self.sep.expect("No sep");
+        let mut raw = header_line
+            .iter()
+            .map(|field| field.as_bytes())
+            .collect::<Vec<_>>()
+            .join(&sep.to_byte());
+        raw.push(b'\n');
+        let header_parsing = parse_header(
+            &mut Cursor::new(raw),
+            &self.schema_overrides,
+            self.sep_override,
+        )?;
+        if header_parsing.sep != sep {
+            return Err(HeaderParseError::Malformed(
+                "filings in one stream must use the same separator".to_string(),
+            )
+            .into());
+        }
+        log::debug!(
+            "starting filing {} at row {}",
+            self.filing_index + 1,
+            self.rows_read - 1
+        );
+        self.header = Some(header_parsing.header);
+        self.seen_transaction_ids.clear();
+        let p = self.csv_reader.as_mut().expect("No row parser");
+        let mut line = match p.next_line() {
+            None => return Err(CoverParseError::Missing.into()),
+            Some(Ok(record)) => record,
+            Some(Err(e)) => return Err(cover_line_error(e)),
+        };
+        self.attach_text_block(&mut line);
+        self.cover = Some(parse_cover_line(
+            self.header.as_ref().expect("No header"),
+            &self.schema_overrides,
+            &mut line.iter(),
+        )?);
+        self.filing_index += 1;
+        Ok(())
+    }
+
+    /// Iterate over the itemization [Record]s of the file.
+    pub fn records(&mut self) -> RecordIter<'_> {
+        RecordIter { fec_file: self }
+    }
+
+    /// Read all the remaining itemization [Record]s, grouped by line code.
+    ///
+    /// This materializes the whole file in memory, so for big files prefer
+    /// [FecFile::group_sorted], which only holds one group at a time.
+    pub fn into_grouped(mut self) -> Result<HashMap<String, Vec<Record>>, Error> {
+        let mut groups: HashMap<String, Vec<Record>> = HashMap::new();
+        while let Some(record) = self.next_record() {
+            let record = record?;
+            groups
+                .entry(record.record_code.clone())
+                .or_default()
+                .push(record);
+        }
+        Ok(groups)
+    }
+
+    /// Iterate over runs of consecutive itemization [Record]s that share
+    /// a line code, as `(line code, records)` pairs.
+    ///
+    /// Within a filing, records with the same line code are usually next
+    /// to each other, so this groups them without reading the whole file.
+    /// If they aren't, the same line code is yielded again for each run.
+    /// An error partway through a run ends it: the records before the
+    /// error are yielded as a group, then the error.
+    pub fn group_sorted(&mut self) -> GroupedRecordIter<'_> {
+        GroupedRecordIter {
+            fec_file: self,
+            pending: None,
+        }
+    }
+
+    /// Iterate over every `n`th itemization [Record] of the file,
+    /// ie rows 0, n, 2n, etc, for a quick preview of a big file.
+    ///
+    /// The rows in between are skipped as with [FecFile::skip_to],
+    /// without parsing them. The cover is always parsed.
+    ///
+    /// Panics if `n` is 0.
+    pub fn sample_every(&mut self, n: usize) -> SampleIter<'_> {
+        assert!(n > 0, "can't sample every 0th record");
+        SampleIter {
+            fec_file: self,
+            n,
+            next_row: 0,
+        }
+    }
+
     fn parse_header(&mut self) -> Result<(), Error> {
         if self.header.is_some() {
             return Ok(());
         }
-        let reader = self.reader.as_mut().expect("no reader");
-        let header_parsing = parse_header(reader).map_err(Error::HeaderParseError)?;
+        let reader = take(&mut self.reader).expect("no reader");
+        let reader = self.reader.insert(maybe_gunzip(reader)?);
+        let mut counter = CountingReader::new(reader);
+        let header_parsing = parse_header(&mut counter, &self.schema_overrides, self.sep_override)?;
+        self.header_len = counter.bytes_read - header_parsing.unread.len() as u64;
+        if !header_parsing.unread.is_empty() {
+            // Put back the lines that turned out not to be part of the header.
+            let reader = take(&mut self.reader).expect("no reader");
+            let unread = Cursor::new(header_parsing.unread.clone());
+            self.reader = Some(Box::new(unread.chain(reader)));
+        }
         self.header = Some(header_parsing.header.clone());
-        self.sep = Some(header_parsing.sep.clone());
+        self.sep = Some(header_parsing.sep);
         Ok(())
     }
 
+    /// Whether the last line read from the csv_reader ended without a
+    /// newline, ie the file may have been truncated partway through it.
+    fn last_line_unterminated(&self) -> bool {
+        let csv_reader = self.csv_reader.as_ref();
+        csv_reader.is_some_and(|r| r.last_line_unterminated())
+    }
+
+    /// If the line just read was cut off by the end of the file, that is
+    /// the likely cause of `error`, so return [Error::TruncatedFile] instead.
+    fn blame_truncation(&self, error: Error) -> Error {
+        let is_line_error = matches!(error, Error::RecordParseError(_) | Error::SchemaError(..));
+        match is_line_error && self.last_line_unterminated() {
+            true => Error::TruncatedFile,
+            false => error,
+        }
+    }
+
+    /// Where the last line read from the csv_reader starts in the source.
+    fn last_line_offset(&self) -> Option<u64> {
+        let offset = self.csv_reader.as_ref()?.last_offset()?;
+        Some(self.header_len + offset)
+    }
+
     fn parse_cover(&mut self) -> Result<(), Error> {
         if self.cover.is_some() {
             return Ok(());
         }
         self.make_csv_parser()?;
-        let fec_version = &self.fec_version().clone();
         let p = self.csv_reader.as_mut().expect("No row parser");
-        let line = match p.next_line() {
-            None => return Err(Error::CoverParseError("no cover record".to_string())),
+        let mut line = match p.next_line() {
+            None => return Err(CoverParseError::Missing.into()),
             Some(Ok(record)) => record,
-            Some(Err(e)) => return Err(Error::CoverParseError(e.to_string())),
+            Some(Err(e)) => return Err(cover_line_error(e)),
         };
-        self.cover = Some(parse_cover_line(fec_version, &mut line.iter())?);
+        self.attach_text_block(&mut line);
+        self.cover = Some(parse_cover_line(
+            self.header.as_ref().expect("No header"),
+            &self.schema_overrides,
+            &mut line.iter(),
+        )?);
         Ok(())
     }
 
@@ -128,7 +1116,9 @@ impl FecFile {
         if self.csv_reader.is_none() {
             // Hand off the reader ownership to the row parser.
             let reader = take(&mut self.reader).expect("no reader");
-            self.csv_reader = Some(CsvReader::new(reader, sep));
+            let mut csv_reader = CsvReader::new(reader, sep);
+            csv_reader.set_windows_1252_fallback(self.windows_1252_fallback);
+            self.csv_reader = Some(csv_reader);
         }
         Ok(())
     }
@@ -145,3 +1135,133 @@ impl<'a> Iterator for LineIter<'a> {
         self.fec_file.next_line()
     }
 }
+
+/// A line that can't be split into fields is a malformed cover, but
+/// errors from the source itself are passed through as-is.
+pub(crate) fn cover_line_error(e: Error) -> Error {
+    match e {
+        Error::RecordParseError(RecordParseError::Malformed(msg)) => {
+            CoverParseError::Malformed(msg).into()
+        }
+        e => e,
+    }
+}
+
+/// Put the free-form `text` of an F99 line into the line's "text" field,
+/// or at the end if its schema doesn't have one.
+pub(crate) fn put_text_in_line(
+    fec_version: &str,
+    overrides: &SchemaOverrides,
+    line: &mut Vec<String>,
+    text: String,
+) {
+    let index = lookup_schema_with_overrides(overrides, fec_version, &line[0])
+        .ok()
+        .and_then(|schema| schema.fields.iter().position(|f| f.name == "text"));
+    match index {
+        // The values start after the line code.
+        Some(i) => {
+            if line.len() < i + 2 {
+                line.resize(i + 2, String::new());
+            }
+            line[i + 1] = text;
+        }
+        None => line.push(text),
+    }
+}
+
+/// Lets [FecFile::rewind] keep a handle on a source that it has handed off.
+struct SharedReader<R>(Arc<Mutex<R>>);
+
+impl<R: Read> Read for SharedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().read(buf)
+    }
+}
+
+fn is_header_line(line: &[String]) -> bool {
+    line.first()
+        .is_some_and(|code| code.eq_ignore_ascii_case("HDR"))
+}
+
+pub struct RecordIter<'a> {
+    fec_file: &'a mut FecFile,
+}
+
+impl<'a> Iterator for RecordIter<'a> {
+    type Item = Result<Record, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.fec_file.next_record()
+    }
+}
+
+pub struct SampleIter<'a> {
+    fec_file: &'a mut FecFile,
+    n: usize,
+    /// The row to skip to before reading the next record.
+    next_row: usize,
+}
+
+impl<'a> Iterator for SampleIter<'a> {
+    type Item = Result<Record, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Err(e) = self.fec_file.skip_to(self.next_row) {
+            return Some(Err(e));
+        }
+        let record = self.fec_file.next_record()?;
+        if let Ok(record) = &record {
+            self.next_row = record.row_index + self.n;
+        }
+        Some(record)
+    }
+}
+
+pub struct GroupedRecordIter<'a> {
+    fec_file: &'a mut FecFile,
+    /// The first record of the next group, or the error that ended the
+    /// previous one, read while finding the end of the previous one.
+    pending: Option<Result<Record, Error>>,
+}
+
+impl<'a> Iterator for GroupedRecordIter<'a> {
+    type Item = Result<(String, Vec<Record>), Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let first = match self
+            .pending
+            .take()
+            .or_else(|| self.fec_file.next_record())?
+        {
+            Ok(record) => record,
+            Err(e) => return Some(Err(e)),
+        };
+        let code = first.record_code.clone();
+        let mut group = vec![first];
+        while let Some(record) = self.fec_file.next_record() {
+            match record {
+                Ok(record) if record.record_code == code => group.push(record),
+                // Yield the group so far, and the error on the next call.
+                other => {
+                    self.pending = Some(other);
+                    break;
+                }
+            }
+        }
+        Some(Ok((code, group)))
+    }
+}
+
+pub struct FilingRecordIter<'a> {
+    fec_file: &'a mut FecFile,
+}
+
+impl<'a> Iterator for FilingRecordIter<'a> {
+    type Item = Result<(usize, Record), Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let record = self.fec_file.next_record()?;
+        Some(record.map(|r| (self.fec_file.filing_index(), r)))
+    }
+}
@@ -0,0 +1,130 @@
+//! Typed line codes, so consumers can `match` on the forms they know.
+
+use std::fmt;
+
+/// The form or schedule that a line belongs to.
+///
+/// Line codes carry more detail than this, eg "F3XN" is a new Form 3X
+/// and "SA11AI" is a Schedule A for line 11(a)(i). [FormType::from_code]
+/// strips that detail. Lines that are part of a form, like the "F56"
+/// contributions of a Form 5, map to that form.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum FormType {
+    F1,
+    F1M,
+    F2,
+    F3,
+    F3L,
+    F3P,
+    F3PS,
+    F3S,
+    F3X,
+    F3Z,
+    F4,
+    F5,
+    F6,
+    F7,
+    F8,
+    F9,
+    F10,
+    F13,
+    F24,
+    F99,
+    SA,
+    SB,
+    SC,
+    SC1,
+    SC2,
+    SD,
+    SE,
+    SF,
+    SI,
+    SL,
+    H1,
+    H2,
+    H3,
+    H4,
+    H5,
+    H6,
+    Text,
+    /// Any other code, as it appeared in the file.
+    Other(String),
+}
+
+/// Every known form and its code, longest codes first, so that eg
+/// "F3XN" matches F3X before F3.
+const KNOWN: [(&str, FormType); 37] = [
+    ("TEXT", FormType::Text),
+    ("F1M", FormType::F1M),
+    ("F3L", FormType::F3L),
+    ("F3PS", FormType::F3PS),
+    ("F3P", FormType::F3P),
+    ("F3S", FormType::F3S),
+    ("F3X", FormType::F3X),
+    ("F3Z", FormType::F3Z),
+    ("F10", FormType::F10),
+    ("F13", FormType::F13),
+    ("F24", FormType::F24),
+    ("F99", FormType::F99),
+    ("SC1", FormType::SC1),
+    ("SC2", FormType::SC2),
+    ("F1", FormType::F1),
+    ("F2", FormType::F2),
+    ("F3", FormType::F3),
+    ("F4", FormType::F4),
+    ("F5", FormType::F5),
+    ("F6", FormType::F6),
+    ("F7", FormType::F7),
+    ("F8", FormType::F8),
+    ("F9", FormType::F9),
+    ("SA", FormType::SA),
+    ("SB", FormType::SB),
+    ("SC", FormType::SC),
+    ("SD", FormType::SD),
+    ("SE", FormType::SE),
+    ("SF", FormType::SF),
+    ("SI", FormType::SI),
+    ("SL", FormType::SL),
+    ("H1", FormType::H1),
+    ("H2", FormType::H2),
+    ("H3", FormType::H3),
+    ("H4", FormType::H4),
+    ("H5", FormType::H5),
+    ("H6", FormType::H6),
+];
+
+impl FormType {
+    /// Map a line code, eg "SA11AI" or "f3xn", to its form.
+    ///
+    /// Matching ignores case and surrounding whitespace.
+    /// Unknown codes become [FormType::Other].
+    pub fn from_code(code: &str) -> FormType {
+        let upper = code.trim().to_ascii_uppercase();
+        KNOWN
+            .iter()
+            .find(|(prefix, _)| upper.starts_with(prefix))
+            .map(|(_, form)| form.clone())
+            .unwrap_or_else(|| FormType::Other(code.to_string()))
+    }
+
+    /// The code of the form, eg "F3X", or the original code for
+    /// [FormType::Other].
+    pub fn as_str(&self) -> &str {
+        match self {
+            FormType::Other(code) => code,
+            form => {
+                let (code, _) = KNOWN
+                    .iter()
+                    .find(|(_, known)| known == form)
+                    .expect("every known form is in KNOWN");
+                code
+            }
+        }
+    }
+}
+
+impl fmt::Display for FormType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
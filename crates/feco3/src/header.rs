@@ -7,16 +7,19 @@
 //! The header contains
 
 use std::{
+    collections::BTreeMap,
     fmt,
     io::{BufReader, Read},
-    str::{from_utf8, Utf8Error},
+    str::{from_utf8, FromStr},
 };
 
-use crate::{csv::Sep, record::Record, record::Value, schemas::LineParser};
+use crate::{csv::Sep, record::Record, record::Value, schemas::LineParser, Error};
 use bytelines::ByteLines;
+use serde::Serialize;
 use std::result::Result;
 
-use crate::schemas::LiteralLineParser;
+use crate::record::RecordSchema;
+use crate::schemas::{lookup_schema_with_overrides, LiteralLineParser, SchemaOverrides};
 
 /// The header of a FecFile.
 ///
@@ -28,7 +31,7 @@ use crate::schemas::LiteralLineParser;
 /// but currently we only parse this subset.
 /// See the "hdr" section of [mappings.json](mappings.json) to
 /// see where these fields come from.
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, Serialize)]
 pub struct Header {
     /// The version of the FEC file format.
     pub fec_version: String,
@@ -42,59 +45,226 @@ pub struct Header {
     /// If this is an amendment, the number within the chain of amendments.
     /// Starts at 1 (or 2, I need to check), and counts up from there.
     pub report_number: Option<String>,
+    /// How many lines of each line code the file says it has,
+    /// eg {"SA11A1": 139}.
+    ///
+    /// Only legacy headers (before version 3) list these, under
+    /// "Schedule_Counts:". Empty for every other file, though some
+    /// cover lines declare counts instead, see [Cover::schedule_counts].
+    ///
+    /// [Cover::schedule_counts]: crate::Cover::schedule_counts
+    /// See [FecFile::check_schedule_counts](crate::FecFile::check_schedule_counts).
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub schedule_counts: BTreeMap<String, u64>,
+    /// The header exactly as it appeared in the file, see [Header::raw].
+    #[serde(skip)]
+    raw: Vec<u8>,
 }
 
-#[derive(Debug, Clone)]
-pub struct HeaderParseError {
-    pub message: String,
-    pub read: Vec<u8>,
+/// A parsed FEC file format version, eg "8.3" or "P3.2".
+///
+/// Versions compare numerically, so "8.10" > "8.3".
+/// Versions starting with "P" are for paper filings, which are numbered
+/// separately from the electronic ones; every paper version sorts after
+/// every electronic version, which isn't meaningful, so only compare
+/// versions where [FecVersion::paper] is the same.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FecVersion {
+    pub paper: bool,
+    pub major: u32,
+    pub minor: u32,
+}
+
+impl FromStr for FecVersion {
+    type Err = HeaderParseError;
+
+    /// Parse eg "8.3", "P2.6", "3.00", or just "3".
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || HeaderParseError::UnknownVersion(s.to_string());
+        let trimmed = s.trim();
+        let (paper, rest) = match trimmed.strip_prefix(['P', 'p']) {
+            Some(rest) => (true, rest),
+            None => (false, trimmed),
+        };
+        let (major, minor) = match rest.split_once('.') {
+            Some((major, minor)) => (major, minor),
+            None => (rest, "0"),
+        };
+        Ok(Self {
+            paper,
+            major: major.parse().map_err(|_| err())?,
+            minor: minor.parse().map_err(|_| err())?,
+        })
+    }
 }
 
-impl fmt::Display for HeaderParseError {
+impl fmt::Display for FecVersion {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(
-            f,
-            "HeaderParseError: {} (read: '{}')",
-            self.message,
-            String::from_utf8_lossy(&self.read)
-        )
+        let prefix = if self.paper { "P" } else { "" };
+        write!(f, "{}{}.{}", prefix, self.major, self.minor)
     }
 }
 
-impl std::error::Error for HeaderParseError {}
+/// The [Header::fec_version] of files that have no header at all.
+pub const UNKNOWN_VERSION: &str = "unknown";
+
+/// The version whose schemas are used to parse files with no header.
+///
+/// Header-less files are all from the earliest days of electronic filing.
+pub const HEADERLESS_VERSION: &str = "3.00";
+
+impl Header {
+    /// The version to look up schemas with.
+    ///
+    /// This is [Header::fec_version], unless the file had no header,
+    /// in which case we guess [HEADERLESS_VERSION].
+    pub fn schema_version(&self) -> &str {
+        if self.fec_version == UNKNOWN_VERSION {
+            HEADERLESS_VERSION
+        } else {
+            &self.fec_version
+        }
+    }
+
+    /// The bytes of the header line (or lines, for legacy headers) exactly
+    /// as they appeared in the file, without any UTF-8 byte order mark
+    /// or the line ending after it.
+    ///
+    /// Re-serializing the parsed fields won't always give these back,
+    /// eg if the header had fields that we don't parse.
+    /// This is empty for files with no header.
+    pub fn raw(&self) -> &[u8] {
+        &self.raw
+    }
+
+    /// Parse [Header::fec_version] into a comparable [FecVersion].
+    pub fn version(&self) -> Result<FecVersion, HeaderParseError> {
+        self.fec_version.parse()
+    }
+}
+
+/// The ways that parsing a [Header] can fail.
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum HeaderParseError {
+    /// The source ended before the header was complete.
+    #[error("HeaderParseError: file ended before the header was complete")]
+    TooShort,
+    /// The FEC version was empty or isn't one we know about.
+    #[error("HeaderParseError: unknown fec version '{0}'")]
+    UnknownVersion(String),
+    /// A required field of the header was missing or empty.
+    #[error("HeaderParseError: missing field '{0}'")]
+    MissingField(&'static str),
+    /// The header line isn't split by any separator we recognize.
+    #[error("HeaderParseError: no recognized delimiter in header line")]
+    InvalidDelimiter,
+    /// The header was there, but malformed in some other way.
+    #[error("HeaderParseError: {0}")]
+    Malformed(String),
+    /// The underlying source failed while we were reading the header.
+    #[error("HeaderParseError: error reading header: {0}")]
+    Io(String),
+}
 
 #[derive(Debug, Clone)]
 pub struct HeaderParsing {
     pub header: Header,
     pub sep: Sep,
+    /// Bytes that were read while looking for the header, but aren't part
+    /// of it. These need to be parsed as the first lines of the body.
+    pub unread: Vec<u8>,
 }
 
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
 type Lines<R> = bytelines::ByteLinesIter<BufReader<R>>;
 
 /// Read from src and parse the header.
-pub fn parse_header(src: &mut impl Read) -> Result<HeaderParsing, HeaderParseError> {
+///
+/// `overrides` are used to look up the schema of the header line.
+///
+/// If `sep` is given, it is used instead of detecting the separator.
+pub fn parse_header(
+    src: &mut impl Read,
+    overrides: &SchemaOverrides,
+    sep: Option<Sep>,
+) -> Result<HeaderParsing, HeaderParseError> {
+    let mut recording = RecordingReader {
+        inner: src,
+        bytes: Vec::new(),
+    };
     // Only buffer one character at a time so that we don't over-consume
     // the src. As soon as we see every line of the header, we want to stop
     // reading so the rest of src can be used by the RowsParser.
-    let mut lines = ByteLines::new(BufReader::with_capacity(1, src)).into_iter();
+    let mut lines = ByteLines::new(BufReader::with_capacity(1, &mut recording)).into_iter();
     let mut read_bytes = Vec::new();
-    let first_line = next_line(&mut read_bytes, &mut lines).map_err(|e| HeaderParseError {
-        message: e.to_string(),
-        read: read_bytes.clone(),
-    })?;
+    let first_line = next_line(&mut read_bytes, &mut lines)?;
+    // Files saved on Windows sometimes start with a UTF-8 byte order mark.
+    let first_line = match first_line.strip_prefix(UTF8_BOM) {
+        Some(rest) => {
+            log::debug!("skipping UTF-8 byte order mark");
+            rest.to_vec()
+        }
+        None => first_line,
+    };
 
     // If the first line contains "/*", its a legacy header.
-    let header;
-    if byte_slice_contains(&first_line, b"/*") {
-        header = parse_legacy_header(&mut lines, &mut read_bytes)
+    let mut header = if byte_slice_contains(&first_line, b"/*") {
+        parse_legacy_header(&mut lines, &mut read_bytes)
+    } else if is_headerless(&first_line, sep) {
+        parse_headerless(first_line, sep)
     } else {
-        header = parse_nonlegacy_header(&first_line)
+        parse_nonlegacy_header(&first_line, overrides, sep)
+    };
+    drop(lines);
+    if let Ok(parsing) = &mut header {
+        // Legacy headers are always comma separated, but the body of an
+        // export with a custom separator might not be.
+        if let Some(sep) = sep {
+            parsing.sep = sep;
+        }
+        if parsing.unread.is_empty() {
+            parsing.header.raw = trim_raw_header(recording.bytes);
+        }
     }
-    log::debug!("Parsed header: {:?}", header);
-    header.map_err(|e| HeaderParseError {
-        message: e,
-        read: read_bytes.clone(),
-    })
+    match &header {
+        Ok(h) => log::debug!("Parsed header: {:?}", h),
+        Err(e) => log::debug!(
+            "Failed to parse header: {} (read: '{}')",
+            e,
+            byte_slice_to_string(&read_bytes)
+        ),
+    }
+    header
+}
+
+/// Keeps a copy of everything read through it.
+struct RecordingReader<'a, R: Read> {
+    inner: &'a mut R,
+    bytes: Vec<u8>,
+}
+
+impl<'a, R: Read> Read for RecordingReader<'a, R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.bytes.extend_from_slice(&buf[..n]);
+        Ok(n)
+    }
+}
+
+/// Strip the byte order mark and final line ending from the bytes
+/// that were read for the header.
+fn trim_raw_header(mut raw: Vec<u8>) -> Vec<u8> {
+    if raw.starts_with(UTF8_BOM) {
+        raw.drain(..UTF8_BOM.len());
+    }
+    if raw.ends_with(b"\n") {
+        raw.pop();
+        if raw.ends_with(b"\r") {
+            raw.pop();
+        }
+    }
+    raw
 }
 
 // /* Header
@@ -118,13 +288,14 @@ pub fn parse_header(src: &mut impl Read) -> Result<HeaderParsing, HeaderParseErr
 fn parse_legacy_header(
     lines: &mut Lines<impl Read>,
     read_bytes: &mut Vec<u8>,
-) -> Result<HeaderParsing, String> {
+) -> Result<HeaderParsing, HeaderParseError> {
     log::debug!("parsing legacy header");
     // read from lines until we hit another "/*" or we've read 100 lines,
     // at which point we error
     let mut header = Header::default();
     let mut num_lines = 0;
     let max_lines = 100;
+    let mut in_schedule_counts = false;
     loop {
         let line_bytes = next_line(read_bytes, lines)?;
         if byte_slice_contains(&line_bytes, b"/*") {
@@ -132,15 +303,25 @@ fn parse_legacy_header(
         }
         num_lines += 1;
         if num_lines > max_lines {
-            return Err(format!("more than {} lines in header", max_lines));
+            return Err(HeaderParseError::Malformed(format!(
+                "more than {} lines in header",
+                max_lines
+            )));
         }
         let line = byte_slice_to_string(&line_bytes);
-        // TODO: parse the schedule counts like in
+        // The schedule counts are the last lines, eg "SA11A1    = 00139", like in
         // https://github.com/esonderegger/fecfile/blob/a5ad9af6fc3b408acaf386871e608085f374441e/fecfile/fecparser.py#L134
         if line.to_lowercase().contains("schedule_counts") {
+            in_schedule_counts = true;
             continue;
         }
         let (key, value) = parse_legacy_kv(&line)?;
+        if in_schedule_counts {
+            if let Ok(count) = value.parse() {
+                header.schedule_counts.insert(key.to_uppercase(), count);
+                continue;
+            }
+        }
         match key.to_lowercase().as_str() {
             "fec_ver_#" => header.fec_version = value,
             "soft_name" => header.software_name = value,
@@ -154,25 +335,29 @@ fn parse_legacy_header(
         }
     }
     // Make sure we've found all the required fields.
-    if header.fec_version == "" {
-        return Err("missing FEC_Ver_#".to_string());
+    if header.fec_version.is_empty() {
+        return Err(HeaderParseError::MissingField("FEC_Ver_#"));
     }
-    if header.software_name == "" {
-        return Err("missing Soft_Name".to_string());
+    if header.software_name.is_empty() {
+        return Err(HeaderParseError::MissingField("Soft_Name"));
     }
     if header.software_version.is_none() {
-        return Err("missing Soft_Ver#".to_string());
+        return Err(HeaderParseError::MissingField("Soft_Ver#"));
     }
     Ok(HeaderParsing {
         header,
         sep: Sep::Comma,
+        unread: Vec::new(),
     })
 }
 
-fn parse_legacy_kv(line: &str) -> std::result::Result<(String, String), String> {
+fn parse_legacy_kv(line: &str) -> Result<(String, String), HeaderParseError> {
     let parts = line.split('=').collect::<Vec<&str>>();
     if parts.len() != 2 {
-        return Err(format!("more than one '=' in header k=v line: {:?}", line));
+        return Err(HeaderParseError::Malformed(format!(
+            "expected exactly one '=' in header k=v line: {:?}",
+            line
+        )));
     }
     let key = parts[0].trim().to_string();
     let value = parts[1].trim().to_string();
@@ -188,56 +373,192 @@ fn parse_legacy_kv(line: &str) -> std::result::Result<(String, String), String>
 /// "HDRFEC8.3NGP8"
 /// or
 /// "HDR8.3NGP8"
-fn parse_nonlegacy_header(line: &Vec<u8>) -> Result<HeaderParsing, String> {
+///
+/// Files before version 6 are comma separated instead, and may quote
+/// their fields, eg `"HDR","FEC","5.00","FECfile"`.
+///
+/// Which position holds which field depends on the version, eg paper
+/// versions have no "FEC" field, and versions 3 to 5 have a name delimiter
+/// before the report ID. So the fields are matched up using the schema of
+/// the HDR line for the version, lined up on where the version was found.
+fn parse_nonlegacy_header(
+    line: &[u8],
+    overrides: &SchemaOverrides,
+    sep: Option<Sep>,
+) -> Result<HeaderParsing, HeaderParseError> {
     log::debug!("parsing non-legacy header");
     let mut header = Header::default();
-    let sep = Sep::detect(line);
+    let sep = sep.unwrap_or_else(|| Sep::detect(line));
     log::debug!("separator: {:?}", sep);
-    let parts: Result<Vec<&str>, Utf8Error> =
-        line.split(|c| *c == sep.to_byte()).map(from_utf8).collect();
-    let parts = parts.map_err(|e| e.to_string())?;
+    let mut parts = split_header_line(line, &sep)?;
 
     if parts.len() < 2 {
-        return Err(format!("less than 2 parts in header: {:?}", parts));
+        return Err(HeaderParseError::InvalidDelimiter);
     }
-    let version = match parts[1] {
-        "FEC" => {
-            if parts.len() < 3 {
-                return Err(format!("less than 3 parts in header: {:?}", parts));
-            }
-            parts[2]
-        }
-        _ => parts[1],
+    let (index, version) = find_version(&parts)?;
+    let version = version.to_string();
+    let to_header_error = |e| match e {
+        Error::SchemaError(v, _) => HeaderParseError::UnknownVersion(v),
+        e => HeaderParseError::Malformed(e.to_string()),
     };
-    if version.is_empty() {
-        return Err(format!("empty version in header: {:?}", parts));
-    }
-    let string_parts = parts.iter().map(|s| s.to_string()).collect::<Vec<String>>();
+    let schema =
+        lookup_schema_with_overrides(overrides, &version, &parts[0]).map_err(to_header_error)?;
+    align_header_parts(&mut parts, index, &schema);
     let record = LiteralLineParser
-        .parse_line(version, &mut string_parts.iter())
-        .map_err(|e| e.to_string())?;
-    header.fec_version = version.to_string();
+        .parse_line_with_overrides(&version, overrides, &mut parts.iter())
+        .map_err(to_header_error)?;
     header.software_name = get_string_value_strict(&record, "soft_name")?
         .as_ref()
-        .ok_or("software name is empty")?
+        .ok_or(HeaderParseError::MissingField("soft_name"))?
         .clone();
-    header.software_version = get_string_value_strict(&record, "soft_ver")?.clone();
-    header.report_id = get_string_value_strict(&record, "report_id")?.clone();
-    header.report_number = get_string_value_strict(&record, "report_number")?.clone();
-    Ok(HeaderParsing { header, sep })
+    header.software_version = get_optional_string_value(&record, "soft_ver")?;
+    header.report_id = get_optional_string_value(&record, "report_id")?;
+    header.report_number = get_optional_string_value(&record, "report_number")?;
+    header.fec_version = version;
+    Ok(HeaderParsing {
+        header,
+        sep,
+        unread: Vec::new(),
+    })
+}
+
+/// Some of the earliest filings skip the header and start right in on
+/// the form lines, eg `F3N,C00101766,...`.
+fn is_headerless(line: &[u8], sep: Option<Sep>) -> bool {
+    let has_sep = match sep {
+        Some(sep) => line.contains(&sep.to_byte()),
+        None => line.contains(&b',') || line.contains(&Sep::Ascii28.to_byte()),
+    };
+    let trimmed = line.trim_ascii_start();
+    let first = trimmed.strip_prefix(b"\"").unwrap_or(trimmed);
+    let is_hdr = first.len() >= 3 && first[..3].eq_ignore_ascii_case(b"HDR");
+    has_sep && !is_hdr
+}
+
+fn parse_headerless(
+    first_line: Vec<u8>,
+    sep: Option<Sep>,
+) -> Result<HeaderParsing, HeaderParseError> {
+    log::warn!(
+        "no header found, assuming version {} for the schemas",
+        HEADERLESS_VERSION
+    );
+    let header = Header {
+        fec_version: UNKNOWN_VERSION.to_string(),
+        ..Default::default()
+    };
+    let sep = sep.unwrap_or_else(|| Sep::detect(&first_line));
+    let mut unread = first_line;
+    unread.push(b'\n');
+    Ok(HeaderParsing {
+        header,
+        sep,
+        unread,
+    })
+}
+
+/// Split the header line into trimmed fields.
+fn split_header_line(line: &[u8], sep: &Sep) -> Result<Vec<String>, HeaderParseError> {
+    let parts: Vec<&[u8]> = match sep {
+        Sep::Ascii28 | Sep::Other(_) => line.split(|c| *c == sep.to_byte()).collect(),
+        // Comma separated files can quote fields, so let the csv crate
+        // deal with that.
+        Sep::Comma => {
+            let mut reader = csv::ReaderBuilder::new()
+                .has_headers(false)
+                .flexible(true)
+                .from_reader(line);
+            let mut record = csv::ByteRecord::new();
+            reader
+                .read_byte_record(&mut record)
+                .map_err(|e| HeaderParseError::Malformed(e.to_string()))?;
+            return record.iter().map(to_trimmed_string).collect();
+        }
+    };
+    parts.into_iter().map(to_trimmed_string).collect()
+}
+
+fn to_trimmed_string(bytes: &[u8]) -> Result<String, HeaderParseError> {
+    from_utf8(bytes)
+        .map(|s| s.trim().to_string())
+        .map_err(|e| HeaderParseError::Malformed(e.to_string()))
+}
+
+/// Find the FEC version in the fields of a header line,
+/// and the index of the field it's in.
+///
+/// It's usually the 3rd field (after "HDR" and "FEC"), or the 2nd field
+/// if "FEC" is missing. Some ancient files have it elsewhere,
+/// so if it's not in either place, we take the first field that looks
+/// like a version, eg "3.00" or "P2.6".
+fn find_version(parts: &[String]) -> Result<(usize, &str), HeaderParseError> {
+    let expected_index = match parts[1].as_str() {
+        "FEC" if parts.len() > 2 => 2,
+        "FEC" => return Err(HeaderParseError::MissingField("fec_version")),
+        _ => 1,
+    };
+    let expected = parts[expected_index].as_str();
+    if VERSION_REGEX.is_match(expected) {
+        return Ok((expected_index, expected));
+    }
+    match parts
+        .iter()
+        .enumerate()
+        .skip(1)
+        .find(|(_, p)| VERSION_REGEX.is_match(p))
+    {
+        Some((index, version)) => {
+            log::warn!(
+                "version not in expected position in header, guessing '{}' from {:?}",
+                version,
+                parts
+            );
+            Ok((index, version))
+        }
+        None => Err(HeaderParseError::UnknownVersion(expected.to_string())),
+    }
+}
+
+/// Shift the fields of a header line so that the version, found at
+/// `version_index`, is where `schema` expects it, and drop any fields
+/// past the end of the schema.
+///
+/// For example, "HDR8.3NGP8" leaves out the "FEC" that the
+/// schema has before the version, so an empty field is put in its place.
+fn align_header_parts(parts: &mut Vec<String>, version_index: usize, schema: &RecordSchema) {
+    // The line code isn't one of the schema's fields.
+    let expected_index = match schema.fields.iter().position(|f| f.name == "fec_version") {
+        Some(i) => i + 1,
+        None => return,
+    };
+    if version_index < expected_index {
+        let missing = expected_index - version_index;
+        parts.splice(1..1, std::iter::repeat_n(String::new(), missing));
+    } else if version_index > expected_index {
+        let extra = parts.drain(1..1 + version_index - expected_index);
+        log::debug!("ignoring unexpected header fields {:?}", extra.as_slice());
+    }
+    if parts.len() > schema.fields.len() + 1 {
+        let extra = parts.drain(schema.fields.len() + 1..);
+        log::debug!("ignoring extra header fields {:?}", extra.as_slice());
+    }
+}
+
+lazy_static! {
+    static ref VERSION_REGEX: regex::Regex = regex::Regex::new(r"^P?\d+\.\d+$").unwrap();
 }
 
 ///Get the next line, return an error if we can't.
 fn next_line(
     read_bytes: &mut Vec<u8>,
     lines: &mut Lines<impl Read>,
-) -> Result<Vec<u8>, &'static str> {
+) -> Result<Vec<u8>, HeaderParseError> {
     let line = match lines.next() {
-        None => return Err("unexpected end of file"),
+        None => return Err(HeaderParseError::TooShort),
         Some(Ok(line)) => line,
-        Some(Err(_e)) => return Err("error reading line"),
+        Some(Err(e)) => return Err(HeaderParseError::Io(e.to_string())),
     };
-    if read_bytes.len() > 0 {
+    if !read_bytes.is_empty() {
         read_bytes.push(b'\n');
     }
     read_bytes.extend_from_slice(&line);
@@ -254,18 +575,31 @@ fn byte_slice_to_string(bytes: &[u8]) -> String {
     String::from_utf8_lossy(bytes).to_string()
 }
 
+/// Like [get_string_value_strict], but a field the line is too short to
+/// have is None, rather than an error.
+fn get_optional_string_value(
+    record: &Record,
+    key: &'static str,
+) -> Result<Option<String>, HeaderParseError> {
+    match get_string_value_strict(record, key) {
+        Ok(value) => Ok(value.clone()),
+        Err(HeaderParseError::MissingField(_)) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
 fn get_string_value_strict<'a>(
     record: &'a Record,
-    key: &str,
-) -> Result<&'a Option<String>, String> {
+    key: &'static str,
+) -> Result<&'a Option<String>, HeaderParseError> {
     let value = record
         .get_value(key)
-        .ok_or(format!("missing field '{}' in record {:?}", key, record))?;
+        .ok_or(HeaderParseError::MissingField(key))?;
     match value {
         Value::String(option_string) => Ok(option_string),
-        _ => Err(format!(
+        _ => Err(HeaderParseError::Malformed(format!(
             "expected Value::String, got {:?} for field '{}'",
             value, key
-        )),
+        ))),
     }
 }
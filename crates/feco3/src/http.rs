@@ -0,0 +1,123 @@
+//! Stream .fec files over HTTP, resuming after dropped connections.
+//!
+//! Bulk files from the FEC can be hundreds of MB, and connections
+//! sometimes drop partway through. Instead of restarting the whole parse,
+//! we reconnect with a `Range: bytes=N-` request and carry on from
+//! the last byte we successfully read.
+
+use std::io::{self, Read};
+use std::thread::sleep;
+use std::time::Duration;
+
+use crate::Error;
+
+/// How many redirects to follow when fetching a .fec file.
+const MAX_REDIRECTS: u32 = 10;
+
+/// How many times to reconnect after a dropped connection, by default.
+pub const DEFAULT_MAX_RETRIES: u32 = 3;
+
+/// How long to wait before the first reconnect. Doubles after each attempt.
+const INITIAL_BACKOFF: Duration = Duration::from_millis(250);
+
+/// A reader over the body of an HTTP response, that reconnects
+/// using Range requests if the connection fails.
+pub struct ResumingReader {
+    agent: ureq::Agent,
+    url: String,
+    body: Box<dyn Read + Send>,
+    /// How many bytes of the body we've read so far.
+    offset: u64,
+    max_retries: u32,
+}
+
+impl ResumingReader {
+    /// Send the initial request. Only the response headers are read.
+    pub fn open(url: &str, max_retries: u32) -> Result<Self, Error> {
+        log::debug!("fetching {}", url);
+        let agent = ureq::AgentBuilder::new().redirects(MAX_REDIRECTS).build();
+        let resp = request(&agent, url, None)?;
+        Ok(Self {
+            agent,
+            url: url.to_string(),
+            body: resp.into_reader(),
+            offset: 0,
+            max_retries,
+        })
+    }
+
+    /// Re-request the body, starting at `self.offset`.
+    fn reconnect(&mut self) -> Result<(), Error> {
+        let resp = request(&self.agent, &self.url, Some(self.offset))?;
+        let partial = resp.status() == 206;
+        let mut body = resp.into_reader();
+        if !partial {
+            // The server ignored the Range header and sent everything again.
+            log::debug!(
+                "server doesn't support ranges, skipping {} bytes",
+                self.offset
+            );
+            let skipped = io::copy(&mut (&mut body).take(self.offset), &mut io::sink())?;
+            if skipped != self.offset {
+                return Err(Error::HttpError(
+                    "body was shorter on reconnect".to_string(),
+                ));
+            }
+        }
+        self.body = body;
+        Ok(())
+    }
+}
+
+impl Read for ResumingReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut attempt = 0;
+        loop {
+            let mut err = match self.body.read(buf) {
+                Ok(n) => {
+                    self.offset += n as u64;
+                    return Ok(n);
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => Error::IoError(e),
+            };
+            // Keep reconnecting until it works or we run out of attempts.
+            loop {
+                if attempt >= self.max_retries {
+                    let msg = format!(
+                        "gave up after {} retries at byte {}: {}",
+                        attempt, self.offset, err
+                    );
+                    return Err(io::Error::other(Error::HttpError(msg)));
+                }
+                let backoff = INITIAL_BACKOFF * 2u32.pow(attempt);
+                log::warn!(
+                    "reading {} failed at byte {} ({}), retrying in {:?}",
+                    self.url,
+                    self.offset,
+                    err,
+                    backoff
+                );
+                sleep(backoff);
+                attempt += 1;
+                match self.reconnect() {
+                    Ok(()) => break,
+                    Err(e) => err = e,
+                }
+            }
+        }
+    }
+}
+
+fn request(agent: &ureq::Agent, url: &str, start: Option<u64>) -> Result<ureq::Response, Error> {
+    let mut req = agent.get(url).set("User-Agent", "Mozilla/5.0");
+    if let Some(start) = start {
+        req = req.set("Range", &format!("bytes={}-", start));
+    }
+    req.call().map_err(|e| match e {
+        ureq::Error::Status(code, resp) => {
+            Error::HttpError(format!("{} {}", code, resp.status_text()))
+        }
+        e => Error::HttpError(e.to_string()),
+    })
+}
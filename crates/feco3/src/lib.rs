@@ -6,10 +6,12 @@
 //!
 //! The parser takes a byte stream as input, which makes it flexible enough
 //! to parse files from a variety of sources, including local files, HTTP
-//! streams, or a custom source.
+//! streams, or a custom source. Gzipped sources are detected and
+//! decompressed automatically.
 //!
 //! FECo3 includes a framework for writing the parsed data. Currently,
-//! writing to a directory of .csvs or .parquet files are built in,
+//! writing to a directory of .csvs or .parquet files, or to a single
+//! newline-delimited JSON stream or JSON array, are built in,
 //! but the framework is designed to be extensible to other formats.
 //!
 //! There are bindings for python available
@@ -18,18 +20,58 @@
 #[macro_use]
 extern crate lazy_static;
 
+#[cfg(feature = "tokio")]
+mod async_fec;
+mod builder;
 mod cover;
 mod csv;
+mod decompress;
+mod diff;
 mod fec;
+mod form;
 mod header;
+#[cfg(feature = "http")]
+mod http;
+mod progress;
 pub mod record;
-mod schemas;
+pub mod schemas;
+mod stats;
 pub mod writers;
+#[cfg(feature = "zip")]
+pub mod zip;
 
+#[cfg(feature = "tokio")]
+pub use crate::async_fec::AsyncFecFile;
+pub use crate::builder::FecFileBuilder;
+pub use crate::cover::parse_cover_from_reader;
 pub use crate::cover::Cover;
+pub use crate::cover::CoverParseError;
+pub use crate::csv::Sep;
+pub use crate::diff::diff_filings;
+pub use crate::diff::FieldChange;
+pub use crate::diff::FilingDiff;
+pub use crate::diff::RecordChange;
+pub use crate::fec::DuplicateMode;
+pub use crate::fec::ErrorMode;
 pub use crate::fec::FecFile;
+pub use crate::fec::FilingRecordIter;
+pub use crate::fec::GroupedRecordIter;
 pub use crate::fec::LineIter;
+pub use crate::fec::RecordFilter;
+pub use crate::fec::RecordIter;
+pub use crate::fec::RowError;
+pub use crate::fec::SampleIter;
+pub use crate::form::FormType;
+pub use crate::header::FecVersion;
 pub use crate::header::Header;
+pub use crate::header::HeaderParseError;
+pub use crate::progress::ProgressCallback;
+pub use crate::record::RecordParseError;
+pub use crate::schemas::ParseMode;
+pub use crate::stats::CountMismatch;
+pub use crate::stats::LineCodeStats;
+pub use crate::stats::ParseStats;
+pub use crate::stats::ValidationReport;
 
 /// The error type for this crate.
 #[derive(thiserror::Error, Debug)]
@@ -40,16 +82,22 @@ pub enum Error {
 
     #[allow(missing_docs)]
     #[error("Failed to parse cover line: {0}")]
-    CoverParseError(String),
+    CoverParseError(#[from] cover::CoverParseError),
 
     #[allow(missing_docs)]
     #[error("Failed to parse record: {0}")]
-    RecordParseError(String),
+    RecordParseError(#[from] record::RecordParseError),
 
     #[allow(missing_docs)]
     #[error("Failed to find schema for fec version {0} and line code {1}")]
     SchemaError(String, String),
 
+    /// Two line codes are aliased to a canonical code with no schema of
+    /// its own, but have different fields, so their records can't share
+    /// one output. See [schemas::SchemaOverrides::insert_alias].
+    #[error("Line codes {0} and {1} are both aliased to {2}, but have different fields")]
+    AliasConflict(String, String, String),
+
     #[allow(missing_docs)]
     #[error(transparent)]
     IoError(#[from] std::io::Error),
@@ -57,4 +105,9 @@ pub enum Error {
     #[allow(missing_docs)]
     #[error("Http error: {0}")]
     HttpError(String),
+
+    /// The file ends partway through a line, eg because a download
+    /// was cut off, rather than the line itself being corrupt.
+    #[error("The file ends in the middle of a line, it may be truncated")]
+    TruncatedFile,
 }
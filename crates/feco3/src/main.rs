@@ -1,35 +1,67 @@
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 
 use clap_verbosity_flag::Verbosity;
 
-use clap::{Parser, ValueEnum};
+use clap::{Parser, Subcommand};
 
 use feco3::writers::csv::CSVProcessor;
+use feco3::writers::feather::FeatherProcessor;
 use feco3::writers::parquet::ParquetProcessor;
 use feco3::FecFile;
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)] // Read from `Cargo.toml`
 struct Cli {
-    /// File path, or ":stdin:" to read from stdin
-    input: String,
-
-    /// Output directory
-    #[arg(long, short, default_value = "out")]
-    output: PathBuf,
-
-    /// Writer to use
-    #[arg(long, short, value_enum, default_value = "parquet")]
-    writer: Writer,
+    #[command(subcommand)]
+    command: Command,
 
     #[command(flatten)]
     verbose: Verbosity,
 }
 
-#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug)]
-enum Writer {
-    Parquet,
-    CSV,
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Write the itemizations to a directory of .csv files, one per line code
+    ToCsv {
+        /// File path, http(s) URL, or "-" (or ":stdin:") to read from stdin
+        input: String,
+        /// Output directory
+        out_dir: PathBuf,
+    },
+    /// Write the itemizations to a directory of .parquet files, one per line code
+    ToParquet {
+        /// File path, http(s) URL, or "-" (or ":stdin:") to read from stdin
+        input: String,
+        /// Output directory
+        out_dir: PathBuf,
+    },
+    /// Write the itemizations to a directory of .feather files, one per line code
+    ToFeather {
+        /// File path, http(s) URL, or "-" (or ":stdin:") to read from stdin
+        input: String,
+        /// Output directory
+        out_dir: PathBuf,
+    },
+    /// Print the header as JSON
+    Header {
+        /// File path, http(s) URL, or "-" (or ":stdin:") to read from stdin
+        input: String,
+    },
+    /// Print the number of itemizations for each line code as JSON
+    Count {
+        /// File path, http(s) URL, or "-" (or ":stdin:") to read from stdin
+        input: String,
+    },
+}
+
+fn open(input: &str) -> Result<FecFile, feco3::Error> {
+    Ok(match input {
+        "-" | ":stdin:" => FecFile::from_stdin(),
+        #[cfg(feature = "http")]
+        url if url.starts_with("http://") || url.starts_with("https://") => FecFile::from_url(url)?,
+        path => FecFile::from_path(&PathBuf::from(path))?,
+    })
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -37,13 +69,26 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     env_logger::Builder::new()
         .filter_level(cli.verbose.log_level_filter())
         .init();
-    let mut fec = match cli.input.as_str() {
-        ":stdin:" => FecFile::from_reader(Box::new(std::io::stdin())),
-        _ => FecFile::from_path(&PathBuf::from(cli.input))?,
-    };
-    match cli.writer {
-        Writer::Parquet => ParquetProcessor::new(cli.output, None).process(&mut fec)?,
-        Writer::CSV => CSVProcessor::new(cli.output).process(&mut fec)?,
+    match cli.command {
+        Command::ToCsv { input, out_dir } => {
+            CSVProcessor::new(out_dir).process(&mut open(&input)?)?
+        }
+        Command::ToParquet { input, out_dir } => {
+            ParquetProcessor::new(out_dir, None).process(&mut open(&input)?)?
+        }
+        Command::ToFeather { input, out_dir } => {
+            FeatherProcessor::new(out_dir).process(&mut open(&input)?)?
+        }
+        Command::Header { input } => {
+            let mut fec = open(&input)?;
+            println!("{}", serde_json::to_string_pretty(fec.get_header()?)?);
+        }
+        Command::Count { input } => {
+            let counts = open(&input)?.count_by_line_code()?;
+            // Sorted, so the output is stable.
+            let counts = counts.into_iter().collect::<BTreeMap<_, _>>();
+            println!("{}", serde_json::to_string_pretty(&counts)?);
+        }
     };
     Ok(())
 }
@@ -0,0 +1,83 @@
+//! Report how many bytes have been read from a source, and what they were.
+
+use std::io::Read;
+use std::sync::{Arc, Mutex};
+
+use sha2::{Digest, Sha256};
+
+/// Called with the total number of bytes read so far.
+pub type ProgressCallback = Box<dyn FnMut(u64) + Send>;
+
+/// Wraps a reader, calling a [ProgressCallback] after every read.
+///
+/// The reads are usually buffered further up the stack, so this is called
+/// every few KB, not for every line.
+pub struct ProgressReader<R: Read> {
+    inner: R,
+    bytes_read: u64,
+    callback: ProgressCallback,
+}
+
+impl<R: Read> ProgressReader<R> {
+    pub fn new(inner: R, callback: ProgressCallback) -> Self {
+        Self {
+            inner,
+            bytes_read: 0,
+            callback,
+        }
+    }
+}
+
+impl<R: Read> Read for ProgressReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        if n > 0 {
+            self.bytes_read += n as u64;
+            (self.callback)(self.bytes_read);
+        }
+        Ok(n)
+    }
+}
+
+/// Wraps a reader, counting the bytes read through it.
+pub struct CountingReader<R: Read> {
+    inner: R,
+    pub bytes_read: u64,
+}
+
+impl<R: Read> CountingReader<R> {
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            bytes_read: 0,
+        }
+    }
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.bytes_read += n as u64;
+        Ok(n)
+    }
+}
+
+/// Wraps a reader, feeding every byte read through it into a shared hasher.
+pub struct HashingReader<R: Read> {
+    inner: R,
+    hasher: Arc<Mutex<Sha256>>,
+}
+
+impl<R: Read> HashingReader<R> {
+    pub fn new(inner: R, hasher: Arc<Mutex<Sha256>>) -> Self {
+        Self { inner, hasher }
+    }
+}
+
+impl<R: Read> Read for HashingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.hasher.lock().unwrap().update(&buf[..n]);
+        Ok(n)
+    }
+}
@@ -4,18 +4,86 @@
 //! [https://github.com/NickCrews/feco3/wiki](https://github.com/NickCrews/feco3/wiki)
 //! for more info
 
+use std::borrow::Cow;
 use std::fmt;
 use std::hash::Hash;
+use std::sync::Arc;
 
+use rust_decimal::prelude::ToPrimitive;
+use serde::{Deserialize, Serialize};
+
+use crate::form::FormType;
 use crate::Error;
 
-#[derive(Debug, Clone)]
+/// The ways that parsing a single itemization line can fail.
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum RecordParseError {
+    /// The line couldn't be split into fields, eg it isn't valid UTF-8.
+    #[error("malformed line: {0}")]
+    Malformed(String),
+    /// The line is empty, so there is no line code.
+    #[error("no line code")]
+    NoLineCode,
+    /// The line has more values than the schema, and the parser is strict.
+    #[error("more values than the {expected} in the schema")]
+    TooManyValues { expected: usize },
+    /// The line has fewer values than the schema, and the parser is strict.
+    #[error("only {found} of the {expected} values in the schema")]
+    TooFewValues { expected: usize, found: usize },
+    /// The schema's repeating group has more fields than the schema itself.
+    #[error("repeating group of {repeating} fields in a schema of {fields}")]
+    InvalidRepeating { repeating: usize, fields: usize },
+    /// A value couldn't be parsed as the type its field should be.
+    #[error(
+        "failed to parse {} as {typ:?}: '{raw}' ({reason})",
+        describe_field(.line_code, .field)
+    )]
+    InvalidValue {
+        typ: ValueType,
+        raw: String,
+        reason: String,
+        /// The line code of the line the value was in, if known.
+        line_code: Option<String>,
+        /// The name of the field the value was in, if known.
+        field: Option<String>,
+    },
+}
+
+fn describe_field(line_code: &Option<String>, field: &Option<String>) -> String {
+    match (line_code, field) {
+        (Some(line_code), Some(field)) => format!("field '{}' of line '{}'", field, line_code),
+        (None, Some(field)) => format!("field '{}'", field),
+        (Some(line_code), None) => format!("value of line '{}'", line_code),
+        (None, None) => "value".to_string(),
+    }
+}
+
+/// A single parsed value.
+///
+/// With serde, values are tagged with their type, eg
+/// `{"type": "Date", "value": "2020-01-15"}`, so they round-trip exactly.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value")]
 pub enum Value {
     String(Option<String>),
     Integer(Option<i64>),
     Float(Option<f64>),
+    /// An exact decimal number, for amounts of money.
+    ///
+    /// Unlike [Value::Float], sums of these don't pick up rounding errors.
+    Decimal(Option<rust_decimal::Decimal>),
     Date(Option<chrono::NaiveDate>),
+    /// A date field whose raw contents couldn't be parsed as a date.
+    ///
+    /// We keep the raw string instead of failing the whole record,
+    /// since one bad date shouldn't make the rest of the line unusable.
+    InvalidDate(String),
     Boolean(Option<bool>),
+    /// The values of a field in a repeating group, one per repetition.
+    ///
+    /// See [RecordSchema::repeating]. The items all have the type of
+    /// the field in the schema.
+    List(Vec<Value>),
 }
 
 impl fmt::Display for Value {
@@ -27,77 +95,222 @@ impl fmt::Display for Value {
             Value::Integer(None) => write!(f, ""),
             Value::Float(Some(fl)) => write!(f, "{}", fl),
             Value::Float(None) => write!(f, ""),
+            Value::Decimal(Some(d)) => write!(f, "{}", d),
+            Value::Decimal(None) => write!(f, ""),
             Value::Date(Some(d)) => write!(f, "{}", d.format("%Y-%m-%d")),
             Value::Date(None) => write!(f, ""),
+            Value::InvalidDate(s) => write!(f, "{}", s),
             Value::Boolean(Some(b)) => write!(f, "{}", b),
             Value::Boolean(None) => write!(f, ""),
+            Value::List(items) => {
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, "; ")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                Ok(())
+            }
         }
     }
 }
 
 impl Value {
+    /// The type of the value. For a [Value::List], this is the type of
+    /// its items, or [ValueType::String] if it is empty.
     pub fn typ(&self) -> ValueType {
         match self {
             Value::String(_) => ValueType::String,
             Value::Integer(_) => ValueType::Integer,
             Value::Float(_) => ValueType::Float,
-            Value::Date(_) => ValueType::Date,
+            Value::Decimal(_) => ValueType::Decimal,
+            Value::Date(_) | Value::InvalidDate(_) => ValueType::Date,
             Value::Boolean(_) => ValueType::Boolean,
+            Value::List(items) => items.first().map_or(ValueType::String, Value::typ),
         }
     }
 }
 
 /// Similar to Value, but just store the type of the value, not the value itself.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum ValueType {
     String,
     Integer,
     Float,
+    /// For amounts of money, parsed into a [Value::Decimal].
+    Decimal,
     Date,
     Boolean,
 }
 
 impl ValueType {
+    /// Parse a raw field from a .fec file into a [Value] of this type.
+    ///
+    /// Missing and empty fields are treated as null, so they become
+    /// eg `Value::Integer(None)`, rather than failing to parse.
+    /// The null keeps its type so writers still know what column it belongs in.
+    /// The exception is an empty boolean field, which is an unchecked
+    /// box, so false. See [parse_fec_bool].
     pub fn parse_to_value(&self, raw: Option<&String>) -> Result<Value, Error> {
+        let raw = raw.filter(|raw| !raw.is_empty() || *self == ValueType::Boolean);
         let parsed_val = match raw {
-            None => match self {
-                ValueType::String => Value::String(None),
-                ValueType::Integer => Value::Integer(None),
-                ValueType::Float => Value::Float(None),
-                ValueType::Date => Value::Date(None),
-                ValueType::Boolean => Value::Boolean(None),
-            },
+            None => self.null(),
             Some(raw) => match self {
                 ValueType::String => Value::String(Some(raw.clone())),
                 ValueType::Integer => {
-                    let i = raw
-                        .parse::<i64>()
-                        .map_err(|e| Error::RecordParseError(e.to_string()))?;
+                    let i = raw.parse::<i64>().map_err(|e| self.invalid(raw, e))?;
                     Value::Integer(Some(i))
                 }
                 ValueType::Float => {
-                    let f = raw
-                        .parse::<f64>()
-                        .map_err(|e| Error::RecordParseError(e.to_string()))?;
+                    let f = raw.parse::<f64>().map_err(|e| self.invalid(raw, e))?;
                     Value::Float(Some(f))
                 }
-                ValueType::Date => Value::Date(Some(parse_date(raw)?)),
+                ValueType::Decimal => {
+                    let d = raw
+                        .parse::<rust_decimal::Decimal>()
+                        .map_err(|e| self.invalid(raw, e))?;
+                    Value::Decimal(Some(d))
+                }
+                ValueType::Date => parse_date_value(raw),
                 ValueType::Boolean => {
-                    let b = raw
-                        .parse::<bool>()
-                        .map_err(|e| Error::RecordParseError(e.to_string()))?;
+                    let b = parse_fec_bool(raw)
+                        .ok_or_else(|| self.invalid(raw, "not a FEC boolean"))?;
                     Value::Boolean(Some(b))
                 }
             },
         };
         Ok(parsed_val)
     }
+
+    /// Like [ValueType::parse_to_value], but a date that doesn't parse is
+    /// an error instead of a [Value::InvalidDate], for
+    /// [ParseMode::Strict](crate::ParseMode::Strict).
+    pub fn parse_to_value_strict(&self, raw: Option<&String>) -> Result<Value, Error> {
+        match self.parse_to_value(raw)? {
+            Value::InvalidDate(raw) => Err(self.invalid(&raw, "not a date")),
+            value => Ok(value),
+        }
+    }
+
+    /// Like [ValueType::parse_to_value], but numbers may have a leading "$"
+    /// and thousands separators, eg "$1,234.56", as in some hand-edited
+    /// or third-party files.
+    pub fn parse_to_value_lenient(&self, raw: Option<&String>) -> Result<Value, Error> {
+        let is_numeric = matches!(
+            self,
+            ValueType::Integer | ValueType::Float | ValueType::Decimal
+        );
+        match self.parse_to_value(raw) {
+            Err(e) if is_numeric => match raw.and_then(|raw| strip_amount_formatting(raw)) {
+                Some(stripped) => self.parse_to_value(Some(&stripped)).map_err(|_| e),
+                None => Err(e),
+            },
+            result => result,
+        }
+    }
+
+    /// A null [Value] of this type, eg `Value::Integer(None)`.
+    pub fn null(&self) -> Value {
+        match self {
+            ValueType::String => Value::String(None),
+            ValueType::Integer => Value::Integer(None),
+            ValueType::Float => Value::Float(None),
+            ValueType::Decimal => Value::Decimal(None),
+            ValueType::Date => Value::Date(None),
+            ValueType::Boolean => Value::Boolean(None),
+        }
+    }
+
+    fn invalid(&self, raw: &str, reason: impl fmt::Display) -> Error {
+        Error::RecordParseError(RecordParseError::InvalidValue {
+            typ: *self,
+            raw: raw.to_string(),
+            reason: reason.to_string(),
+            line_code: None,
+            field: None,
+        })
+    }
 }
 
-#[derive(Debug, Clone)]
+/// Strip a leading "$" and the thousands separators from an amount,
+/// eg "$1,234.56" or "-$1,234" to "1234.56" or "-1234".
+///
+/// None if there was nothing to strip, or the commas aren't between
+/// groups of 3 digits, eg "1,23".
+fn strip_amount_formatting(raw: &str) -> Option<String> {
+    let trimmed = raw.trim();
+    let (sign, unsigned) = match trimmed.strip_prefix('-') {
+        Some(rest) => ("-", rest),
+        None => ("", trimmed),
+    };
+    let (had_dollar, unsigned) = match unsigned.strip_prefix('$') {
+        Some(rest) => (true, rest),
+        None => (false, unsigned),
+    };
+    let (whole, fraction) = match unsigned.split_once('.') {
+        Some((whole, fraction)) => (whole, Some(fraction)),
+        None => (unsigned, None),
+    };
+    let mut groups = whole.split(',');
+    let first = groups.next().unwrap_or_default();
+    let mut digits = first.to_string();
+    let mut had_commas = false;
+    for group in groups {
+        if first.is_empty() || first.len() > 3 || group.len() != 3 {
+            return None;
+        }
+        had_commas = true;
+        digits.push_str(group);
+    }
+    if !had_dollar && !had_commas {
+        return None;
+    }
+    Some(match fraction {
+        Some(fraction) => format!("{}{}.{}", sign, digits, fraction),
+        None => format!("{}{}", sign, digits),
+    })
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FieldSchema {
     pub name: String,
     pub typ: ValueType,
+    /// A human-readable name for the field, eg "Contributor Last Name",
+    /// for files meant for people rather than programs.
+    ///
+    /// The built-in schemas don't have these, see [FieldSchema::display_label].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+}
+
+impl FieldSchema {
+    /// The human-readable name of the field.
+    ///
+    /// This is the `label` from the schema if it has one, otherwise it's
+    /// made from the name, eg "contributor_last_name" becomes
+    /// "Contributor Last Name".
+    pub fn display_label(&self) -> String {
+        match &self.label {
+            Some(label) => label.clone(),
+            None => humanize(&self.name),
+        }
+    }
+}
+
+/// Turn a field name like "contributor_last_name" into a label like
+/// "Contributor Last Name".
+pub(crate) fn humanize(name: &str) -> String {
+    name.split('_')
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
 }
 
 /// A parsed line of a .FEC file.
@@ -105,16 +318,80 @@ pub struct FieldSchema {
 /// See
 /// [https://github.com/NickCrews/feco3/wiki](https://github.com/NickCrews/feco3/wiki)
 /// for more info
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Record {
     /// The record type code that begins the line in the .fec file, eg "SA11"
     pub record_code: String,
-    pub schema: RecordSchema,
+    /// Shared between all records with the same line code and version.
+    pub schema: Arc<RecordSchema>,
     /// May contain fewer or more values than the schema expects.
     pub values: Vec<Value>,
+    /// How many values were found past the end of the schema.
+    ///
+    /// These are kept at the end of `values` as strings.
+    /// A non-zero count usually means the FEC added columns
+    /// that our schemas don't know about yet.
+    pub extra_fields: usize,
+    /// How many fields of the schema had no value in the line.
+    ///
+    /// Depending on the parser, these are either left out of `values`,
+    /// or filled in with nulls.
+    pub missing_fields: usize,
+    /// How many values were in the line, not counting the line code.
+    ///
+    /// For a schema with a repeating group, this counts every value of
+    /// every repetition. See [Record::received_field_count].
+    #[serde(default)]
+    pub received_fields: usize,
+    /// The index of the line among the itemization lines of the file,
+    /// starting at 0. The header and cover lines aren't counted.
+    pub row_index: usize,
+    /// Where the line starts in the (decompressed) source, in bytes.
+    ///
+    /// None if the record wasn't read from a [FecFile](crate::FecFile).
+    pub byte_offset: Option<u64>,
+    /// The raw fields of the line, including the line code, exactly as
+    /// they were in the file.
+    ///
+    /// Only kept if [FecFile::set_keep_raw](crate::FecFile::set_keep_raw)
+    /// is on, since it doubles the memory used per record.
+    pub raw: Option<Vec<String>>,
+    /// Whether an earlier record in the same filing had the same
+    /// [Record::transaction_id].
+    ///
+    /// Only set in [DuplicateMode::Flag](crate::DuplicateMode::Flag).
+    #[serde(default)]
+    pub is_duplicate: bool,
+    /// Where the record came from, eg the file name or filing ID, for
+    /// telling apart the records of several files merged together.
+    ///
+    /// Only set if [FecFile::set_source_id](crate::FecFile::set_source_id)
+    /// was given one.
+    #[serde(default)]
+    pub source_id: Option<Arc<str>>,
 }
 
 impl Record {
+    /// Whether the line had more values than the schema expects.
+    pub fn had_extra_fields(&self) -> bool {
+        self.extra_fields > 0
+    }
+
+    /// How many values the schema expects, not counting the line code.
+    pub fn expected_field_count(&self) -> usize {
+        self.schema.fields.len()
+    }
+
+    /// How many values were actually in the line, not counting the line code.
+    ///
+    /// This can differ from `values.len()`, since some parsers fill in
+    /// missing fields with nulls, and repeating groups are gathered into
+    /// lists. Divide by [Record::expected_field_count] for how complete
+    /// the line was.
+    pub fn received_field_count(&self) -> usize {
+        self.received_fields
+    }
+
     pub fn get_value(&self, field_name: &str) -> Option<&Value> {
         let field_index = self
             .schema
@@ -123,13 +400,239 @@ impl Record {
             .position(|f| f.name == field_name)?;
         self.values.get(field_index)
     }
+
+    /// The ID that uniquely identifies this itemization within its filing,
+    /// eg "SA11AI.4265", from the "transaction_id" (or in some versions
+    /// "transaction_id_number") field.
+    ///
+    /// None if the line has no such field, or it is empty.
+    pub fn transaction_id(&self) -> Option<&str> {
+        let value = self
+            .get_value("transaction_id")
+            .or_else(|| self.get_value("transaction_id_number"))?;
+        match value {
+            Value::String(Some(id)) if !id.trim().is_empty() => Some(id.trim()),
+            _ => None,
+        }
+    }
+
+    /// The form or schedule this line belongs to, eg [FormType::SA]
+    /// for "SA11AI".
+    pub fn form_type(&self) -> FormType {
+        FormType::from_code(&self.record_code)
+    }
+
+    /// Every committee and candidate ID in the line, eg the filer, or the
+    /// committee that made a contribution, in the order of the fields.
+    ///
+    /// This is a heuristic based on the field names in the schema:
+    /// fields with an "id" part and a "committee" or "candidate" part,
+    /// eg "filer_committee_id_number" or "donor_candidate_fec_id".
+    /// Fields that just say "fec_id", eg "contributor_fec_id", could be
+    /// either, so their kind is guessed from the first letter of the ID.
+    /// Empty values are skipped.
+    pub fn referenced_ids(&self) -> Vec<(IdKind, String)> {
+        self.typed_fields()
+            .filter_map(|(name, _, val)| {
+                let id = match val {
+                    Value::String(Some(s)) if !s.trim().is_empty() => s.trim(),
+                    _ => return None,
+                };
+                let kind = IdKind::from_field_name(&name, id)?;
+                Some((kind, id.to_string()))
+            })
+            .collect()
+    }
+
+    /// Each value with the name and type of its field in the schema.
+    ///
+    /// Values past the end of the schema are named "extra_1", "extra_2",
+    /// etc, like the writers do, and are typed as [ValueType::String].
+    /// Fields that had no value in the line aren't included.
+    pub fn typed_fields(&self) -> impl Iterator<Item = (Cow<'_, str>, ValueType, &Value)> {
+        let fields = &self.schema.fields;
+        self.values
+            .iter()
+            .enumerate()
+            .map(move |(i, val)| match fields.get(i) {
+                Some(field) => (Cow::Borrowed(field.name.as_str()), field.typ, val),
+                None => {
+                    let name = format!("extra_{}", i - fields.len() + 1);
+                    (Cow::Owned(name), ValueType::String, val)
+                }
+            })
+    }
+
+    /// The well-known summary totals, if this is an F3X line
+    /// (eg "F3XN", "F3XA", or "F3XT"), otherwise None.
+    ///
+    /// These are the "column A" values, ie for this reporting period only.
+    pub fn as_f3x(&self) -> Option<F3XSummary> {
+        if !self.record_code.to_uppercase().starts_with("F3X") {
+            return None;
+        }
+        Some(F3XSummary {
+            coverage_from_date: self.get_date("coverage_from_date"),
+            coverage_through_date: self.get_date("coverage_through_date"),
+            cash_on_hand_beginning_period: self.get_f64("col_a_cash_on_hand_beginning_period"),
+            total_receipts: self.get_f64("col_a_total_receipts"),
+            total_disbursements: self.get_f64("col_a_total_disbursements"),
+            cash_on_hand_close_of_period: self.get_f64("col_a_cash_on_hand_close_of_period"),
+            debts_to: self.get_f64("col_a_debts_to"),
+            debts_by: self.get_f64("col_a_debts_by"),
+        })
+    }
+
+    /// The amount of money this line moves, signed so that the amounts of
+    /// a filing's lines can be summed: receipts are positive and
+    /// disbursements are negative.
+    ///
+    /// For Schedule A (receipts) lines this is the contribution amount as
+    /// filed, which is already negative for refunds of contributions.
+    /// For Schedule B (disbursements) lines it is the expenditure amount,
+    /// negated, so a negative disbursement (eg a refunded payment) counts
+    /// as money back in.
+    ///
+    /// Memo items (with memo code "X") only give detail on money counted
+    /// by another line, eg the individual contributions that make up
+    /// an earmarked total, so they are None so as not to count them twice.
+    /// Other lines are None too, as is a missing amount.
+    pub fn signed_amount(&self) -> Option<f64> {
+        if self.is_memo() {
+            return None;
+        }
+        match self.form_type() {
+            FormType::SA => self.get_f64("contribution_amount"),
+            FormType::SB => self.get_f64("expenditure_amount").map(|a| -a),
+            _ => None,
+        }
+    }
+
+    /// Whether this is a memo item, ie its memo code is "X".
+    pub fn is_memo(&self) -> bool {
+        match self.get_value("memo_code") {
+            Some(Value::String(Some(code))) => code.trim().eq_ignore_ascii_case("X"),
+            Some(Value::Boolean(Some(b))) => *b,
+            _ => false,
+        }
+    }
+
+    /// Get a string field without copying it.
+    ///
+    /// None if the field is missing or empty, or wasn't parsed as a string.
+    pub fn get_str(&self, field_name: &str) -> Option<&str> {
+        match self.get_value(field_name)? {
+            Value::String(s) => s.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// Get a field as an integer, whether it was parsed as one or left as
+    /// a string.
+    ///
+    /// None if the field is missing or empty, or isn't a whole number.
+    pub fn get_i64(&self, field_name: &str) -> Option<i64> {
+        match self.get_value(field_name)? {
+            Value::Integer(i) => *i,
+            Value::String(s) => s.as_ref()?.trim().parse().ok(),
+            _ => None,
+        }
+    }
+
+    /// Get a field as a float, whether it was parsed as one or left as a string.
+    ///
+    /// None if the field is missing or empty, or isn't a number.
+    pub fn get_f64(&self, field_name: &str) -> Option<f64> {
+        match self.get_value(field_name)? {
+            Value::Float(f) => *f,
+            Value::Decimal(d) => d.and_then(|d| d.to_f64()),
+            Value::Integer(i) => i.map(|i| i as f64),
+            Value::String(s) => s.as_ref()?.trim().parse().ok(),
+            _ => None,
+        }
+    }
+
+    /// Get a field as a date, whether it was parsed as one or left as a string.
+    ///
+    /// None if the field is missing or empty, or isn't a valid date.
+    pub fn get_date(&self, field_name: &str) -> Option<chrono::NaiveDate> {
+        match self.get_value(field_name)? {
+            Value::Date(d) => *d,
+            Value::String(s) => parse_date(s.as_ref()?.trim()),
+            _ => None,
+        }
+    }
+}
+
+/// What kind of entity an ID refers to, see [Record::referenced_ids].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IdKind {
+    /// eg "C00401224"
+    Committee,
+    /// eg "P80001571", "H0AK00097", or "S0AK00196"
+    Candidate,
+}
+
+impl IdKind {
+    /// Guess the kind of ID in field `name`, or None if it isn't an ID field.
+    fn from_field_name(name: &str, id: &str) -> Option<IdKind> {
+        let parts = name.split('_').collect::<Vec<_>>();
+        if !parts.contains(&"id") {
+            return None;
+        }
+        if parts.contains(&"candidate") {
+            Some(IdKind::Candidate)
+        } else if parts.contains(&"committee") || parts.contains(&"cmtte") {
+            Some(IdKind::Committee)
+        } else if parts.contains(&"fec") {
+            match id.chars().next()?.to_ascii_uppercase() {
+                'C' => Some(IdKind::Committee),
+                'H' | 'S' | 'P' => Some(IdKind::Candidate),
+                _ => None,
+            }
+        } else {
+            None
+        }
+    }
+}
+
+/// The summary totals from an F3X (PAC and party committee) report.
+///
+/// See [Record::as_f3x]. Fields that are missing or unparseable are None.
+#[derive(Debug, Clone, PartialEq)]
+pub struct F3XSummary {
+    pub coverage_from_date: Option<chrono::NaiveDate>,
+    pub coverage_through_date: Option<chrono::NaiveDate>,
+    pub cash_on_hand_beginning_period: Option<f64>,
+    pub total_receipts: Option<f64>,
+    pub total_disbursements: Option<f64>,
+    pub cash_on_hand_close_of_period: Option<f64>,
+    /// Debts owed to the committee.
+    pub debts_to: Option<f64>,
+    /// Debts owed by the committee.
+    pub debts_by: Option<f64>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RecordSchema {
     /// Record code, eg "F3" or "SA11"
     pub code: String,
     pub fields: Vec<FieldSchema>,
+    /// How many of the last `fields` form a group that repeats to the end
+    /// of the line, eg the name and ID of each of a committee's affiliates.
+    ///
+    /// Each field of the group is parsed into a [Value::List], with one
+    /// item per repetition. 0, the default, means nothing repeats. It
+    /// can be at most the number of `fields`.
+    #[serde(default)]
+    pub repeating: usize,
+}
+
+impl RecordSchema {
+    /// Whether the field at `index` is part of the repeating group.
+    pub fn is_repeating(&self, index: usize) -> bool {
+        index < self.fields.len() && index + self.repeating >= self.fields.len()
+    }
 }
 
 impl Hash for RecordSchema {
@@ -146,8 +649,35 @@ impl PartialEq for RecordSchema {
 
 impl Eq for RecordSchema {}
 
-fn parse_date(raw: &str) -> Result<chrono::NaiveDate, Error> {
-    let date = chrono::NaiveDate::parse_from_str(raw, "%Y%m%d")
-        .map_err(|e| Error::RecordParseError(e.to_string()))?;
-    Ok(date)
+/// The date formats found in .fec files, in order of preference.
+///
+/// Nearly every file uses "YYYYMMDD", but some older ones use "MM/DD/YYYY".
+const DATE_FORMATS: [&str; 2] = ["%Y%m%d", "%m/%d/%Y"];
+
+/// Parse a raw date from a .fec file, trying each of [DATE_FORMATS].
+pub(crate) fn parse_date(raw: &str) -> Option<chrono::NaiveDate> {
+    DATE_FORMATS
+        .iter()
+        .find_map(|fmt| chrono::NaiveDate::parse_from_str(raw, fmt).ok())
+}
+
+/// Parse a boolean the way .fec files write them.
+///
+/// Checkboxes are "X" or empty, and other flags are "Y"/"N" or "1"/"0".
+/// So "X", "Y", "1", and "true" are true, and "N", "0", "false",
+/// and the empty string are false, ignoring case and surrounding spaces.
+/// Anything else is None.
+pub fn parse_fec_bool(raw: &str) -> Option<bool> {
+    match raw.trim().to_ascii_lowercase().as_str() {
+        "x" | "y" | "1" | "true" => Some(true),
+        "n" | "0" | "false" | "" => Some(false),
+        _ => None,
+    }
+}
+
+fn parse_date_value(raw: &str) -> Value {
+    match parse_date(raw) {
+        Some(date) => Value::Date(Some(date)),
+        None => Value::InvalidDate(raw.to_string()),
+    }
 }
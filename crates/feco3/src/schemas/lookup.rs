@@ -1,8 +1,10 @@
 use std::collections::HashMap;
+use std::path::Path;
 
-use crate::{record::RecordSchema, Error};
+use crate::record::{FieldSchema, RecordSchema, ValueType};
+use crate::{Error, FecVersion};
 use serde_json::Value;
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
 
 /// Lookup a [RecordSchema] given the .FEC file version and the line code.
 ///
@@ -10,17 +12,379 @@ use std::sync::Mutex;
 /// This is found in the header of the .FEC file.
 /// The line code is the first field in each line of the .FEC file.
 /// It is a string like "F3" or "SA11".
-pub fn lookup_schema(version: &str, line_code: &str) -> Result<&'static RecordSchema, Error> {
+///
+/// Schemas are cached, so every call with the same version and line code
+/// returns the same shared instance.
+pub fn lookup_schema(version: &str, line_code: &str) -> Result<Arc<RecordSchema>, Error> {
     let key = (version.to_string(), line_code.to_string());
     if let Some(schema) = CACHE.lock().unwrap().get(&key) {
-        return Ok(schema);
+        return Ok(schema.clone());
     }
-    let schema = do_lookup(version, line_code)?;
-    CACHE.lock().unwrap().insert(key, schema);
+    let schema = match do_lookup(version, line_code) {
+        Ok(schema) => schema,
+        Err(e) => lookup_nearest_lower(version, line_code).ok_or(e)?,
+    };
+    let schema = Arc::new(schema);
+    CACHE.lock().unwrap().insert(key, schema.clone());
     Ok(schema)
 }
 
-fn do_lookup(version: &str, line_code: &str) -> Result<&'static RecordSchema, Error> {
+/// The versions that the bundled schemas cover.
+const KNOWN_VERSIONS: &[&str] = &[
+    "1.0", "2.0", "3.0", "5.0", "5.1", "5.2", "5.3", "6.1", "6.2", "6.3", "6.4", "7.0", "8.0",
+    "8.1", "8.2", "8.3", "8.4", "P1.0", "P2.2", "P2.3", "P2.4", "P2.6", "P3.0", "P3.1", "P3.2",
+    "P3.3", "P3.4",
+];
+
+/// The bundled schema version that files of `version` are parsed with.
+///
+/// This is `version` itself if the bundled schemas cover it, eg "3.00"
+/// resolves to "3.0". Otherwise it is the nearest lower version with the
+/// same major version, eg "8.5" resolves to "8.4", like [lookup_schema].
+/// None if there is no such version, eg for "9.0" or "garbage".
+/// A line code that the resolved version has no schema for falls back
+/// further, to the nearest lower version that has one.
+///
+/// With [SchemaOverrides::fallback_to_latest], those versions use
+/// [lookup_latest_schema] instead.
+pub fn resolve_version(version: &str) -> Option<String> {
+    let target: FecVersion = version.parse().ok()?;
+    KNOWN_VERSIONS
+        .iter()
+        .filter_map(|v| v.parse::<FecVersion>().ok())
+        .filter(|v| v.paper == target.paper && v.major == target.major && *v <= target)
+        .max()
+        .map(|v| v.to_string())
+}
+
+/// For a version newer than the bundled schemas, eg "8.5", use the schema
+/// of the nearest lower known version with the same major version, eg "8.4".
+///
+/// Minor version bumps rarely change the columns, but a major one might,
+/// so we don't guess across those.
+fn lookup_nearest_lower(version: &str, line_code: &str) -> Option<RecordSchema> {
+    let target: FecVersion = version.parse().ok()?;
+    let mut candidates = KNOWN_VERSIONS
+        .iter()
+        .filter_map(|v| Some((v.parse::<FecVersion>().ok()?, *v)))
+        .filter(|(v, _)| v.paper == target.paper && v.major == target.major && *v < target)
+        .collect::<Vec<_>>();
+    candidates.sort();
+    let (nearest, schema) = candidates.into_iter().rev().find_map(|(_, v)| {
+        let schema = do_lookup(v, line_code).ok()?;
+        Some((v, schema))
+    })?;
+    log::warn!(
+        "no schema for version {} and line code {}, using the one for {}",
+        version,
+        line_code,
+        nearest
+    );
+    Some(schema)
+}
+
+/// Schemas supplied at runtime, which take precedence over the built-in ones.
+///
+/// This is useful when the bundled schemas lag behind a new version of a form.
+/// Unlike the built-in schemas, the version and line code must match
+/// exactly (the line code is case-insensitive), there is no regex matching.
+///
+/// If `fallback_to_latest` is set, versions with no built-in schema at all
+/// use the schema of the latest known version, see [lookup_latest_schema].
+///
+/// Line codes can also be aliased to a canonical code, see
+/// [SchemaOverrides::insert_alias].
+#[derive(Debug, Clone, Default)]
+pub struct SchemaOverrides {
+    schemas: HashMap<(String, String), Arc<RecordSchema>>,
+    /// Keyed by the uppercased alias.
+    aliases: HashMap<String, String>,
+    pub fallback_to_latest: bool,
+}
+
+impl SchemaOverrides {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a schema for the given version, replacing any previous one.
+    /// The line code is taken from [RecordSchema::code].
+    pub fn insert(&mut self, version: &str, schema: RecordSchema) {
+        let key = (version.to_string(), schema.code.to_uppercase());
+        self.schemas.insert(key, Arc::new(schema));
+    }
+
+    pub fn get(&self, version: &str, line_code: &str) -> Option<&Arc<RecordSchema>> {
+        let key = (version.to_string(), line_code.to_uppercase());
+        self.schemas.get(&key)
+    }
+
+    /// Add all the schemas and aliases of `other`, replacing any for the
+    /// same version and line code.
+    pub fn extend(&mut self, other: SchemaOverrides) {
+        self.schemas.extend(other.schemas);
+        self.aliases.extend(other.aliases);
+    }
+
+    /// Look up lines with code `alias` as if their code were `canonical`,
+    /// eg to treat "SA11AI" and "SA11AII" both as "SA11".
+    ///
+    /// The schema's [RecordSchema::code] becomes `canonical`, so writers
+    /// put both in the same output. If there is no schema for `canonical`
+    /// itself, eg "SA", the schema of `alias` is used, renamed. Every alias
+    /// of such a code must then have the same fields, otherwise looking
+    /// them up is an [Error::AliasConflict].
+    /// The alias must match exactly, ignoring case.
+    pub fn insert_alias(&mut self, alias: &str, canonical: &str) {
+        self.aliases
+            .insert(alias.to_uppercase(), canonical.to_string());
+    }
+
+    /// The canonical code that `line_code` is an alias of, if any.
+    pub fn canonical(&self, line_code: &str) -> Option<&str> {
+        self.aliases
+            .get(&line_code.to_uppercase())
+            .map(|c| c.as_str())
+    }
+}
+
+/// Load schemas from every `.json` file in `dir`, to use as overrides.
+///
+/// Each file is an object mapping a version to a list of schemas
+/// for it, in the same shape as a serialized [RecordSchema], eg
+///
+/// ```json
+/// {
+///   "8.4": [
+///     {
+///       "code": "SA11AI",
+///       "fields": [{ "name": "filer_committee_id_number", "typ": "String" }]
+///     }
+///   ]
+/// }
+/// ```
+///
+/// A schema can also set `"repeating"`, see [RecordSchema::repeating],
+/// and each field can have a `"label"`, see [FieldSchema::label].
+///
+/// [FieldSchema::label]: crate::record::FieldSchema::label
+///
+/// Files are loaded in order of their names, so if two files define the
+/// same version and line code, the later one wins. Subdirectories are
+/// ignored. See [SchemaOverrides] for how these are matched.
+pub fn load_from_dir(dir: &Path) -> Result<SchemaOverrides, Error> {
+    let mut paths = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_file() && path.extension().is_some_and(|ext| ext == "json") {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+    let mut overrides = SchemaOverrides::new();
+    for path in paths {
+        log::debug!("loading schemas from {:?}", path);
+        let invalid = |e: serde_json::Error| {
+            let msg = format!("invalid schema file {:?}: {}", path, e);
+            std::io::Error::new(std::io::ErrorKind::InvalidData, msg)
+        };
+        let file = std::io::BufReader::new(std::fs::File::open(&path)?);
+        let by_version: HashMap<String, Vec<RecordSchema>> =
+            serde_json::from_reader(file).map_err(invalid)?;
+        for (version, schemas) in by_version {
+            for schema in schemas {
+                if schema.repeating > schema.fields.len() {
+                    let msg = format!(
+                        "invalid schema file {:?}: {} repeats {} of its {} fields",
+                        path,
+                        schema.code,
+                        schema.repeating,
+                        schema.fields.len()
+                    );
+                    return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, msg).into());
+                }
+                overrides.insert(&version, schema);
+            }
+        }
+    }
+    Ok(overrides)
+}
+
+/// Like [lookup_schema], but check `overrides` first.
+pub fn lookup_schema_with_overrides(
+    overrides: &SchemaOverrides,
+    version: &str,
+    line_code: &str,
+) -> Result<Arc<RecordSchema>, Error> {
+    let canonical = match overrides.canonical(line_code) {
+        None => return lookup_unaliased(overrides, version, line_code),
+        Some(canonical) => canonical,
+    };
+    if let Ok(schema) = lookup_unaliased(overrides, version, canonical) {
+        return Ok(schema);
+    }
+    // Registered schemas can differ between files, so only cache built-in ones.
+    let registered = overrides.get(version, line_code).is_some();
+    let key = (
+        overrides.fallback_to_latest,
+        version.to_string(),
+        line_code.to_string(),
+        canonical.to_string(),
+    );
+    if !registered {
+        if let Some(schema) = ALIAS_CACHE.lock().unwrap().get(&key) {
+            return Ok(schema.clone());
+        }
+    }
+    let schema = lookup_unaliased(overrides, version, line_code)?;
+    // Records of every alias go to one output, so they need the same fields.
+    for (other, other_canonical) in &overrides.aliases {
+        if other_canonical != canonical || other.eq_ignore_ascii_case(line_code) {
+            continue;
+        }
+        if let Ok(other_schema) = lookup_unaliased(overrides, version, other) {
+            if !same_fields(&schema, &other_schema) {
+                return Err(Error::AliasConflict(
+                    line_code.to_string(),
+                    other.clone(),
+                    canonical.to_string(),
+                ));
+            }
+        }
+    }
+    let renamed = Arc::new(RecordSchema {
+        code: canonical.to_string(),
+        ..(*schema).clone()
+    });
+    if !registered {
+        ALIAS_CACHE.lock().unwrap().insert(key, renamed.clone());
+    }
+    Ok(renamed)
+}
+
+fn same_fields(a: &RecordSchema, b: &RecordSchema) -> bool {
+    a.repeating == b.repeating
+        && a.fields.len() == b.fields.len()
+        && a.fields
+            .iter()
+            .zip(&b.fields)
+            .all(|(a, b)| a.name == b.name && a.typ == b.typ)
+}
+
+fn lookup_unaliased(
+    overrides: &SchemaOverrides,
+    version: &str,
+    line_code: &str,
+) -> Result<Arc<RecordSchema>, Error> {
+    if let Some(schema) = overrides.get(version, line_code) {
+        return Ok(schema.clone());
+    }
+    match lookup_schema(version, line_code) {
+        Err(e) if overrides.fallback_to_latest => lookup_latest_schema(version, line_code).ok_or(e),
+        result => result,
+    }
+}
+
+/// Lookup the schema for `line_code` in the latest version that has one.
+///
+/// This is a last resort for versions the bundled schemas don't know about
+/// at all, eg "9.0", since the FEC often bumps the version without
+/// changing the columns. Paper versions (eg "P9.0") fall back to the
+/// latest paper version.
+pub fn lookup_latest_schema(version: &str, line_code: &str) -> Option<Arc<RecordSchema>> {
+    let paper = version.trim().starts_with(['P', 'p']);
+    let key = (paper, line_code.to_string());
+    if let Some(schema) = LATEST_CACHE.lock().unwrap().get(&key) {
+        return Some(schema.clone());
+    }
+    let mut candidates = KNOWN_VERSIONS
+        .iter()
+        .filter_map(|v| Some((v.parse::<FecVersion>().ok()?, *v)))
+        .filter(|(v, _)| v.paper == paper)
+        .collect::<Vec<_>>();
+    candidates.sort();
+    let (latest, schema) = candidates.into_iter().rev().find_map(|(_, v)| {
+        let schema = do_lookup(v, line_code).ok()?;
+        Some((v, schema))
+    })?;
+    log::warn!(
+        "no schema for version {} and line code {}, falling back to the one for {}",
+        version,
+        line_code,
+        latest
+    );
+    let schema = Arc::new(schema);
+    LATEST_CACHE.lock().unwrap().insert(key, schema.clone());
+    Some(schema)
+}
+
+/// The line codes with a known schema for the given .FEC file version.
+///
+/// The built-in schemas match line codes with regexes (case-insensitive),
+/// so these are the patterns, eg `^sa[^3]` matches "SA11AI", "SA17", etc.
+/// Use [field_names] to get the columns for a concrete line code.
+pub fn line_codes_for_version(version: &str) -> Vec<String> {
+    MAPPINGS
+        .iter()
+        .filter(|(_, versions)| versions.iter().any(|(v, _)| v.is_match(version)))
+        .map(|(line_code_regex, _)| line_code_regex.as_str().to_string())
+        .collect()
+}
+
+/// The field names of the built-in schema for the given version and line code,
+/// or None if there is no schema for them.
+///
+/// This doesn't include the line code itself, which is the first value
+/// in every line.
+pub fn field_names(version: &str, line_code: &str) -> Option<Vec<String>> {
+    let schema = lookup_schema(version, line_code).ok()?;
+    Some(schema.fields.iter().map(|f| f.name.clone()).collect())
+}
+
+/// The type the FEC documents for a field, eg [ValueType::Date] for the
+/// "contribution_date" of "SA11AI" lines, or None if it's just text.
+///
+/// The built-in schemas leave every field as [ValueType::String], so
+/// nothing is lost if a value doesn't parse. This is for writers whose
+/// output benefits from typed columns, see [typed_schema].
+pub fn documented_field_type(version: &str, line_code: &str, field: &str) -> Option<ValueType> {
+    TYPES
+        .iter()
+        .filter(|(form_regex, _)| form_regex.is_match(line_code))
+        .flat_map(|(_, versions)| versions)
+        .filter(|(version_regex, _)| version_regex.is_match(version))
+        .flat_map(|(_, fields)| fields)
+        .find(|(field_regex, _)| field_regex.is_match(field))
+        .map(|(_, typ)| *typ)
+}
+
+/// `schema` with its [ValueType::String] fields changed to their
+/// [documented_field_type], if they have one.
+///
+/// Fields that already have another type, eg from a registered schema,
+/// are left alone.
+pub fn typed_schema(version: &str, schema: &RecordSchema) -> RecordSchema {
+    let fields = schema
+        .fields
+        .iter()
+        .map(|field| {
+            let documented = match field.typ {
+                ValueType::String => documented_field_type(version, &schema.code, &field.name),
+                _ => None,
+            };
+            FieldSchema {
+                typ: documented.unwrap_or(field.typ),
+                ..field.clone()
+            }
+        })
+        .collect();
+    RecordSchema {
+        fields,
+        ..schema.clone()
+    }
+}
+
+fn do_lookup(version: &str, line_code: &str) -> Result<RecordSchema, Error> {
     log::debug!(
         "looking up schema for version: '{}', line_code: '{}'",
         version,
@@ -42,16 +406,16 @@ fn do_lookup(version: &str, line_code: &str) -> Result<&'static RecordSchema, Er
                 field_schemas.push(crate::record::FieldSchema {
                     name: field_name.clone(),
                     typ: crate::record::ValueType::String,
+                    label: None,
                 });
             }
             let schema = RecordSchema {
                 code: line_code.to_string(),
                 fields: field_schemas,
+                repeating: 0,
             };
             log::debug!("found schema: {:?}", schema);
-
-            // We should only do this once for each schema, so we can leak the Box.
-            return Ok(Box::leak(Box::new(schema)));
+            return Ok(schema);
         }
     }
     Err(Error::SchemaError(
@@ -61,15 +425,27 @@ fn do_lookup(version: &str, line_code: &str) -> Result<&'static RecordSchema, Er
 }
 
 lazy_static! {
-    static ref CACHE: Mutex<HashMap<(String, String), &'static RecordSchema>> =
+    static ref CACHE: Mutex<HashMap<(String, String), Arc<RecordSchema>>> =
+        Mutex::new(HashMap::new());
+    /// Keyed by whether the version is a paper one, and the line code.
+    static ref LATEST_CACHE: Mutex<HashMap<(bool, String), Arc<RecordSchema>>> =
         Mutex::new(HashMap::new());
-    static ref MAPPINGS: Vec<(FormRegex, Vec<(VersionRegex, Vec<String>)>)> = load_mappings();
+    /// Keyed by [SchemaOverrides::fallback_to_latest], the version,
+    /// the aliased line code, and the canonical code.
+    static ref ALIAS_CACHE: Mutex<HashMap<AliasKey, Arc<RecordSchema>>> =
+        Mutex::new(HashMap::new());
+    static ref MAPPINGS: Mappings = load_mappings();
+    static ref TYPES: Types = load_types();
 }
 
+type AliasKey = (bool, String, String, String);
 type VersionRegex = regex::Regex;
 type FormRegex = regex::Regex;
+type Mappings = Vec<(FormRegex, Vec<(VersionRegex, Vec<String>)>)>;
+type FieldRegex = regex::Regex;
+type Types = Vec<(FormRegex, Vec<(VersionRegex, Vec<(FieldRegex, ValueType)>)>)>;
 
-fn load_mappings() -> Vec<(FormRegex, Vec<(VersionRegex, Vec<String>)>)> {
+fn load_mappings() -> Mappings {
     let mappings_str = include_str!("mappings.json");
     let value = match serde_json::from_str(mappings_str).unwrap() {
         Value::Object(map) => map,
@@ -102,6 +478,42 @@ fn load_mappings() -> Vec<(FormRegex, Vec<(VersionRegex, Vec<String>)>)> {
     result
 }
 
+fn load_types() -> Types {
+    let types_str = include_str!("types.json");
+    let value = match serde_json::from_str(types_str).unwrap() {
+        Value::Object(map) => map,
+        _ => panic!("types.json is not a map"),
+    };
+    let mut result = Vec::new();
+    for (form_pattern, versions_value) in value {
+        let versions = match versions_value {
+            Value::Object(map) => map,
+            _ => panic!("types.json is not a map"),
+        };
+        let mut versions_vec = Vec::new();
+        for (version_pattern, fields_value) in versions {
+            let fields_value = match fields_value {
+                Value::Object(map) => map,
+                _ => panic!("types.json is not a map"),
+            };
+            let mut fields = Vec::new();
+            for (field_pattern, type_value) in fields_value {
+                // Dates are always %Y%m%d, which parse_date already handles.
+                // The "float" fields are amounts of money, so keep them exact.
+                let typ = match type_value.get("type").and_then(Value::as_str) {
+                    Some("date") => ValueType::Date,
+                    Some("float") => ValueType::Decimal,
+                    other => panic!("unknown type in types.json: {:?}", other),
+                };
+                fields.push((make_regex(&field_pattern), typ));
+            }
+            versions_vec.push((make_regex(&version_pattern), fields));
+        }
+        result.push((make_regex(&form_pattern), versions_vec));
+    }
+    result
+}
+
 fn make_regex(s: &str) -> regex::Regex {
     regex::RegexBuilder::new(s)
         .case_insensitive(true)
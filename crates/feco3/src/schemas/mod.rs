@@ -1,5 +1,16 @@
+//! The schemas of each line in an .fec file, and parsing lines according to them.
+//!
+//! The schemas are bundled with the crate, see mappings.json.
+//! More can be loaded at runtime, see [load_from_dir].
+
 mod lookup;
 mod parse;
 
-pub use crate::schemas::lookup::lookup_schema;
-pub use crate::schemas::parse::{CoercingLineParser, LineParser, LiteralLineParser};
+pub use crate::schemas::lookup::{
+    documented_field_type, field_names, line_codes_for_version, load_from_dir,
+    lookup_latest_schema, lookup_schema, lookup_schema_with_overrides, resolve_version,
+    typed_schema, SchemaOverrides,
+};
+pub use crate::schemas::parse::{
+    CoercingLineParser, FieldTransform, LineParser, LiteralLineParser, ParseMode,
+};
@@ -1,9 +1,13 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
 use crate::{
-    record::{Record, RecordSchema, Value},
+    record::{FieldSchema, Record, RecordParseError, RecordSchema, Value, ValueType},
     Error,
 };
 
-use super::lookup_schema;
+use super::{lookup_schema_with_overrides, SchemaOverrides};
 
 pub trait LineParser<'a> {
     /// Parse the values to a given schema.
@@ -13,6 +17,31 @@ pub trait LineParser<'a> {
         line: &mut impl Iterator<Item = &'a String>,
     ) -> Result<Vec<Value>, Error>;
 
+    /// Like [LineParser::parse_values], for a line with code `line_code`.
+    ///
+    /// This differs from `schema.code` for aliased line codes, see
+    /// [SchemaOverrides::insert_alias]. By default it is ignored.
+    fn parse_values_as(
+        &mut self,
+        line_code: &str,
+        schema: &RecordSchema,
+        line: &mut impl Iterator<Item = &'a String>,
+    ) -> Result<Vec<Value>, Error> {
+        let _ = line_code;
+        self.parse_values(schema, line)
+    }
+
+    /// The schema of a record with code `line_code`, whose values were
+    /// parsed against `schema`.
+    ///
+    /// Parsers that change the types of fields should return a schema
+    /// with those types, so writers build columns that fit the values.
+    /// By default this is `schema`.
+    fn record_schema(&mut self, line_code: &str, schema: Arc<RecordSchema>) -> Arc<RecordSchema> {
+        let _ = line_code;
+        schema
+    }
+
     /// Parse a complete line of a .FEC file.
     ///
     /// Given a version string like "8.0" and a iterable of byte slices,
@@ -23,18 +52,100 @@ pub trait LineParser<'a> {
         &mut self,
         fec_version: &str,
         line: &mut impl Iterator<Item = &'a String>,
+    ) -> Result<Record, Error> {
+        self.parse_line_with_overrides(fec_version, &SchemaOverrides::new(), line)
+    }
+
+    /// Like [LineParser::parse_line], but look for the schema in
+    /// `overrides` before the built-in schemas.
+    fn parse_line_with_overrides(
+        &mut self,
+        fec_version: &str,
+        overrides: &SchemaOverrides,
+        line: &mut impl Iterator<Item = &'a String>,
     ) -> Result<Record, Error> {
         let (record_code, line) = get_record_code(line)?;
-        let schema: &RecordSchema = lookup_schema(fec_version, record_code)?;
-        let values = self.parse_values(schema, line)?;
+        let schema = lookup_schema_with_overrides(overrides, fec_version, record_code)?;
+        if schema.repeating > 0 {
+            return parse_repeating_line(self, record_code, schema, line);
+        }
+        let mut n_raw = 0;
+        let values =
+            self.parse_values_as(record_code, &schema, &mut line.inspect(|_| n_raw += 1))?;
+        let missing_fields = schema.fields.len().saturating_sub(n_raw);
+        let extra_fields = values.len().saturating_sub(schema.fields.len());
+        let schema = self.record_schema(record_code, schema);
         Ok(Record {
             record_code: record_code.to_string(),
-            schema: schema.clone(),
+            schema,
             values,
+            extra_fields,
+            missing_fields,
+            received_fields: n_raw,
+            row_index: 0,
+            byte_offset: None,
+            raw: None,
+            is_duplicate: false,
+            source_id: None,
         })
     }
 }
 
+/// Parse a line whose schema ends in a repeating group of fields.
+///
+/// The group is repeated as many times as it takes to hold the rest of
+/// the line, the values are parsed against that, then the values of each
+/// field in the group are gathered into a [Value::List].
+fn parse_repeating_line<'a, P: LineParser<'a> + ?Sized>(
+    parser: &mut P,
+    record_code: &str,
+    schema: Arc<RecordSchema>,
+    line: impl Iterator<Item = &'a String>,
+) -> Result<Record, Error> {
+    let raw = line.collect::<Vec<_>>();
+    let fixed = schema.fields.len().checked_sub(schema.repeating).ok_or(
+        RecordParseError::InvalidRepeating {
+            repeating: schema.repeating,
+            fields: schema.fields.len(),
+        },
+    )?;
+    let group = &schema.fields[fixed..];
+    let repetitions = raw.len().saturating_sub(fixed).div_ceil(group.len());
+    let mut fields = schema.fields[..fixed].to_vec();
+    for _ in 0..repetitions {
+        fields.extend_from_slice(group);
+    }
+    let expanded = RecordSchema {
+        code: schema.code.clone(),
+        fields,
+        repeating: 0,
+    };
+    let n_raw = raw.len();
+    let mut values = parser.parse_values_as(record_code, &expanded, &mut raw.into_iter())?;
+    // Like other missing fields, the lists are left out if the parser
+    // doesn't fill in the fields before them.
+    if values.len() >= fixed {
+        let repeated = values.split_off(fixed);
+        for i in 0..group.len() {
+            let items = repeated.iter().skip(i).step_by(group.len()).cloned();
+            values.push(Value::List(items.collect()));
+        }
+    }
+    Ok(Record {
+        record_code: record_code.to_string(),
+        missing_fields: fixed.saturating_sub(n_raw),
+        schema: parser.record_schema(record_code, schema),
+        values,
+        extra_fields: 0,
+        received_fields: n_raw,
+        row_index: 0,
+        byte_offset: None,
+        raw: None,
+        is_duplicate: false,
+        source_id: None,
+    })
+}
+
 /// A LineParser that returns a line with the exact values seen.
 ///
 /// This might be different from the expected number of values in the schema.
@@ -54,64 +165,324 @@ impl<'a> LineParser<'a> for LiteralLineParser {
         for raw_value in raw {
             let field_schema = field_schemas
                 .next()
-                .ok_or(Error::RecordParseError("too many values".to_string()))?;
+                .ok_or(RecordParseError::TooManyValues {
+                    expected: schema.fields.len(),
+                })?;
             let rv = match raw_value.trim() {
                 "" => None,
                 s => Some(s.to_string()),
             };
-            let value = field_schema.typ.parse_to_value(rv.as_ref())?;
+            let value = field_schema
+                .typ
+                .parse_to_value(rv.as_ref())
+                .map_err(|e| in_field(e, &schema.code, &field_schema.name))?;
             values.push(value);
         }
-        let extra_schema_fields = field_schemas.count();
-        if extra_schema_fields > 0 {
-            log::warn!("warning: extra_schema_fields: {}", extra_schema_fields);
+        let missing = field_schemas.count();
+        if missing > 0 {
+            log::debug!(
+                "{} line has {} fewer values than its schema",
+                schema.code,
+                missing
+            );
         }
         Ok(values)
     }
 }
 
+/// Say which field of which line a value that failed to parse came from.
+fn in_field(e: Error, line_code: &str, field: &str) -> Error {
+    match e {
+        Error::RecordParseError(RecordParseError::InvalidValue {
+            typ, raw, reason, ..
+        }) => RecordParseError::InvalidValue {
+            typ,
+            raw,
+            reason,
+            line_code: Some(line_code.to_string()),
+            field: Some(field.to_string()),
+        }
+        .into(),
+        e => e,
+    }
+}
+
 /// The first value in each line is the record type code.
 fn get_record_code<'a, T>(mut line: T) -> Result<(&'a str, T), Error>
 where
     T: Iterator<Item = &'a String>,
 {
-    let record_code = line
-        .next()
-        .ok_or(Error::RecordParseError("No form name".to_string()))?;
+    let record_code = line.next().ok_or(RecordParseError::NoLineCode)?;
     Ok((record_code, line))
 }
 
-pub struct CoercingLineParser;
+/// How a [CoercingLineParser] treats lines that don't match their schema.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ParseMode {
+    /// Keep extra values as strings, fill in missing values with nulls,
+    /// and turn values that don't parse as their type into nulls.
+    /// This is the default, since real files deviate from the schemas a lot.
+    #[default]
+    Lenient,
+    /// Fail the line if it has extra or missing values,
+    /// or a value that doesn't parse as its type, including dates that
+    /// would otherwise be kept as a [Value::InvalidDate].
+    Strict,
+}
+
+/// Transforms a value after it has been parsed to its type,
+/// eg to trim or uppercase it. See [CoercingLineParser::add_field_transform].
+pub type FieldTransform = Box<dyn Fn(Value) -> Value + Send + Sync>;
+
+/// A [FieldTransform] that can be cloned along with its parser.
+type SharedTransform = Arc<dyn Fn(Value) -> Value + Send + Sync>;
+
+/// A LineParser that parses each value to the type in the schema.
+///
+/// In [ParseMode::Lenient], values that don't parse as their type
+/// become typed nulls.
+/// The type of individual fields can be overridden with
+/// [CoercingLineParser::set_field_type], eg to normalize messy historical data,
+/// and values can be cleaned up with [CoercingLineParser::add_field_transform].
+#[derive(Clone, Default)]
+pub struct CoercingLineParser {
+    /// Keyed by the uppercased line code and the field name.
+    field_types: HashMap<(String, String), ValueType>,
+    /// Keyed like `field_types`.
+    field_transforms: HashMap<(String, String), Vec<SharedTransform>>,
+    /// How many values have failed to parse as their type, and become nulls.
+    coercion_failures: usize,
+    mode: ParseMode,
+    /// See [CoercingLineParser::set_lenient_numbers].
+    lenient_numbers: bool,
+    /// The schemas with the `field_types` applied, keyed by the uppercased
+    /// line code, along with the schema each was made from.
+    typed_schemas: HashMap<String, (Arc<RecordSchema>, Arc<RecordSchema>)>,
+}
+
+impl CoercingLineParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_mode(&mut self, mode: ParseMode) {
+        self.mode = mode;
+    }
+
+    /// If true, numbers may have a leading "$" and thousands separators,
+    /// eg "$1,234.56", see [ValueType::parse_to_value_lenient].
+    ///
+    /// Only applies in [ParseMode::Lenient], so [ParseMode::Strict]
+    /// still rejects them.
+    pub fn set_lenient_numbers(&mut self, lenient: bool) {
+        self.lenient_numbers = lenient;
+    }
+
+    /// Parse the field `field_name` of lines with code `line_code` as `typ`,
+    /// instead of the type declared in the schema.
+    ///
+    /// The line code must match exactly (case-insensitive), eg "SA11AI".
+    /// For aliased line codes, this can be the code in the file or the
+    /// canonical code, and the code in the file wins.
+    pub fn set_field_type(&mut self, line_code: &str, field_name: &str, typ: ValueType) {
+        let key = (line_code.to_uppercase(), field_name.to_string());
+        self.field_types.insert(key, typ);
+        self.typed_schemas.clear();
+    }
+
+    /// Pass every value of the field `field_name` of lines with code
+    /// `line_code` through `transform`, after it is parsed to its type.
+    ///
+    /// The line code must match like in [CoercingLineParser::set_field_type].
+    /// If several transforms are added for a field, they run in the order
+    /// they were added.
+    pub fn add_field_transform(
+        &mut self,
+        line_code: &str,
+        field_name: &str,
+        transform: FieldTransform,
+    ) {
+        let key = (line_code.to_uppercase(), field_name.to_string());
+        self.field_transforms
+            .entry(key)
+            .or_default()
+            .push(Arc::from(transform));
+    }
+
+    /// Parse `raw` as `typ` like a field would be in [ParseMode::Lenient],
+    /// see [CoercingLineParser::set_lenient_numbers].
+    pub(crate) fn parse_lenient(
+        &self,
+        typ: ValueType,
+        raw: Option<&String>,
+    ) -> Result<Value, Error> {
+        match self.lenient_numbers {
+            true => typ.parse_to_value_lenient(raw),
+            false => typ.parse_to_value(raw),
+        }
+    }
+
+    /// How many values have become nulls because they didn't parse as
+    /// their type, since the last call. Resets the count.
+    pub fn take_coercion_failures(&mut self) -> usize {
+        std::mem::take(&mut self.coercion_failures)
+    }
+
+    /// Look up the override for `field` of a line with code `line_code`,
+    /// falling back to the schema's code if it was aliased.
+    fn lookup<'m, T>(
+        overrides: &'m HashMap<(String, String), T>,
+        line_code: &str,
+        schema: &RecordSchema,
+        field: &FieldSchema,
+    ) -> Option<&'m T> {
+        let key = (line_code.to_uppercase(), field.name.clone());
+        overrides.get(&key).or_else(|| {
+            let canonical = (schema.code.to_uppercase(), field.name.clone());
+            (canonical != key)
+                .then(|| overrides.get(&canonical))
+                .flatten()
+        })
+    }
+
+    fn field_type(&self, line_code: &str, schema: &RecordSchema, field: &FieldSchema) -> ValueType {
+        if self.field_types.is_empty() {
+            return field.typ;
+        }
+        Self::lookup(&self.field_types, line_code, schema, field)
+            .copied()
+            .unwrap_or(field.typ)
+    }
+
+    fn transform(
+        &self,
+        line_code: &str,
+        schema: &RecordSchema,
+        field: &FieldSchema,
+        value: Value,
+    ) -> Value {
+        if self.field_transforms.is_empty() {
+            return value;
+        }
+        match Self::lookup(&self.field_transforms, line_code, schema, field) {
+            Some(transforms) => transforms.iter().fold(value, |v, f| f(v)),
+            None => value,
+        }
+    }
+}
+
+impl fmt::Debug for CoercingLineParser {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CoercingLineParser")
+            .field("field_types", &self.field_types)
+            .field("field_transforms", &self.field_transforms.keys())
+            .field("coercion_failures", &self.coercion_failures)
+            .field("mode", &self.mode)
+            .field("lenient_numbers", &self.lenient_numbers)
+            .finish()
+    }
+}
 
 impl<'a> LineParser<'a> for CoercingLineParser {
     fn parse_values(
         &mut self,
         schema: &RecordSchema,
         line: &mut impl Iterator<Item = &'a String>,
+    ) -> Result<Vec<Value>, Error> {
+        self.parse_values_as(&schema.code, schema, line)
+    }
+
+    /// `schema`, with the types of the fields from
+    /// [CoercingLineParser::set_field_type].
+    fn record_schema(&mut self, line_code: &str, schema: Arc<RecordSchema>) -> Arc<RecordSchema> {
+        if self.field_types.is_empty() {
+            return schema;
+        }
+        let key = line_code.to_uppercase();
+        if let Some((source, typed)) = self.typed_schemas.get(&key) {
+            if Arc::ptr_eq(source, &schema) {
+                return typed.clone();
+            }
+        }
+        let fields = schema
+            .fields
+            .iter()
+            .map(|field| FieldSchema {
+                typ: self.field_type(line_code, &schema, field),
+                ..field.clone()
+            })
+            .collect::<Vec<_>>();
+        let changed = fields
+            .iter()
+            .zip(&schema.fields)
+            .any(|(a, b)| a.typ != b.typ);
+        let typed = match changed {
+            true => Arc::new(RecordSchema {
+                fields,
+                ..(*schema).clone()
+            }),
+            false => schema.clone(),
+        };
+        self.typed_schemas.insert(key, (schema, typed.clone()));
+        typed
+    }
+
+    /// Parse the values, looking up [CoercingLineParser::set_field_type]
+    /// and [CoercingLineParser::add_field_transform] by `line_code`
+    /// before `schema.code`.
+    fn parse_values_as(
+        &mut self,
+        line_code: &str,
+        schema: &RecordSchema,
+        line: &mut impl Iterator<Item = &'a String>,
     ) -> Result<Vec<Value>, Error> {
         let mut field_schemas = schema.fields.iter();
         let mut values = Vec::new();
         for raw in line {
-            let field_type = match field_schemas.next() {
-                Some(field_schema) => field_schema.typ,
+            let field_schema = match field_schemas.next() {
+                Some(field_schema) => field_schema,
+                None if self.mode == ParseMode::Strict => {
+                    return Err(RecordParseError::TooManyValues {
+                        expected: schema.fields.len(),
+                    }
+                    .into());
+                }
                 None => {
                     let default_value = Value::String(Some(raw.clone()));
                     values.push(default_value);
                     continue;
                 }
             };
-            let value = match field_type.parse_to_value(Some(raw)) {
+            let field_type = self.field_type(line_code, schema, field_schema);
+            let parsed = match self.mode {
+                ParseMode::Strict => field_type.parse_to_value_strict(Some(raw)),
+                _ if self.lenient_numbers => field_type.parse_to_value_lenient(Some(raw)),
+                _ => field_type.parse_to_value(Some(raw)),
+            };
+            let value = match parsed {
                 Ok(value) => value,
-                Err(_) => field_type.parse_to_value(None)?,
+                Err(e) if self.mode == ParseMode::Strict => {
+                    return Err(in_field(e, line_code, &field_schema.name))
+                }
+                Err(_) => {
+                    self.coercion_failures += 1;
+                    field_type.parse_to_value(None)?
+                }
             };
-            values.push(value);
+            values.push(self.transform(line_code, schema, field_schema, value));
         }
         let not_seen_fields = field_schemas;
+        if self.mode == ParseMode::Strict && not_seen_fields.len() > 0 {
+            return Err(RecordParseError::TooFewValues {
+                expected: schema.fields.len(),
+                found: values.len(),
+            }
+            .into());
+        }
         for f in not_seen_fields {
-            let value = f.typ.parse_to_value(None)?;
-            values.push(value);
+            let value = self.field_type(line_code, schema, f).parse_to_value(None)?;
+            values.push(self.transform(line_code, schema, f, value));
         }
-        assert!(values.len() == schema.fields.len());
         Ok(values)
     }
 }
@@ -0,0 +1,93 @@
+//! Tally how often the lines of a file deviate from their schemas.
+
+use std::collections::HashMap;
+
+use crate::fec::RowError;
+use crate::record::Record;
+
+/// How the lines with one line code compared to their schema.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LineCodeStats {
+    /// How many records were parsed.
+    pub records: u64,
+    /// How many records had fewer values than the schema has fields.
+    pub fewer_fields: u64,
+    /// How many records had more values than the schema has fields.
+    pub more_fields: u64,
+    /// How many values couldn't be parsed as their field's type,
+    /// and became nulls instead.
+    pub coercion_failures: u64,
+}
+
+/// See [FecFile::stats](crate::FecFile::stats).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ParseStats {
+    /// Keyed by the line code as it appears in the file, eg "SA11AI".
+    pub by_line_code: HashMap<String, LineCodeStats>,
+}
+
+impl ParseStats {
+    pub(crate) fn add(&mut self, record: &Record, coercion_failures: usize) {
+        let stats = self
+            .by_line_code
+            .entry(record.record_code.clone())
+            .or_default();
+        stats.records += 1;
+        if record.missing_fields > 0 {
+            stats.fewer_fields += 1;
+        }
+        if record.had_extra_fields() {
+            stats.more_fields += 1;
+        }
+        stats.coercion_failures += coercion_failures as u64;
+    }
+
+    pub(crate) fn add_coercion_failure(&mut self, line_code: &str) {
+        let stats = self.by_line_code.entry(line_code.to_string()).or_default();
+        stats.coercion_failures += 1;
+    }
+}
+
+/// A line code whose count in the file doesn't match the count the
+/// header or cover line declares, see
+/// [FecFile::check_schedule_counts](crate::FecFile::check_schedule_counts).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CountMismatch {
+    /// The line code, uppercased, eg "SA11A1".
+    pub line_code: String,
+    /// How many lines the header or cover line says there are.
+    /// Only declared line codes are checked, so this is always from one.
+    pub declared: u64,
+    /// How many lines there actually are.
+    pub actual: u64,
+}
+
+/// The result of [FecFile::validate](crate::FecFile::validate).
+#[derive(Debug)]
+pub struct ValidationReport {
+    /// How many records parsed successfully.
+    pub records: u64,
+    /// Every row that failed to parse, with its index.
+    pub errors: Vec<RowError>,
+    /// How the records that did parse deviated from their schemas.
+    pub stats: ParseStats,
+}
+
+impl ValidationReport {
+    /// True if no rows failed to parse.
+    ///
+    /// Records that deviated from their schemas, but could still be
+    /// parsed, don't count. Check [ValidationReport::stats] for those.
+    pub fn is_valid(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// How many records of each line code parsed successfully.
+    pub fn records_by_line_code(&self) -> HashMap<&str, u64> {
+        self.stats
+            .by_line_code
+            .iter()
+            .map(|(code, stats)| (code.as_str(), stats.records))
+            .collect()
+    }
+}
@@ -1,6 +1,7 @@
 //! Convert [Record]s into arrow [RecordBatch]es.
 use arrow::array::{
-    ArrayBuilder, BooleanBuilder, Date32Builder, Float64Builder, Int64Builder, StringBuilder,
+    ArrayBuilder, BooleanBuilder, Date32Builder, Decimal128Builder, Float64Builder, Int64Builder,
+    ListBuilder, StringBuilder,
 };
 use arrow::datatypes::Date32Type;
 use arrow::{
@@ -9,7 +10,6 @@ use arrow::{
 };
 use std::sync::Arc;
 
-use crate::schemas::{CoercingLineParser, LineParser};
 use crate::{record::Record, Error, FecFile};
 use crate::{
     record::{FieldSchema, RecordSchema, Value, ValueType},
@@ -18,12 +18,22 @@ use crate::{
 
 use super::base::{MultiRecordWriter, RecordWriterFactory};
 
+/// How many digits after the decimal point [Value::Decimal]s are stored with.
+///
+/// Decimals with more digits than this are rounded, but FEC amounts
+/// are in cents, so there is plenty of room.
+pub const DECIMAL_SCALE: i8 = 6;
+
+/// The most digits a [DataType::Decimal128] can have.
+const DECIMAL_PRECISION: u8 = 38;
+
 /// Convert a [ValueType] into the arrow equivalent, an arrow [DataType].
 pub fn value_type_to_arrow_type(vt: &ValueType) -> DataType {
     match vt {
         ValueType::String => DataType::Utf8,
         ValueType::Integer => DataType::Int64,
         ValueType::Float => DataType::Float64,
+        ValueType::Decimal => DataType::Decimal128(DECIMAL_PRECISION, DECIMAL_SCALE),
         ValueType::Date => DataType::Date32,
         ValueType::Boolean => DataType::Boolean,
     }
@@ -35,11 +45,20 @@ pub fn field_schema_to_arrow_field(fs: &FieldSchema) -> Field {
 }
 
 /// Convert a [RecordSchema] into the arrow equivalent, an arrow [Schema]
+///
+/// The fields of the repeating group, if any, become list columns.
 pub fn record_schema_to_arrow_schema(rs: &RecordSchema) -> Schema {
     let fields = rs
         .fields
         .iter()
-        .map(field_schema_to_arrow_field)
+        .enumerate()
+        .map(|(i, fs)| match rs.is_repeating(i) {
+            false => field_schema_to_arrow_field(fs),
+            true => {
+                let item = Field::new("item", value_type_to_arrow_type(&fs.typ), true);
+                Field::new(fs.name.clone(), DataType::List(Arc::new(item)), true)
+            }
+        })
         .collect::<Vec<_>>();
     Schema::new(fields)
 }
@@ -52,6 +71,8 @@ pub fn record_schema_to_arrow_schema(rs: &RecordSchema) -> Schema {
 pub struct RecordBatchWriter {
     feco3_schema: RecordSchema,
     builders: Vec<Box<dyn ArrayBuilder>>,
+    /// See [RecordBatchWriter::buffered_bytes].
+    buffered_bytes: usize,
 }
 
 impl RecordBatchWriter {
@@ -61,11 +82,13 @@ impl RecordBatchWriter {
         Self {
             feco3_schema,
             builders,
+            buffered_bytes: 0,
         }
     }
 
     /// Build and return the accumulated [RecordBatch], and reset itself.
     pub fn build_batch(&mut self) -> RecordBatch {
+        self.buffered_bytes = 0;
         let arrays = self
             .builders
             .iter_mut()
@@ -79,18 +102,34 @@ impl RecordBatchWriter {
     pub fn len(&self) -> usize {
         self.builders[0].len()
     }
+
+    /// Whether there are no records buffered.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Roughly how much memory the buffered records take up.
+    ///
+    /// This counts the bytes of the values themselves, not the overhead
+    /// of the arrow builders, so it is only good for comparing writers
+    /// and enforcing a rough budget.
+    pub fn buffered_bytes(&self) -> usize {
+        self.buffered_bytes
+    }
 }
 
 impl RecordWriter for RecordBatchWriter {
     fn write_record(&mut self, record: &Record) -> std::io::Result<()> {
-        if record.schema != self.feco3_schema {
+        if *record.schema != self.feco3_schema {
             return Err(std::io::Error::new(
                 std::io::ErrorKind::InvalidInput,
                 "record schema does not match writer schema",
             ));
         }
-        for (i, val) in record.values.iter().enumerate() {
-            append_value_to_builder(&mut *self.builders[i], val);
+        // Any extra values past the end of the schema have no column to go in.
+        for (builder, val) in self.builders.iter_mut().zip(record.values.iter()) {
+            append_value_to_builder(&mut **builder, val);
+            self.buffered_bytes += value_size(val);
         }
         Ok(())
     }
@@ -123,9 +162,25 @@ pub struct ItemizationBatch {
     pub record_batch: RecordBatch,
 }
 
+/// Parse a [FecFile] into arrow [RecordBatch]es, for in-memory analytics.
+///
+/// Records are buffered per line code, and a batch is returned
+/// as soon as `max_batch_size` records of one line code have accumulated.
+/// Once the file is exhausted, the leftover partial batches are returned.
+/// Every batch only holds records of a single line code, so they can be
+/// handed straight to eg Polars or DataFusion.
+///
+/// Columns are typed according to [value_type_to_arrow_type].
+/// Values past the end of a line's schema are dropped.
+///
+/// Files with hundreds of rare line codes can buffer a lot of partial
+/// batches, so see [RecordBatchProcessor::with_memory_budget].
 pub struct RecordBatchProcessor {
     multi_writer: MultiRecordWriter<RecordBatchWriterFactory>,
     max_batch_size: usize,
+    memory_budget: Option<usize>,
+    /// The total [RecordBatchWriter::buffered_bytes] of all the writers.
+    buffered_bytes: usize,
 }
 
 impl RecordBatchProcessor {
@@ -134,42 +189,76 @@ impl RecordBatchProcessor {
         Self {
             multi_writer: MultiRecordWriter::new(factory),
             max_batch_size,
+            memory_budget: None,
+            buffered_bytes: 0,
         }
     }
 
+    /// Once the buffered records take up more than roughly `bytes`,
+    /// return the largest partial batch early to free up memory.
+    ///
+    /// See [RecordBatchWriter::buffered_bytes] for how this is estimated.
+    pub fn with_memory_budget(mut self, bytes: usize) -> Self {
+        self.memory_budget = Some(bytes);
+        self
+    }
+
+    /// Get the next batch, or None if the file has been exhausted.
     pub fn next_batch(&mut self, fec: &mut FecFile) -> Result<Option<ItemizationBatch>, Error> {
-        let mut parser = CoercingLineParser;
-        let fec_version = fec.get_header()?.fec_version.clone();
         loop {
-            let line = match fec.next_line() {
-                Some(Ok(line)) => line,
+            let record = match fec.next_record() {
+                Some(Ok(record)) => record,
                 Some(Err(e)) => return Err(e),
                 None => {
                     return Ok(self.get_leftover_batch());
                 }
             };
-            let record: Record = parser.parse_line(&fec_version, &mut line.iter())?;
             let writer = self.multi_writer.get_writer(&record.schema)?;
+            let before = writer.buffered_bytes();
             writer.write_record(&record)?;
+            self.buffered_bytes += writer.buffered_bytes() - before;
             if writer.len() >= self.max_batch_size {
+                self.buffered_bytes -= writer.buffered_bytes();
                 return Ok(Some(ItemizationBatch {
-                    record_code: record.record_code.clone(),
+                    record_code: record.schema.code.clone(),
                     record_batch: writer.build_batch(),
                 }));
             }
+            if self.memory_budget.is_some_and(|b| self.buffered_bytes > b) {
+                return Ok(self.get_largest_batch());
+            }
         }
     }
 
     fn get_leftover_batch(&mut self) -> Option<ItemizationBatch> {
         for (record_schema, writer) in self.multi_writer.writers.iter_mut() {
-            if writer.len() > 0 {
+            if !writer.is_empty() {
+                self.buffered_bytes -= writer.buffered_bytes();
                 return Some(ItemizationBatch {
                     record_code: record_schema.code.clone(),
                     record_batch: writer.build_batch(),
                 });
             }
         }
-        return None;
+        None
+    }
+
+    fn get_largest_batch(&mut self) -> Option<ItemizationBatch> {
+        let (record_schema, writer) = self
+            .multi_writer
+            .writers
+            .iter_mut()
+            .max_by_key(|(_, writer)| writer.buffered_bytes())?;
+        log::debug!(
+            "over the memory budget, returning {} {} records early",
+            writer.len(),
+            record_schema.code
+        );
+        self.buffered_bytes -= writer.buffered_bytes();
+        Some(ItemizationBatch {
+            record_code: record_schema.code.clone(),
+            record_batch: writer.build_batch(),
+        })
     }
 }
 
@@ -177,10 +266,40 @@ fn builders_from_schema(schema: &Schema, capacity: usize) -> Vec<Box<dyn ArrayBu
     schema
         .fields
         .iter()
-        .map(|fs| arrow::array::make_builder(fs.data_type(), capacity))
+        .map(|fs| match fs.data_type() {
+            // make_builder doesn't support lists.
+            DataType::List(item) => make_list_builder(item.data_type(), capacity),
+            typ => arrow::array::make_builder(typ, capacity),
+        })
         .collect()
 }
 
+fn make_list_builder(item: &DataType, capacity: usize) -> Box<dyn ArrayBuilder> {
+    match item {
+        DataType::Utf8 => Box::new(ListBuilder::with_capacity(StringBuilder::new(), capacity)),
+        DataType::Int64 => Box::new(ListBuilder::with_capacity(Int64Builder::new(), capacity)),
+        DataType::Float64 => Box::new(ListBuilder::with_capacity(Float64Builder::new(), capacity)),
+        DataType::Decimal128(_, _) => Box::new(ListBuilder::with_capacity(
+            Decimal128Builder::new().with_data_type(item.clone()),
+            capacity,
+        )),
+        DataType::Date32 => Box::new(ListBuilder::with_capacity(Date32Builder::new(), capacity)),
+        DataType::Boolean => Box::new(ListBuilder::with_capacity(BooleanBuilder::new(), capacity)),
+        t => panic!("unsupported list item type {:?}", t),
+    }
+}
+
+/// Roughly how many bytes `val` takes up in an arrow array.
+fn value_size(val: &Value) -> usize {
+    match val {
+        Value::String(Some(s)) => s.len(),
+        Value::Boolean(_) => 1,
+        Value::Decimal(_) => std::mem::size_of::<i128>(),
+        Value::List(items) => items.iter().map(value_size).sum(),
+        _ => std::mem::size_of::<i64>(),
+    }
+}
+
 fn append_value_to_builder(builder: &mut dyn ArrayBuilder, val: &Value) {
     match val {
         Value::Integer(i) => builder
@@ -193,6 +312,14 @@ fn append_value_to_builder(builder: &mut dyn ArrayBuilder, val: &Value) {
             .downcast_mut::<Float64Builder>()
             .unwrap()
             .append_option(*f),
+        Value::Decimal(d) => builder
+            .as_any_mut()
+            .downcast_mut::<Decimal128Builder>()
+            .unwrap()
+            .append_option(d.map(|mut d| {
+                d.rescale(DECIMAL_SCALE as u32);
+                d.mantissa()
+            })),
         Value::String(s) => builder
             .as_any_mut()
             .downcast_mut::<StringBuilder>()
@@ -203,10 +330,37 @@ fn append_value_to_builder(builder: &mut dyn ArrayBuilder, val: &Value) {
             .downcast_mut::<Date32Builder>()
             .unwrap()
             .append_option(d.map(Date32Type::from_naive_date)),
+        Value::InvalidDate(_) => builder
+            .as_any_mut()
+            .downcast_mut::<Date32Builder>()
+            .unwrap()
+            .append_null(),
         Value::Boolean(b) => builder
             .as_any_mut()
             .downcast_mut::<BooleanBuilder>()
             .unwrap()
             .append_option(*b),
+        Value::List(items) => {
+            let appended = append_list::<StringBuilder>(builder, items)
+                || append_list::<Int64Builder>(builder, items)
+                || append_list::<Float64Builder>(builder, items)
+                || append_list::<Decimal128Builder>(builder, items)
+                || append_list::<Date32Builder>(builder, items)
+                || append_list::<BooleanBuilder>(builder, items);
+            assert!(appended, "not a list builder");
+        }
+    }
+}
+
+/// Append `items` as one list if `builder` is a list of `B`s.
+fn append_list<B: ArrayBuilder>(builder: &mut dyn ArrayBuilder, items: &[Value]) -> bool {
+    let builder = match builder.as_any_mut().downcast_mut::<ListBuilder<B>>() {
+        Some(builder) => builder,
+        None => return false,
+    };
+    for item in items {
+        append_value_to_builder(builder.values(), item);
     }
+    builder.append(true);
+    true
 }
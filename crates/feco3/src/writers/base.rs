@@ -3,15 +3,83 @@
 use std::{collections::HashMap, fs, path::PathBuf};
 
 use crate::record::{Record, RecordSchema};
-use crate::Error;
+use crate::{Error, FecFile};
 use std::collections::hash_map::Entry::{Occupied, Vacant};
 
 /// Writes single itemization records.
+///
+/// Implement this to add your own output format. The trait is object safe,
+/// so writers can be chosen at runtime and kept as `Box<dyn RecordWriter>`.
+///
+/// ```no_run
+/// use feco3::record::Record;
+/// use feco3::writers::base::RecordWriter;
+///
+/// /// Counts the records instead of writing them anywhere.
+/// #[derive(Default)]
+/// struct Counter(usize);
+///
+/// impl RecordWriter for Counter {
+///     fn write_record(&mut self, _record: &Record) -> std::io::Result<()> {
+///         self.0 += 1;
+///         Ok(())
+///     }
+/// }
+///
+/// let mut writer: Box<dyn RecordWriter> = Box::new(Counter::default());
+/// let mut fec = feco3::FecFile::from_path(&"filing.fec".into())?;
+/// writer.write_all(&mut fec)?;
+/// # Ok::<(), feco3::Error>(())
+/// ```
 pub trait RecordWriter: Send {
     fn write_record(&mut self, record: &Record) -> std::io::Result<()>;
+    /// Write out anything still buffered, and any footer the format needs.
+    ///
+    /// Call this once after the last record. Until then, the output may be
+    /// incomplete or even invalid, eg a Parquet file without its footer.
+    /// Don't rely on [Drop] for this, since errors there can't be returned,
+    /// and it doesn't run at all if the process exits first.
+    /// Calling this again, or writing more records afterwards, is a bug,
+    /// but writers should try not to panic if it happens.
     fn finish(&mut self) -> Result<(), Error> {
         Ok(())
     }
+
+    /// Write every remaining record of `fec`, then [RecordWriter::finish].
+    fn write_all(&mut self, fec: &mut FecFile) -> Result<(), Error> {
+        for record in fec.records() {
+            self.write_record(&record?)?;
+        }
+        self.finish()
+    }
+}
+
+impl<W: RecordWriter + ?Sized> RecordWriter for Box<W> {
+    fn write_record(&mut self, record: &Record) -> std::io::Result<()> {
+        (**self).write_record(record)
+    }
+
+    fn finish(&mut self) -> Result<(), Error> {
+        (**self).finish()
+    }
+
+    fn write_all(&mut self, fec: &mut FecFile) -> Result<(), Error> {
+        (**self).write_all(fec)
+    }
+}
+
+impl<W: RecordWriter + ?Sized> RecordWriter for &mut W {
+    fn write_record(&mut self, record: &Record) -> std::io::Result<()> {
+        (**self).write_record(record)
+    }
+
+    fn finish(&mut self) -> Result<(), Error> {
+        (**self).finish()
+    }
+
+    fn write_all(&mut self, fec: &mut FecFile) -> Result<(), Error> {
+        (**self).write_all(fec)
+    }
 }
 
 /// Creates [RecordWriter]s given a schema.
@@ -26,6 +94,8 @@ pub trait FileRecordWriterFactory: Send {
     type Writer: RecordWriter;
     fn file_name(&self, form_name: String) -> String;
     /// Make a new [RecordWriter] for a given schema that writes to the given path.
+    // Changing this to `&Path` would break every implementation outside the crate.
+    #[allow(clippy::ptr_arg)]
     fn make(&mut self, path: &PathBuf, schema: &RecordSchema) -> std::io::Result<Self::Writer>;
 
     /// Some forms have a slash in their name, which is not allowed in file names.
@@ -71,6 +141,73 @@ impl<F: RecordWriterFactory> RecordWriter for MultiRecordWriter<F> {
     }
 }
 
+/// A [RecordWriter] that writes every record to several [RecordWriter]s,
+/// eg to get both CSV and Parquet out of one pass over a file.
+///
+/// If some of the writers fail, the rest are still written to, and the
+/// errors are combined into one. The writers can be borrowed, so they can
+/// be inspected afterwards, eg `TeeWriter::new(vec![Box::new(&mut a), Box::new(&mut b)])`.
+pub struct TeeWriter<'a> {
+    writers: Vec<Box<dyn RecordWriter + 'a>>,
+}
+
+impl<'a> TeeWriter<'a> {
+    pub fn new(writers: Vec<Box<dyn RecordWriter + 'a>>) -> Self {
+        Self { writers }
+    }
+
+    /// Also write to `writer`.
+    pub fn with_writer(mut self, writer: Box<dyn RecordWriter + 'a>) -> Self {
+        self.writers.push(writer);
+        self
+    }
+}
+
+impl RecordWriter for TeeWriter<'_> {
+    fn write_record(&mut self, record: &Record) -> std::io::Result<()> {
+        let errors = self
+            .writers
+            .iter_mut()
+            .filter_map(|w| w.write_record(record).err())
+            .collect::<Vec<_>>();
+        match combine_errors(errors) {
+            None => Ok(()),
+            Some(Ok(e)) => Err(e),
+            Some(Err(message)) => Err(std::io::Error::other(message)),
+        }
+    }
+
+    fn finish(&mut self) -> Result<(), Error> {
+        let errors = self
+            .writers
+            .iter_mut()
+            .filter_map(|w| w.finish().err())
+            .collect::<Vec<_>>();
+        match combine_errors(errors) {
+            None => Ok(()),
+            Some(Ok(e)) => Err(e),
+            Some(Err(message)) => Err(std::io::Error::other(message).into()),
+        }
+    }
+}
+
+/// The only error of `errors` as is, or the messages of all of them
+/// joined together, or None if there are none.
+fn combine_errors<E: std::fmt::Display>(mut errors: Vec<E>) -> Option<Result<E, String>> {
+    match errors.len() {
+        0 => None,
+        1 => errors.pop().map(Ok),
+        n => {
+            let messages = errors.iter().map(|e| e.to_string()).collect::<Vec<_>>();
+            Some(Err(format!(
+                "{} writers failed: {}",
+                n,
+                messages.join("; ")
+            )))
+        }
+    }
+}
+
 /// A [RecordWriterFactory] that uses a new [FileRecordWriterFactory] for each new form.
 pub struct MultiFileRecordWriterFactory<F: FileRecordWriterFactory> {
     base_path: PathBuf,
@@ -2,21 +2,95 @@ use super::base::{
     FileRecordWriterFactory, MultiFileRecordWriterFactory, MultiRecordWriter, RecordWriter,
 };
 use crate::{
-    record::{Record, RecordSchema},
-    schemas::{CoercingLineParser, LineParser},
-    Error, FecFile,
+    record::{humanize, Record, RecordSchema},
+    Cover, Error, FecFile, Header,
 };
-use std::{fs::File, path::PathBuf};
+use serde::Serialize;
+use std::{
+    borrow::Cow,
+    collections::hash_map::Entry::{Occupied, Vacant},
+    collections::HashMap,
+    fs::File,
+    path::{Path, PathBuf},
+};
+
+/// The character encoding to write CSV files in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CsvEncoding {
+    #[default]
+    Utf8,
+    /// ISO-8859-1, which can only represent the first 256 code points.
+    Latin1,
+    /// Like [CsvEncoding::Latin1], but with printable characters such as
+    /// "€" and curly quotes in place of the C1 control characters.
+    Windows1252,
+}
+
+/// What to do with characters that can't be represented in the
+/// [CsvEncoding] of the output, eg "€" in Latin-1.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Unrepresentable {
+    /// Write a "?" instead.
+    #[default]
+    Replace,
+    /// Leave the character out.
+    Drop,
+}
+
+/// Transcodes the values written to a CSV, see [CSVProcessor::with_encoding].
+#[derive(Debug, Clone, Copy, Default)]
+struct Encoder {
+    encoding: CsvEncoding,
+    unrepresentable: Unrepresentable,
+}
+
+impl Encoder {
+    fn encode<'a>(&self, s: &'a str) -> Cow<'a, [u8]> {
+        if self.encoding == CsvEncoding::Utf8 || s.is_ascii() {
+            return Cow::Borrowed(s.as_bytes());
+        }
+        let mut out = Vec::with_capacity(s.len());
+        let mut buf = [0u8; 4];
+        for c in s.chars() {
+            let byte = match self.encoding {
+                CsvEncoding::Latin1 => u8::try_from(u32::from(c)).ok(),
+                _ => {
+                    let (bytes, _, had_errors) =
+                        encoding_rs::WINDOWS_1252.encode(c.encode_utf8(&mut buf));
+                    (!had_errors).then(|| bytes[0])
+                }
+            };
+            match (byte, self.unrepresentable) {
+                (Some(byte), _) => out.push(byte),
+                (None, Unrepresentable::Replace) => out.push(b'?'),
+                (None, Unrepresentable::Drop) => {}
+            }
+        }
+        Cow::Owned(out)
+    }
+
+    /// The inverse of [Encoder::encode], for reading back what was written.
+    fn decode(&self, bytes: &[u8]) -> String {
+        match self.encoding {
+            CsvEncoding::Utf8 => String::from_utf8_lossy(bytes).into_owned(),
+            CsvEncoding::Latin1 => bytes.iter().map(|&b| char::from(b)).collect(),
+            CsvEncoding::Windows1252 => encoding_rs::WINDOWS_1252.decode(bytes).0.into_owned(),
+        }
+    }
+}
 
 /// A [RecordWriter] that writes to CSV format.
 struct CSVFormWriter<W: std::io::Write> {
     csv_writer: csv::Writer<W>,
     schema: RecordSchema,
     has_written_header: bool,
+    /// Write the field labels in the header instead of the names.
+    use_labels: bool,
+    encoder: Encoder,
 }
 
 impl<W: std::io::Write> CSVFormWriter<W> {
-    fn new(raw_writer: W, schema: &RecordSchema) -> Self {
+    fn new(raw_writer: W, schema: &RecordSchema, use_labels: bool, encoder: Encoder) -> Self {
         let writer = csv::WriterBuilder::new()
             .has_headers(false) // We'll write the header ourselves
             .flexible(true)
@@ -25,6 +99,8 @@ impl<W: std::io::Write> CSVFormWriter<W> {
             csv_writer: writer,
             schema: schema.clone(),
             has_written_header: false,
+            use_labels,
+            encoder,
         }
     }
 
@@ -35,8 +111,16 @@ impl<W: std::io::Write> CSVFormWriter<W> {
         }
         self.has_written_header = true;
         let fields = &self.schema.fields;
-        let field_names = fields.iter().map(|f| f.name.as_str());
-        self.csv_writer.write_record(field_names)?;
+        let field_names = fields
+            .iter()
+            .map(|f| match self.use_labels {
+                true => f.display_label(),
+                false => f.name.clone(),
+            })
+            .collect::<Vec<_>>();
+        let encoder = self.encoder;
+        self.csv_writer
+            .write_record(field_names.iter().map(|name| encoder.encode(name)))?;
         Ok(())
     }
 }
@@ -45,13 +129,181 @@ impl<W: std::io::Write + Send> RecordWriter for CSVFormWriter<W> {
     fn write_record(&mut self, record: &Record) -> std::io::Result<()> {
         self.maybe_write_header()?;
         // TODO: Check the length of values vs the schema
-        let string_values = record.values.iter().map(|v| v.to_string());
-        self.csv_writer.write_record(string_values)?;
+        let string_values = record
+            .values
+            .iter()
+            .map(|v| v.to_string())
+            .collect::<Vec<_>>();
+        let encoder = self.encoder;
+        self.csv_writer
+            .write_record(string_values.iter().map(|v| encoder.encode(v)))?;
         Ok(())
     }
+
+    fn finish(&mut self) -> Result<(), Error> {
+        self.csv_writer.flush()?;
+        Ok(())
+    }
+}
+
+/// A [RecordWriter] that writes every record to a single, wide CSV.
+///
+/// The columns are "line_code", followed by the union of the fields of
+/// every line code seen, in the order they were first seen.
+/// Fields a record doesn't have are left as empty cells.
+/// Values past the end of a record's schema get columns "extra_1", etc.
+/// If a schema has the same field name twice, the last value wins.
+///
+/// Since the full set of columns isn't known until every record has been
+/// seen, all rows are buffered in memory and only written on
+/// [RecordWriter::finish]. For large files, prefer [CSVProcessor]
+/// (one file per line code), or the streaming
+/// [NdjsonWriter](crate::writers::ndjson::NdjsonWriter), which doesn't
+/// need to know the columns up front.
+pub struct CSVSingleFileWriter<W: std::io::Write> {
+    writer: W,
+    /// If false, only write the "line_code" column when there is more
+    /// than one line code.
+    always_write_line_code: bool,
+    /// If true, the header has [FieldSchema::display_label]s instead of names.
+    ///
+    /// [FieldSchema::display_label]: crate::record::FieldSchema::display_label
+    use_labels: bool,
+    encoder: Encoder,
+    /// Whether to write each record's [Record::source_id] in a column.
+    include_source_id: bool,
+    columns: Vec<String>,
+    /// The label of each column, in the same order as `columns`.
+    labels: Vec<String>,
+    column_indices: HashMap<String, usize>,
+    /// The line code and the (column index, value) pairs of each row.
+    rows: Vec<(String, Vec<(usize, String)>)>,
+}
+
+impl<W: std::io::Write> CSVSingleFileWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            always_write_line_code: true,
+            use_labels: false,
+            encoder: Encoder::default(),
+            include_source_id: false,
+            columns: Vec::new(),
+            labels: Vec::new(),
+            column_indices: HashMap::new(),
+            rows: Vec::new(),
+        }
+    }
+
+    /// If true, write human-readable labels in the header, eg
+    /// "Contributor Last Name" instead of "contributor_last_name".
+    pub fn with_labels(mut self, use_labels: bool) -> Self {
+        self.use_labels = use_labels;
+        self
+    }
+
+    /// If true, add a "source_id" column with each record's
+    /// [Record::source_id], to tell apart the records of several files.
+    pub fn with_source_id(mut self, include_source_id: bool) -> Self {
+        self.include_source_id = include_source_id;
+        self
+    }
+
+    /// Write in `encoding` instead of UTF-8, see [CSVProcessor::with_encoding].
+    pub fn with_encoding(
+        mut self,
+        encoding: CsvEncoding,
+        unrepresentable: Unrepresentable,
+    ) -> Self {
+        self.encoder = Encoder {
+            encoding,
+            unrepresentable,
+        };
+        self
+    }
+
+    /// Consume the writer, returning the underlying stream.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    fn column_index(&mut self, name: String, label: impl FnOnce() -> String) -> usize {
+        if let Some(i) = self.column_indices.get(&name) {
+            return *i;
+        }
+        let i = self.columns.len();
+        self.labels.push(label());
+        self.columns.push(name.clone());
+        self.column_indices.insert(name, i);
+        i
+    }
 }
 
-struct CSVFileWriterFactory;
+impl<W: std::io::Write + Send> RecordWriter for CSVSingleFileWriter<W> {
+    fn write_record(&mut self, record: &Record) -> std::io::Result<()> {
+        let fields = &record.schema.fields;
+        let mut cells = Vec::with_capacity(record.values.len() + 1);
+        if self.include_source_id {
+            let index = self.column_index("source_id".to_string(), || "Source ID".to_string());
+            let source_id = record.source_id.as_deref().unwrap_or_default();
+            cells.push((index, source_id.to_string()));
+        }
+        for (i, val) in record.values.iter().enumerate() {
+            let field = fields.get(i);
+            let name = match field {
+                Some(field) => field.name.clone(),
+                None => format!("extra_{}", i - fields.len() + 1),
+            };
+            let label = || match field {
+                Some(field) => field.display_label(),
+                None => humanize(&name),
+            };
+            let index = self.column_index(name.clone(), label);
+            cells.push((index, val.to_string()));
+        }
+        self.rows.push((record.record_code.clone(), cells));
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), Error> {
+        let write_line_code = self.always_write_line_code
+            || self.rows.iter().any(|(code, _)| *code != self.rows[0].0);
+        let offset = write_line_code as usize;
+        let mut csv_writer = csv::Writer::from_writer(&mut self.writer);
+        let (line_code, columns) = match self.use_labels {
+            true => ("Line Code", &self.labels),
+            false => ("line_code", &self.columns),
+        };
+        let line_code_header = write_line_code.then_some(line_code);
+        let encoder = self.encoder;
+        let header = line_code_header
+            .into_iter()
+            .chain(columns.iter().map(|c| c.as_str()))
+            .map(|c| encoder.encode(c));
+        csv_writer
+            .write_record(header)
+            .map_err(std::io::Error::from)?;
+        for (line_code, cells) in self.rows.drain(..) {
+            let mut row = vec![String::new(); self.columns.len() + offset];
+            if write_line_code {
+                row[0] = line_code;
+            }
+            for (i, val) in cells {
+                row[i + offset] = val;
+            }
+            csv_writer
+                .write_record(row.iter().map(|cell| encoder.encode(cell)))
+                .map_err(std::io::Error::from)?;
+        }
+        csv_writer.flush()?;
+        Ok(())
+    }
+}
+
+struct CSVFileWriterFactory {
+    use_labels: bool,
+    encoder: Encoder,
+}
 
 impl FileRecordWriterFactory for CSVFileWriterFactory {
     type Writer = CSVFormWriter<File>;
@@ -61,7 +313,7 @@ impl FileRecordWriterFactory for CSVFileWriterFactory {
 
     fn make(&mut self, path: &PathBuf, schema: &RecordSchema) -> std::io::Result<Self::Writer> {
         let file = File::create(path)?;
-        let writer = CSVFormWriter::new(file, schema);
+        let writer = CSVFormWriter::new(file, schema, self.use_labels, self.encoder);
         Ok(writer)
     }
 }
@@ -71,30 +323,256 @@ impl FileRecordWriterFactory for CSVFileWriterFactory {
 /// Each form type gets its own file. If the form type contains a "/"
 /// (which would result in a subdirectory), it is replaced with a "-".
 /// For example, "SC/10" would be written to "SC-10.csv".
+///
+/// Optionally, the [Header] and [Cover] are also written as JSON to
+/// a `_metadata.json` file in the same directory,
+/// since that context is otherwise lost when splitting into CSVs.
+///
+/// Use [CSVProcessor::with_naming] to choose the files yourself,
+/// and [CSVProcessor::with_labels] for headers meant for people.
 pub struct CSVProcessor {
     multi_writer: MultiRecordWriter<MultiFileRecordWriterFactory<CSVFileWriterFactory>>,
+    out_dir: PathBuf,
+    write_metadata: bool,
+    naming: Option<Naming>,
+    /// The writers for each path, when using custom naming.
+    named_writers: HashMap<PathBuf, NamedWriter>,
+    use_labels: bool,
+    encoder: Encoder,
+}
+
+/// Maps a schema to the path of the file its records are written to.
+pub type Naming = Box<dyn Fn(&RecordSchema) -> PathBuf + Send>;
+
+/// The writer for one path of a [Naming].
+enum NamedWriter {
+    /// Only one line code has been written to the path so far,
+    /// so it is streamed like the files of [CSVProcessor::new].
+    Streaming {
+        line_code: String,
+        writer: Box<CSVFormWriter<File>>,
+    },
+    /// Several line codes share the path, so their rows are buffered.
+    Buffered(CSVSingleFileWriter<File>),
+}
+
+impl NamedWriter {
+    /// Switch a [NamedWriter::Streaming] writer to buffering, reading
+    /// back the rows it has already written to `path`.
+    fn into_buffered(self, path: &Path) -> Result<CSVSingleFileWriter<File>, Error> {
+        let (line_code, mut writer) = match self {
+            NamedWriter::Buffered(writer) => return Ok(writer),
+            NamedWriter::Streaming { line_code, writer } => (line_code, writer),
+        };
+        writer.finish()?;
+        let CSVFormWriter {
+            schema,
+            use_labels,
+            encoder,
+            ..
+        } = *writer;
+        let mut reader = csv::ReaderBuilder::new()
+            .flexible(true)
+            .from_path(path)
+            .map_err(std::io::Error::from)?;
+        let written = reader
+            .byte_records()
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(std::io::Error::from)?;
+        log::debug!("Buffering CSV writer at: {:?}", path);
+        let mut buffered = CSVSingleFileWriter::new(File::create(path)?)
+            .with_labels(use_labels)
+            .with_encoding(encoder.encoding, encoder.unrepresentable);
+        buffered.always_write_line_code = false;
+        let fields = &schema.fields;
+        for row in written {
+            let mut cells = Vec::with_capacity(row.len());
+            for (i, bytes) in row.iter().enumerate() {
+                let field = fields.get(i);
+                let name = match field {
+                    Some(field) => field.name.clone(),
+                    None => format!("extra_{}", i - fields.len() + 1),
+                };
+                let label = || match field {
+                    Some(field) => field.display_label(),
+                    None => humanize(&name),
+                };
+                let index = buffered.column_index(name.clone(), label);
+                cells.push((index, encoder.decode(bytes)));
+            }
+            buffered.rows.push((line_code.clone(), cells));
+        }
+        Ok(buffered)
+    }
+}
+
+impl RecordWriter for NamedWriter {
+    fn write_record(&mut self, record: &Record) -> std::io::Result<()> {
+        match self {
+            NamedWriter::Streaming { writer, .. } => writer.write_record(record),
+            NamedWriter::Buffered(writer) => writer.write_record(record),
+        }
+    }
+
+    fn finish(&mut self) -> Result<(), Error> {
+        match self {
+            NamedWriter::Streaming { writer, .. } => writer.finish(),
+            NamedWriter::Buffered(writer) => writer.finish(),
+        }
+    }
+}
+
+/// The contents of the `_metadata.json` sidecar.
+#[derive(Serialize)]
+struct Metadata<'a> {
+    header: &'a Header,
+    cover: &'a Cover,
 }
 
 impl CSVProcessor {
     /// Create a new CSVProcessor that writes to the given directory.
     pub fn new(out_dir: PathBuf) -> Self {
-        let factory = CSVFileWriterFactory;
-        let f2 = MultiFileRecordWriterFactory::new(out_dir, factory);
-        let multi_writer = MultiRecordWriter::new(f2);
-        Self { multi_writer }
+        Self::with_metadata(out_dir, false)
+    }
+
+    /// Like [CSVProcessor::new], but if `write_metadata` is true,
+    /// also write the header and cover to `_metadata.json`.
+    pub fn with_metadata(out_dir: PathBuf, write_metadata: bool) -> Self {
+        Self {
+            multi_writer: Self::make_multi_writer(&out_dir, false, Encoder::default()),
+            out_dir,
+            write_metadata,
+            naming: None,
+            named_writers: HashMap::new(),
+            use_labels: false,
+            encoder: Encoder::default(),
+        }
+    }
+
+    fn make_multi_writer(
+        out_dir: &Path,
+        use_labels: bool,
+        encoder: Encoder,
+    ) -> MultiRecordWriter<MultiFileRecordWriterFactory<CSVFileWriterFactory>> {
+        let factory = CSVFileWriterFactory {
+            use_labels,
+            encoder,
+        };
+        let f2 = MultiFileRecordWriterFactory::new(out_dir.to_path_buf(), factory);
+        MultiRecordWriter::new(f2)
+    }
+
+    /// If true, write human-readable labels in the header rows, eg
+    /// "Contributor Last Name" instead of "contributor_last_name".
+    ///
+    /// Labels come from [FieldSchema::label] in the schemas, falling back
+    /// to a label made from the name. See [FieldSchema::display_label].
+    ///
+    /// [FieldSchema::label]: crate::record::FieldSchema::label
+    /// [FieldSchema::display_label]: crate::record::FieldSchema::display_label
+    pub fn with_labels(mut self, use_labels: bool) -> Self {
+        self.multi_writer = Self::make_multi_writer(&self.out_dir, use_labels, self.encoder);
+        self.use_labels = use_labels;
+        self
+    }
+
+    /// Write the CSVs in `encoding` instead of UTF-8, for downstream systems
+    /// that need eg Latin-1.
+    ///
+    /// Values are transcoded as they are written. Characters that
+    /// `encoding` can't represent are handled as `unrepresentable` says.
+    /// The `_metadata.json` sidecar is always UTF-8.
+    pub fn with_encoding(
+        mut self,
+        encoding: CsvEncoding,
+        unrepresentable: Unrepresentable,
+    ) -> Self {
+        self.encoder = Encoder {
+            encoding,
+            unrepresentable,
+        };
+        self.multi_writer = Self::make_multi_writer(&self.out_dir, self.use_labels, self.encoder);
+        self
+    }
+
+    /// Choose the file each schema is written to.
+    ///
+    /// Relative paths are relative to the output directory.
+    /// A path that only gets one line code is streamed, like the files of
+    /// [CSVProcessor::new]. If several line codes map to the same path, eg
+    /// to group "SA11AI" and "SA11AII" into "SA11.csv", that file gets the
+    /// union of their columns, plus a leading "line_code" column. Since the
+    /// columns aren't known until every record has been seen, once a second
+    /// line code reaches a path, the rows already written there are read
+    /// back, and from then on its records are buffered in memory and only
+    /// written at the end, like [CSVSingleFileWriter].
+    pub fn with_naming(mut self, naming: Naming) -> Self {
+        self.naming = Some(naming);
+        self
     }
 
-    // TODO: factor this out with ParquetProcessor.process()
     /// Process the given FEC file, writing the results to the output directory.
     pub fn process(&mut self, fec: &mut FecFile) -> Result<(), Error> {
-        let fec_version = fec.get_header()?.fec_version.clone();
-        let mut parser = CoercingLineParser;
-        for line in fec.lines() {
-            let line = line?;
-            let record = parser.parse_line(&fec_version, &mut line.iter())?;
-            self.multi_writer.write_record(&record)?;
+        for record in fec.records() {
+            let record = record?;
+            match &self.naming {
+                None => self.multi_writer.write_record(&record)?,
+                Some(naming) => {
+                    let path = self.out_dir.join(naming(&record.schema));
+                    let shared = matches!(
+                        self.named_writers.get(&path),
+                        Some(NamedWriter::Streaming { line_code, .. })
+                            if *line_code != record.record_code
+                    );
+                    if let Some(writer) = shared.then(|| self.named_writers.remove(&path)).flatten()
+                    {
+                        let buffered = writer.into_buffered(&path)?;
+                        self.named_writers
+                            .insert(path.clone(), NamedWriter::Buffered(buffered));
+                    }
+                    let writer = match self.named_writers.entry(path) {
+                        Occupied(e) => e.into_mut(),
+                        Vacant(e) => {
+                            if let Some(parent) = e.key().parent() {
+                                std::fs::create_dir_all(parent)?;
+                            }
+                            log::debug!("Creating new CSV writer at: {:?}", e.key());
+                            let file = File::create(e.key())?;
+                            let writer = CSVFormWriter::new(
+                                file,
+                                &record.schema,
+                                self.use_labels,
+                                self.encoder,
+                            );
+                            e.insert(NamedWriter::Streaming {
+                                line_code: record.record_code.clone(),
+                                writer: Box::new(writer),
+                            })
+                        }
+                    };
+                    writer.write_record(&record)?;
+                }
+            }
         }
         self.multi_writer.finish()?;
+        for writer in self.named_writers.values_mut() {
+            writer.finish()?;
+        }
+        if self.write_metadata {
+            self.write_metadata(fec)?;
+        }
+        Ok(())
+    }
+
+    fn write_metadata(&self, fec: &mut FecFile) -> Result<(), Error> {
+        let cover = fec.get_cover()?.clone();
+        let metadata = Metadata {
+            header: fec.get_header()?,
+            cover: &cover,
+        };
+        std::fs::create_dir_all(&self.out_dir)?;
+        let file = File::create(self.out_dir.join("_metadata.json"))?;
+        serde_json::to_writer_pretty(file, &metadata).map_err(std::io::Error::other)?;
         Ok(())
     }
 }
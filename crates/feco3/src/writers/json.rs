@@ -0,0 +1,65 @@
+//! Write [Record]s as a single JSON array.
+use std::io::Write;
+
+use crate::record::Record;
+use crate::writers::base::RecordWriter;
+use crate::writers::ndjson::record_to_json;
+use crate::Error;
+
+/// A [RecordWriter] that writes every record into one JSON array,
+/// eg `[{...},{...}]`.
+///
+/// Records are converted with [record_to_json], so the objects are the
+/// same as from [NdjsonWriter](crate::writers::ndjson::NdjsonWriter).
+/// They are streamed out as they are written, so nothing is buffered,
+/// but the array isn't closed until [RecordWriter::finish].
+/// If no records were written, the output is `[]`.
+pub struct JsonArrayWriter<W: Write> {
+    writer: W,
+    /// Whether the opening "[" has been written.
+    started: bool,
+    /// Whether the closing "]" has been written.
+    finished: bool,
+}
+
+impl<W: Write> JsonArrayWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            started: false,
+            finished: false,
+        }
+    }
+
+    /// Consume the writer, returning the underlying stream.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<W: Write + Send> RecordWriter for JsonArrayWriter<W> {
+    fn write_record(&mut self, record: &Record) -> std::io::Result<()> {
+        if self.finished {
+            return Err(std::io::Error::other("JSON array was already closed"));
+        }
+        let sep: &[u8] = if self.started { b"," } else { b"[" };
+        self.started = true;
+        self.writer.write_all(sep)?;
+        serde_json::to_writer(&mut self.writer, &record_to_json(record))?;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), Error> {
+        if self.finished {
+            return Ok(());
+        }
+        if !self.started {
+            self.writer.write_all(b"[")?;
+            self.started = true;
+        }
+        self.writer.write_all(b"]")?;
+        self.finished = true;
+        self.writer.flush()?;
+        Ok(())
+    }
+}
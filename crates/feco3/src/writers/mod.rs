@@ -3,4 +3,9 @@
 pub mod arrow;
 pub mod base;
 pub mod csv;
+pub mod feather;
+pub mod json;
+pub mod ndjson;
 pub mod parquet;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
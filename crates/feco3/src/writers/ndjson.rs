@@ -0,0 +1,133 @@
+//! Write [Record]s as newline-delimited JSON.
+use std::io::Write;
+
+use serde_json::{Map, Number};
+
+use crate::record::{Record, Value};
+use crate::writers::base::RecordWriter;
+use crate::{Error, FecFile};
+
+/// Convert a [Value] into the JSON equivalent.
+///
+/// Missing values become `null`, dates become ISO 8601 strings,
+/// and lists become arrays. Decimals become strings, eg `"1000.10"`,
+/// since a JSON number would usually be read back as a float.
+pub fn value_to_json(val: &Value) -> serde_json::Value {
+    match val {
+        Value::String(Some(s)) => serde_json::Value::String(s.clone()),
+        Value::Integer(Some(i)) => serde_json::Value::Number((*i).into()),
+        Value::Float(Some(f)) => match Number::from_f64(*f) {
+            Some(n) => serde_json::Value::Number(n),
+            None => serde_json::Value::Null,
+        },
+        Value::Decimal(Some(d)) => serde_json::Value::String(d.to_string()),
+        Value::Date(Some(d)) => serde_json::Value::String(d.format("%Y-%m-%d").to_string()),
+        Value::InvalidDate(s) => serde_json::Value::String(s.clone()),
+        Value::Boolean(Some(b)) => serde_json::Value::Bool(*b),
+        Value::List(items) => serde_json::Value::Array(items.iter().map(value_to_json).collect()),
+        Value::String(None)
+        | Value::Integer(None)
+        | Value::Float(None)
+        | Value::Decimal(None)
+        | Value::Date(None)
+        | Value::Boolean(None) => serde_json::Value::Null,
+    }
+}
+
+/// Convert a [Record] into a JSON object.
+///
+/// The line code is stored under "form_type", and each value is keyed
+/// by its field name in the schema. Any values beyond what the schema
+/// expects are keyed as "extra_1", "extra_2", etc.
+pub fn record_to_json(record: &Record) -> serde_json::Value {
+    let mut obj = Map::new();
+    obj.insert(
+        "form_type".to_string(),
+        serde_json::Value::String(record.record_code.clone()),
+    );
+    let fields = &record.schema.fields;
+    for (i, val) in record.values.iter().enumerate() {
+        let key = match fields.get(i) {
+            Some(field) => field.name.clone(),
+            None => format!("extra_{}", i - fields.len() + 1),
+        };
+        obj.insert(key, value_to_json(val));
+    }
+    serde_json::Value::Object(obj)
+}
+
+/// A [RecordWriter] that writes one JSON object per line to a single stream.
+///
+/// Every record is flushed as soon as it is written, so nothing is
+/// buffered between records.
+pub struct NdjsonWriter<W: Write> {
+    writer: W,
+    /// Whether to add each record's [Record::source_id] as "source_id".
+    include_source_id: bool,
+}
+
+impl<W: Write> NdjsonWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            include_source_id: false,
+        }
+    }
+
+    /// If true, add each record's [Record::source_id] under "source_id",
+    /// or null if it has none.
+    pub fn with_source_id(mut self, include_source_id: bool) -> Self {
+        self.include_source_id = include_source_id;
+        self
+    }
+
+    /// Consume the writer, returning the underlying stream.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<W: Write + Send> RecordWriter for NdjsonWriter<W> {
+    fn write_record(&mut self, record: &Record) -> std::io::Result<()> {
+        let mut json = record_to_json(record);
+        if let (true, Some(obj)) = (self.include_source_id, json.as_object_mut()) {
+            let source_id = record.source_id.as_deref().map(str::to_string);
+            obj.insert("source_id".to_string(), source_id.into());
+        }
+        serde_json::to_writer(&mut self.writer, &json)?;
+        self.writer.write_all(b"\n")?;
+        self.writer.flush()
+    }
+
+    fn finish(&mut self) -> Result<(), Error> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Writes all the itemizations of a [FecFile] to a single ndjson stream.
+pub struct NdjsonProcessor<W: Write + Send> {
+    writer: NdjsonWriter<W>,
+}
+
+impl<W: Write + Send> NdjsonProcessor<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer: NdjsonWriter::new(writer),
+        }
+    }
+
+    pub fn process(&mut self, fec: &mut FecFile) -> Result<(), Error> {
+        for record in fec.records() {
+            let record = record?;
+            self.writer.write_record(&record)?;
+        }
+        self.writer.finish()?;
+        Ok(())
+    }
+
+    /// Consume the processor, returning the underlying stream.
+    pub fn into_inner(self) -> W {
+        self.writer.into_inner()
+    }
+}
@@ -1,14 +1,23 @@
 use parquet::{arrow::ArrowWriter, file::properties::WriterProperties};
-use std::{fs::File, path::PathBuf, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet},
+    fs::{self, File},
+    path::PathBuf,
+    sync::Arc,
+};
 
-use crate::record::Record;
-use crate::schemas::{CoercingLineParser, LineParser};
+use crate::record::{FieldSchema, Record, Value, ValueType};
+use crate::schemas::typed_schema;
 use crate::{record::RecordSchema, writers::base::RecordWriter};
 use crate::{Error, FecFile};
 
 use super::arrow::{record_schema_to_arrow_schema, RecordBatchWriter};
 use super::base::{FileRecordWriterFactory, MultiFileRecordWriterFactory, MultiRecordWriter};
 
+/// A [RecordWriter] that writes records of one schema to a Parquet file.
+///
+/// The file isn't valid Parquet until [RecordWriter::finish] writes the
+/// footer, so make sure to call it.
 pub struct ParquetWriter {
     batcher: RecordBatchWriter,
     writer: Option<ArrowWriter<File>>,
@@ -26,7 +35,8 @@ impl ParquetWriter {
         let props = props.unwrap_or_else(|| WriterProperties::builder().build());
         let batch_size = props.max_row_group_size();
         let batcher = RecordBatchWriter::new(feco3_schema.clone(), batch_size);
-        let writer = ArrowWriter::try_new(file, arrow_schema, Some(props.clone())).unwrap();
+        let writer = ArrowWriter::try_new(file, arrow_schema, Some(props.clone()))
+            .map_err(std::io::Error::other)?;
         Ok(Self {
             batcher,
             writer: Some(writer),
@@ -37,26 +47,43 @@ impl ParquetWriter {
     fn write_batch(&mut self) -> std::io::Result<()> {
         let writer = self.writer.as_mut().expect("writing to a closed writer");
         writer.write(&self.batcher.build_batch())?;
+        writer.flush()?;
         Ok(())
     }
+
+    /// Write the buffered records as a row group now, even if there are
+    /// fewer than `max_row_group_size` of them.
+    pub fn flush(&mut self) -> std::io::Result<()> {
+        if self.batcher.is_empty() {
+            return Ok(());
+        }
+        self.write_batch()
+    }
+
+    /// See [RecordBatchWriter::buffered_bytes].
+    pub fn buffered_bytes(&self) -> usize {
+        self.batcher.buffered_bytes()
+    }
 }
 
 impl RecordWriter for ParquetWriter {
     fn write_record(&mut self, record: &Record) -> std::io::Result<()> {
         self.batcher.write_record(record)?;
-        if self.batcher.len() < self.batch_size {
+        if self.batcher.len() >= self.batch_size {
             return self.write_batch();
         }
         Ok(())
     }
 
     fn finish(&mut self) -> Result<(), Error> {
-        self.write_batch()?;
-        let writer = self.writer.take().expect("writing to a closed writer");
-        writer
-            .close()
-            // FIXME
-            .map_err(|e| Error::RecordParseError(e.to_string()))?;
+        if self.writer.is_none() {
+            return Ok(());
+        }
+        if !self.batcher.is_empty() {
+            self.write_batch()?;
+        }
+        let writer = self.writer.take().expect("checked above");
+        writer.close().map_err(std::io::Error::other)?;
         Ok(())
     }
 }
@@ -73,42 +100,492 @@ impl FileRecordWriterFactory for ParquetWriterFactory {
     }
     fn make(&mut self, path: &PathBuf, schema: &RecordSchema) -> std::io::Result<Self::Writer> {
         let file = File::create(path)?;
-        Ok(ParquetWriter::new(file, schema, self.props.clone())?)
+        ParquetWriter::new(file, schema, self.props.clone())
     }
 }
 
-/// Writes forms to a directory of CSV files.
+/// Writes forms to a directory of Parquet files.
 ///
 /// Each form type gets its own file. If the form type contains a "/"
 /// (which would result in a subdirectory), it is replaced with a "-".
-/// For example, "SC/10" would be written to "SC-10.csv".
+/// For example, "SC/10" would be written to "SC-10.parquet".
+///
+/// Records are buffered in memory per form type, and written out as a
+/// row group once `max_row_group_size` of them have accumulated
+/// (see [WriterProperties::max_row_group_size]).
+/// Larger row groups compress better and are faster to scan, but
+/// need more memory while writing, since up to one row group is buffered
+/// for every form type in the file.
+///
+/// To bound that memory, see [ParquetProcessor::with_memory_budget].
+///
+/// With the `parallel` feature, the row groups are compressed and written
+/// on a pool of background threads, see [ParquetProcessor::process_parallel].
+///
+/// To write several line codes to one file instead, see
+/// [ParquetProcessor::with_group].
+///
+/// Dates and amounts are written as date and decimal columns, rather than
+/// as the strings the built-in schemas parse them to,
+/// see [ParquetProcessor::with_typed_columns].
 pub struct ParquetProcessor {
     writer: MultiRecordWriter<MultiFileRecordWriterFactory<ParquetWriterFactory>>,
+    memory_budget: Option<usize>,
+    out_dir: PathBuf,
+    props: WriterProperties,
+    groups: Vec<LineCodeGroup>,
+    typed_columns: bool,
+    /// The [typed_schema] of each line code seen so far in `typed_version`.
+    typed_schemas: HashMap<String, Arc<RecordSchema>>,
+    typed_version: String,
+}
+
+/// Line codes that are written to a single Parquet file.
+struct LineCodeGroup {
+    name: String,
+    prefixes: Vec<String>,
+    /// Buffered until the end, since the columns depend on every line code seen.
+    records: Vec<Record>,
 }
 
 impl ParquetProcessor {
-    /// Create a new CSVProcessor that writes to the given directory.
+    /// Create a new ParquetProcessor that writes to the given directory.
     ///
     /// `writer_props` can be used to configure the parquet writer used for
     /// each file. If None, the default writer properties are used.
     pub fn new(out_dir: PathBuf, writer_props: Option<WriterProperties>) -> Self {
+        let props = writer_props
+            .clone()
+            .unwrap_or_else(|| WriterProperties::builder().build());
         let factory = ParquetWriterFactory {
             props: writer_props,
         };
-        let f2 = MultiFileRecordWriterFactory::new(out_dir, factory);
+        let f2 = MultiFileRecordWriterFactory::new(out_dir.clone(), factory);
         let writer = MultiRecordWriter::new(f2);
-        Self { writer }
+        Self {
+            writer,
+            memory_budget: None,
+            out_dir,
+            props,
+            groups: Vec::new(),
+            typed_columns: true,
+            typed_schemas: HashMap::new(),
+            typed_version: String::new(),
+        }
+    }
+
+    /// Create a new ParquetProcessor that flushes a row group every
+    /// `row_group_size` records of each form type.
+    ///
+    /// This is a shortcut for setting
+    /// [WriterProperties::max_row_group_size] and leaving the rest of
+    /// the properties as default. See the [ParquetProcessor] docs for
+    /// the memory/compression tradeoff.
+    pub fn with_row_group_size(out_dir: PathBuf, row_group_size: usize) -> Self {
+        let props = WriterProperties::builder()
+            .set_max_row_group_size(row_group_size)
+            .build();
+        Self::new(out_dir, Some(props))
+    }
+
+    /// Once the buffered records of all form types take up more than
+    /// roughly `bytes`, write the largest buffer as a row group early.
+    ///
+    /// This keeps files with hundreds of rare form types from running out
+    /// of memory, at the cost of smaller row groups for those forms.
+    /// See [RecordBatchWriter::buffered_bytes] for how this is estimated.
+    pub fn with_memory_budget(mut self, bytes: usize) -> Self {
+        self.memory_budget = Some(bytes);
+        self
     }
 
+    /// If true, the default, write fields the FEC documents as dates or
+    /// amounts as date and decimal columns, see [typed_schema].
+    ///
+    /// Numbers are parsed as [FecFile::set_lenient_numbers] says. Values
+    /// that don't parse as their type are written as nulls, and counted
+    /// in [FecFile::stats]. If false, fields are written with the types in their schemas.
+    pub fn with_typed_columns(mut self, typed_columns: bool) -> Self {
+        self.typed_columns = typed_columns;
+        self
+    }
+
+    /// Convert the values of `record` to the types of its [typed_schema].
+    fn type_record(&mut self, fec: &mut FecFile, record: Record) -> Record {
+        if !self.typed_columns {
+            return record;
+        }
+        let version = fec.version().unwrap_or_default();
+        // Only multi-filing files change version partway through.
+        if self.typed_version != version {
+            self.typed_schemas.clear();
+            self.typed_version = version.to_string();
+        }
+        let schema = match self.typed_schemas.get(&record.schema.code) {
+            Some(schema) => schema.clone(),
+            None => {
+                let schema = Arc::new(typed_schema(version, &record.schema));
+                let code = record.schema.code.clone();
+                self.typed_schemas.insert(code, schema.clone());
+                schema
+            }
+        };
+        let values = record
+            .values
+            .into_iter()
+            .enumerate()
+            .map(|(i, val)| match (schema.fields.get(i), val) {
+                (Some(field), Value::String(s)) if field.typ != ValueType::String => {
+                    fec.coerce_value(&record.record_code, field.typ, s.as_ref())
+                }
+                (_, val) => val,
+            })
+            .collect();
+        Record {
+            schema,
+            values,
+            ..record
+        }
+    }
+
+    /// Write every line code starting with one of `prefixes` to a single
+    /// `<name>.parquet` file, eg all the "SA" variants to "SA.parquet".
+    ///
+    /// The file has a leading "line_code" column with each record's line
+    /// code, followed by the union of the fields of all the line codes,
+    /// in the order they were first seen. Fields a line code doesn't have
+    /// are null. If line codes disagree on the type of a field, or it's
+    /// part of a repeating group, it's written as a string.
+    ///
+    /// Since the columns aren't known until every record has been seen,
+    /// the grouped records are buffered in memory and written at the end,
+    /// without regard to [ParquetProcessor::with_memory_budget].
+    /// If a line code matches more than one group, the first one added wins.
+    pub fn with_group(mut self, name: impl Into<String>, prefixes: &[&str]) -> Self {
+        self.groups.push(LineCodeGroup {
+            name: name.into(),
+            prefixes: prefixes.iter().map(|p| p.to_string()).collect(),
+            records: Vec::new(),
+        });
+        self
+    }
+
+    /// Buffer `record` if it belongs to a group, otherwise hand it back.
+    fn group_record(&mut self, record: Record) -> Option<Record> {
+        let group = self.groups.iter_mut().find(|g| {
+            g.prefixes
+                .iter()
+                .any(|p| record.record_code.starts_with(p.as_str()))
+        });
+        match group {
+            Some(group) => {
+                group.records.push(record);
+                None
+            }
+            None => Some(record),
+        }
+    }
+
+    fn write_groups(&mut self) -> Result<(), Error> {
+        let names = ParquetWriterFactory::default();
+        for group in self.groups.iter_mut() {
+            let records = std::mem::take(&mut group.records);
+            if records.is_empty() {
+                continue;
+            }
+            let schema = union_schema(&group.name, &records);
+            let path = self
+                .out_dir
+                .join(names.file_name(names.norm_form_name(&group.name)));
+            fs::create_dir_all(&self.out_dir)?;
+            log::debug!("Creating new parquet file at: {:?}", path);
+            let file = File::create(path)?;
+            let mut writer = ParquetWriter::new(file, &schema, Some(self.props.clone()))?;
+            let schema = Arc::new(schema);
+            for record in records {
+                writer.write_record(&to_union_record(record, &schema))?;
+            }
+            writer.finish()?;
+        }
+        Ok(())
+    }
+
+    /// Write all the records of `fec`.
+    ///
+    /// This is [ParquetProcessor::process_parallel] if the `parallel`
+    /// feature is enabled, otherwise [ParquetProcessor::process_sequential].
+    #[cfg(feature = "parallel")]
     pub fn process(&mut self, fec: &mut FecFile) -> Result<(), Error> {
-        let fec_version = fec.get_header()?.fec_version.clone();
-        let mut parser = CoercingLineParser;
-        for line in fec.lines() {
-            let line = line?;
-            let record = parser.parse_line(&fec_version, &mut line.iter())?;
-            self.writer.write_record(&record)?;
+        self.process_parallel(fec)
+    }
+
+    /// Write all the records of `fec`.
+    ///
+    /// This is [ParquetProcessor::process_parallel] if the `parallel`
+    /// feature is enabled, otherwise [ParquetProcessor::process_sequential].
+    #[cfg(not(feature = "parallel"))]
+    pub fn process(&mut self, fec: &mut FecFile) -> Result<(), Error> {
+        self.process_sequential(fec)
+    }
+
+    /// Parse, batch, compress, and write everything on the current thread.
+    pub fn process_sequential(&mut self, fec: &mut FecFile) -> Result<(), Error> {
+        let mut buffered = 0;
+        while let Some(record) = fec.next_record() {
+            let record = self.type_record(fec, record?);
+            let Some(record) = self.group_record(record) else {
+                continue;
+            };
+            let writer = self.writer.get_writer(&record.schema)?;
+            let before = writer.buffered_bytes();
+            writer.write_record(&record)?;
+            buffered = buffered - before + writer.buffered_bytes();
+            if self.memory_budget.is_some_and(|b| buffered > b) {
+                let largest = self
+                    .writer
+                    .writers
+                    .values_mut()
+                    .max_by_key(|w| w.buffered_bytes())
+                    .expect("something is buffered");
+                log::debug!("over the memory budget, flushing a row group early");
+                buffered -= largest.buffered_bytes();
+                largest.flush()?;
+            }
         }
         self.writer.finish()?;
+        self.write_groups()
+    }
+
+    /// Parse and batch records on the current thread, while a pool of
+    /// writer threads compresses and writes the row groups.
+    ///
+    /// Each line code is assigned to one writer thread, so its row groups
+    /// are written in order, but different line codes are written
+    /// independently. There is one thread per CPU at most.
+    /// The output is the same as [ParquetProcessor::process_sequential].
+    #[cfg(feature = "parallel")]
+    pub fn process_parallel(&mut self, fec: &mut FecFile) -> Result<(), Error> {
+        let batch_size = self.props.max_row_group_size();
+        let mut pool = parallel::WriterPool::new(self.out_dir.clone(), self.props.clone());
+        let mut batchers = std::collections::HashMap::new();
+        let mut buffered = 0;
+        let parsed: Result<(), Error> = (|| {
+            while let Some(record) = fec.next_record() {
+                let record = self.type_record(fec, record?);
+                let Some(record) = self.group_record(record) else {
+                    continue;
+                };
+                let batcher = batchers
+                    .entry(record.schema.code.clone())
+                    .or_insert_with(|| {
+                        RecordBatchWriter::new((*record.schema).clone(), batch_size)
+                    });
+                let before = batcher.buffered_bytes();
+                batcher.write_record(&record)?;
+                buffered = buffered - before + batcher.buffered_bytes();
+                if batcher.len() >= batch_size {
+                    buffered -= batcher.buffered_bytes();
+                    pool.send(&record.schema.code, batcher.build_batch())?;
+                }
+                if self.memory_budget.is_some_and(|b| buffered > b) {
+                    let (code, largest) = batchers
+                        .iter_mut()
+                        .max_by_key(|(_, b)| b.buffered_bytes())
+                        .expect("something is buffered");
+                    log::debug!("over the memory budget, flushing a row group early");
+                    buffered -= largest.buffered_bytes();
+                    pool.send(code, largest.build_batch())?;
+                }
+            }
+            for (code, batcher) in batchers.iter_mut() {
+                if !batcher.is_empty() {
+                    pool.send(code, batcher.build_batch())?;
+                }
+            }
+            Ok(())
+        })();
+        // If a writer thread failed, that is the real cause of any send error.
+        pool.finish()?;
+        parsed?;
+        self.write_groups()
+    }
+}
+
+/// A schema with a "line_code" column and every field of `records`.
+fn union_schema(name: &str, records: &[Record]) -> RecordSchema {
+    let mut fields = vec![FieldSchema {
+        name: "line_code".to_string(),
+        typ: ValueType::String,
+        label: None,
+    }];
+    let mut indices = HashMap::from([("line_code".to_string(), 0)]);
+    let mut seen = HashSet::new();
+    for record in records {
+        if !seen.insert(record.record_code.as_str()) {
+            continue;
+        }
+        for (i, field) in record.schema.fields.iter().enumerate() {
+            let typ = match record.schema.is_repeating(i) {
+                true => ValueType::String,
+                false => field.typ,
+            };
+            match indices.get(&field.name) {
+                Some(&index) => {
+                    if fields[index].typ != typ {
+                        fields[index].typ = ValueType::String;
+                    }
+                }
+                None => {
+                    indices.insert(field.name.clone(), fields.len());
+                    fields.push(FieldSchema {
+                        typ,
+                        ..field.clone()
+                    });
+                }
+            }
+        }
+    }
+    RecordSchema {
+        code: name.to_string(),
+        fields,
+        repeating: 0,
+    }
+}
+
+/// Rearrange the values of `record` into the columns of `schema`,
+/// made by [union_schema].
+fn to_union_record(record: Record, schema: &Arc<RecordSchema>) -> Record {
+    let mut values: Vec<Value> = schema.fields.iter().map(|f| f.typ.null()).collect();
+    values[0] = Value::String(Some(record.record_code.clone()));
+    for (field, val) in record.schema.fields.iter().zip(record.values) {
+        let Some(index) = schema.fields.iter().position(|f| f.name == field.name) else {
+            continue;
+        };
+        values[index] = match val {
+            Value::List(_) => Value::String(Some(val.to_string())),
+            val if val.typ() == schema.fields[index].typ => val,
+            val => {
+                let s = val.to_string();
+                Value::String((!s.is_empty()).then_some(s))
+            }
+        };
+    }
+    Record {
+        schema: schema.clone(),
+        values,
+        extra_fields: 0,
+        missing_fields: 0,
+        ..record
+    }
+}
+
+#[cfg(feature = "parallel")]
+mod parallel {
+    use std::collections::HashMap;
+    use std::fs::{self, File};
+    use std::path::PathBuf;
+    use std::sync::mpsc::{sync_channel, Receiver, SyncSender};
+    use std::thread::{self, JoinHandle};
+
+    use arrow::record_batch::RecordBatch;
+    use parquet::{arrow::ArrowWriter, file::properties::WriterProperties};
+
+    use super::ParquetWriterFactory;
+    use crate::writers::base::FileRecordWriterFactory;
+    use crate::Error;
+
+    /// How many batches can be queued for each writer thread,
+    /// before the parsing thread waits for them to catch up.
+    const QUEUE_SIZE: usize = 4;
+
+    type Job = (String, RecordBatch);
+
+    struct Worker {
+        jobs: SyncSender<Job>,
+        handle: JoinHandle<Result<(), Error>>,
+    }
+
+    /// Writer threads, which are spawned as new line codes are seen.
+    pub struct WriterPool {
+        out_dir: PathBuf,
+        props: WriterProperties,
+        max_workers: usize,
+        workers: Vec<Worker>,
+        /// Which worker each line code is written by.
+        assignments: HashMap<String, usize>,
+    }
+
+    impl WriterPool {
+        pub fn new(out_dir: PathBuf, props: WriterProperties) -> Self {
+            let max_workers = thread::available_parallelism().map_or(1, |n| n.get());
+            Self {
+                out_dir,
+                props,
+                max_workers,
+                workers: Vec::new(),
+                assignments: HashMap::new(),
+            }
+        }
+
+        /// Queue a batch to be written to the file for `code`.
+        pub fn send(&mut self, code: &str, batch: RecordBatch) -> Result<(), Error> {
+            let next = self.assignments.len() % self.max_workers;
+            let index = *self.assignments.entry(code.to_string()).or_insert(next);
+            if index == self.workers.len() {
+                let (jobs, receiver) = sync_channel(QUEUE_SIZE);
+                let out_dir = self.out_dir.clone();
+                let props = self.props.clone();
+                let handle = thread::spawn(move || write_jobs(out_dir, props, receiver));
+                self.workers.push(Worker { jobs, handle });
+            }
+            self.workers[index]
+                .jobs
+                .send((code.to_string(), batch))
+                .map_err(|_| std::io::Error::other("parquet writer thread stopped"))?;
+            Ok(())
+        }
+
+        /// Wait for all the queued batches to be written and the files closed.
+        pub fn finish(self) -> Result<(), Error> {
+            let mut result = Ok(());
+            for worker in self.workers {
+                drop(worker.jobs);
+                let joined = match worker.handle.join() {
+                    Ok(joined) => joined,
+                    Err(_) => Err(std::io::Error::other("parquet writer thread panicked").into()),
+                };
+                result = result.and(joined);
+            }
+            result
+        }
+    }
+
+    fn write_jobs(
+        out_dir: PathBuf,
+        props: WriterProperties,
+        jobs: Receiver<Job>,
+    ) -> Result<(), Error> {
+        let names = ParquetWriterFactory::default();
+        let mut writers: HashMap<String, ArrowWriter<File>> = HashMap::new();
+        for (code, batch) in jobs {
+            if !writers.contains_key(&code) {
+                let file_name = names.file_name(names.norm_form_name(&code));
+                let path = out_dir.join(file_name);
+                fs::create_dir_all(&out_dir)?;
+                log::debug!("Creating new parquet file at: {:?}", path);
+                let file = File::create(path)?;
+                let writer = ArrowWriter::try_new(file, batch.schema(), Some(props.clone()))
+                    .map_err(std::io::Error::other)?;
+                writers.insert(code.clone(), writer);
+            }
+            let writer = writers.get_mut(&code).expect("writer was just inserted");
+            writer.write(&batch).map_err(std::io::Error::other)?;
+            // Batches cut short by the memory budget are their own row group.
+            writer.flush().map_err(std::io::Error::other)?;
+        }
+        for (_, writer) in writers {
+            writer.close().map_err(std::io::Error::other)?;
+        }
         Ok(())
     }
 }
@@ -0,0 +1,231 @@
+//! Write [Record]s to a SQLite database, with one table per line code.
+use std::collections::HashMap;
+use std::path::Path;
+
+use rusqlite::types::Value as SqlValue;
+use rusqlite::{params_from_iter, Connection};
+
+use crate::record::{Record, RecordSchema, Value, ValueType};
+use crate::writers::base::RecordWriter;
+use crate::Error;
+
+/// How many rows to insert per transaction by default.
+pub const DEFAULT_BATCH_SIZE: usize = 10_000;
+
+/// Convert a [ValueType] into the equivalent SQLite column affinity.
+pub fn value_type_to_sql_type(vt: &ValueType) -> &'static str {
+    match vt {
+        ValueType::String => "TEXT",
+        ValueType::Integer => "INTEGER",
+        ValueType::Float => "REAL",
+        // REAL would lose the exactness, and NUMERIC converts to REAL.
+        ValueType::Decimal => "TEXT",
+        // SQLite has no date type, ISO 8601 strings sort correctly.
+        ValueType::Date => "TEXT",
+        ValueType::Boolean => "INTEGER",
+    }
+}
+
+fn value_to_sql(val: &Value) -> SqlValue {
+    match val {
+        Value::String(Some(s)) => SqlValue::Text(s.clone()),
+        Value::Integer(Some(i)) => SqlValue::Integer(*i),
+        Value::Float(Some(f)) => SqlValue::Real(*f),
+        Value::Decimal(Some(d)) => SqlValue::Text(d.to_string()),
+        Value::Date(Some(d)) => SqlValue::Text(d.format("%Y-%m-%d").to_string()),
+        Value::InvalidDate(s) => SqlValue::Text(s.clone()),
+        Value::Boolean(Some(b)) => SqlValue::Integer(*b as i64),
+        // SQLite has no list type, so store them as JSON arrays.
+        Value::List(_) => SqlValue::Text(crate::writers::ndjson::value_to_json(val).to_string()),
+        Value::String(None)
+        | Value::Integer(None)
+        | Value::Float(None)
+        | Value::Decimal(None)
+        | Value::Date(None)
+        | Value::Boolean(None) => SqlValue::Null,
+    }
+}
+
+fn quote_ident(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// Make unique, non-empty column names from the fields of a schema.
+///
+/// Some schemas have blank or repeated field names, which SQLite
+/// won't accept, so we rename those to "column_N" or "name_N".
+fn column_names(schema: &RecordSchema) -> Vec<String> {
+    let mut names: Vec<String> = Vec::with_capacity(schema.fields.len());
+    for (i, field) in schema.fields.iter().enumerate() {
+        let mut name = match field.name.as_str() {
+            "" => format!("column_{}", i + 1),
+            name => name.to_string(),
+        };
+        let base = name.clone();
+        let mut n = 2;
+        while names
+            .iter()
+            .any(|existing| existing.eq_ignore_ascii_case(&name))
+        {
+            name = format!("{}_{}", base, n);
+            n += 1;
+        }
+        names.push(name);
+    }
+    names
+}
+
+/// The table we've created for one line code.
+struct Table {
+    name: String,
+    columns: Vec<String>,
+}
+
+impl Table {
+    fn insert_sql(&self) -> String {
+        let columns = self
+            .columns
+            .iter()
+            .map(|c| quote_ident(c))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let placeholders = vec!["?"; self.columns.len()].join(", ");
+        format!(
+            "INSERT INTO {} ({}) VALUES ({})",
+            quote_ident(&self.name),
+            columns,
+            placeholders
+        )
+    }
+}
+
+/// A [RecordWriter] that inserts records into a SQLite database.
+///
+/// A table is created for each line code the first time it is seen,
+/// named after the line code (eg "SA11AI" or "SC/10"), with a column for
+/// each field in the schema. If a record has more values than its schema,
+/// the table is widened with "extra_1", "extra_2", ... TEXT columns.
+///
+/// Rows are inserted in transactions of `batch_size` rows for speed,
+/// so call [RecordWriter::finish] to commit the last partial batch.
+pub struct SqliteWriter {
+    conn: Connection,
+    tables: HashMap<String, Table>,
+    batch_size: usize,
+    /// How many rows have been inserted in the current transaction.
+    pending: usize,
+}
+
+impl SqliteWriter {
+    /// Write to an existing SQLite connection.
+    pub fn new(conn: Connection, batch_size: usize) -> Self {
+        Self {
+            conn,
+            tables: HashMap::new(),
+            batch_size: batch_size.max(1),
+            pending: 0,
+        }
+    }
+
+    /// Open (or create) a SQLite database at the given path.
+    ///
+    /// `batch_size` is how many rows to insert per transaction.
+    /// If None, [DEFAULT_BATCH_SIZE] is used.
+    pub fn open(path: &Path, batch_size: Option<usize>) -> Result<Self, Error> {
+        let conn = Connection::open(path).map_err(to_io_error)?;
+        Ok(Self::new(conn, batch_size.unwrap_or(DEFAULT_BATCH_SIZE)))
+    }
+
+    /// The underlying connection, eg for querying what's been written.
+    pub fn connection(&self) -> &Connection {
+        &self.conn
+    }
+
+    fn ensure_table(&mut self, record: &Record) -> rusqlite::Result<()> {
+        let code = &record.schema.code;
+        if !self.tables.contains_key(code) {
+            let columns = column_names(&record.schema);
+            let defs = columns
+                .iter()
+                .zip(record.schema.fields.iter())
+                .enumerate()
+                .map(|(i, (c, f))| match record.schema.is_repeating(i) {
+                    // Lists are stored as JSON.
+                    true => format!("{} TEXT", quote_ident(c)),
+                    false => format!("{} {}", quote_ident(c), value_type_to_sql_type(&f.typ)),
+                })
+                .collect::<Vec<_>>()
+                .join(", ");
+            let sql = format!(
+                "CREATE TABLE IF NOT EXISTS {} ({})",
+                quote_ident(code),
+                defs
+            );
+            log::debug!("creating table: {}", sql);
+            self.conn.execute(&sql, [])?;
+            let table = Table {
+                name: code.clone(),
+                columns,
+            };
+            self.tables.insert(code.clone(), table);
+        }
+        let table = self.tables.get_mut(code).expect("table should exist");
+        let n_schema = record.schema.fields.len();
+        while table.columns.len() < record.values.len() {
+            let extra = format!("extra_{}", table.columns.len() - n_schema + 1);
+            let sql = format!(
+                "ALTER TABLE {} ADD COLUMN {} TEXT",
+                quote_ident(&table.name),
+                quote_ident(&extra)
+            );
+            log::debug!("widening table: {}", sql);
+            self.conn.execute(&sql, [])?;
+            table.columns.push(extra);
+        }
+        Ok(())
+    }
+
+    fn insert(&mut self, record: &Record) -> rusqlite::Result<()> {
+        if self.pending == 0 {
+            self.conn.execute_batch("BEGIN")?;
+        }
+        self.ensure_table(record)?;
+        {
+            let table = &self.tables[&record.schema.code];
+            let mut stmt = self.conn.prepare_cached(&table.insert_sql())?;
+            let params = (0..table.columns.len()).map(|i| match record.values.get(i) {
+                Some(val) => value_to_sql(val),
+                None => SqlValue::Null,
+            });
+            stmt.execute(params_from_iter(params))?;
+        }
+        self.pending += 1;
+        if self.pending >= self.batch_size {
+            self.commit()?;
+        }
+        Ok(())
+    }
+
+    fn commit(&mut self) -> rusqlite::Result<()> {
+        if self.pending > 0 {
+            self.conn.execute_batch("COMMIT")?;
+            self.pending = 0;
+        }
+        Ok(())
+    }
+}
+
+impl RecordWriter for SqliteWriter {
+    fn write_record(&mut self, record: &Record) -> std::io::Result<()> {
+        self.insert(record).map_err(to_io_error)
+    }
+
+    fn finish(&mut self) -> Result<(), Error> {
+        self.commit().map_err(to_io_error)?;
+        Ok(())
+    }
+}
+
+fn to_io_error(e: rusqlite::Error) -> std::io::Error {
+    std::io::Error::other(e)
+}
@@ -0,0 +1,48 @@
+//! Read .fec files out of .zip archives, without extracting them to disk.
+
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use zip::ZipArchive;
+
+use crate::Error;
+
+/// The most memory to reserve up front for an entry, whatever size it declares.
+const MAX_PREALLOCATION: u64 = 64 * 1024 * 1024;
+
+/// Read the decompressed contents of the entry `entry_name` of the
+/// archive at `path`.
+///
+/// The entry borrows the archive, so it can't be handed off as a
+/// `'static` reader. Instead it is read into memory up front,
+/// which is fine since .fec files compress so well that the archive
+/// is usually the bigger worry.
+pub fn read_entry(path: &Path, entry_name: &str) -> Result<Vec<u8>, Error> {
+    let mut archive = ZipArchive::new(File::open(path)?).map_err(std::io::Error::from)?;
+    let mut entry = archive.by_name(entry_name).map_err(std::io::Error::from)?;
+    // The size is whatever the archive claims, so don't trust it too far.
+    let capacity = entry.size().min(MAX_PREALLOCATION) as usize;
+    let mut bytes = Vec::with_capacity(capacity);
+    entry.read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// The names of the .fec entries in the archive at `path`, in order.
+///
+/// Matching is on the extension, ignoring case, so eg "1550548.FEC"
+/// is included but "README.txt" isn't.
+pub fn list_fec_entries(path: &Path) -> Result<Vec<String>, Error> {
+    let mut archive = ZipArchive::new(File::open(path)?).map_err(std::io::Error::from)?;
+    let mut names = Vec::new();
+    for i in 0..archive.len() {
+        let entry = archive.by_index_raw(i).map_err(std::io::Error::from)?;
+        let is_fec = Path::new(entry.name())
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("fec"));
+        if is_fec {
+            names.push(entry.name().to_string());
+        }
+    }
+    Ok(names)
+}
@@ -0,0 +1,55 @@
+#![cfg(feature = "tokio")]
+
+use std::path::PathBuf;
+
+use feco3::{AsyncFecFile, FecFile};
+
+fn repo_root() -> PathBuf {
+    PathBuf::from("../..")
+}
+
+#[tokio::test]
+async fn it_parses_in_memory_buffers() {
+    let path = repo_root().join("test/fecs/slash_form.fec");
+    let bytes = std::fs::read(&path).unwrap();
+    let mut fec = AsyncFecFile::new(bytes.as_slice());
+    assert_eq!(fec.get_header().await.unwrap().fec_version, "8.3");
+    assert_eq!(fec.get_cover().await.unwrap().form_type, "F3A");
+    let mut records = Vec::new();
+    while let Some(record) = fec.next_record().await {
+        records.push(record.unwrap());
+    }
+
+    let expected = FecFile::from_path(&path)
+        .unwrap()
+        .records()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(records.len(), expected.len());
+    for (actual, expected) in records.iter().zip(expected.iter()) {
+        assert_eq!(actual.record_code, expected.record_code);
+        assert_eq!(actual.values, expected.values);
+        assert_eq!(actual.row_index, expected.row_index);
+    }
+}
+
+#[tokio::test]
+async fn it_parses_legacy_headers_and_f99_text() {
+    let bytes = std::fs::read(repo_root().join("test/fecs/legacy_header.fec")).unwrap();
+    let mut fec = AsyncFecFile::new(bytes.as_slice());
+    let expected = FecFile::from_path(&repo_root().join("test/fecs/legacy_header.fec"))
+        .unwrap()
+        .get_header()
+        .unwrap()
+        .clone();
+    assert_eq!(
+        fec.get_header().await.unwrap().fec_version,
+        expected.fec_version
+    );
+
+    let bytes = std::fs::read(repo_root().join("test/fecs/f99_text.fec")).unwrap();
+    let mut fec = AsyncFecFile::new(bytes.as_slice());
+    let text = fec.get_cover().await.unwrap().text.clone().unwrap();
+    assert!(text.starts_with("Dear Ms. Adame-Wilson:\n\n"));
+    assert!(fec.next_record().await.is_none());
+}
@@ -0,0 +1,97 @@
+use std::path::PathBuf;
+
+use assert_cmd::Command;
+
+fn repo_root() -> PathBuf {
+    PathBuf::from("../..")
+}
+
+fn feco3() -> Command {
+    Command::cargo_bin("feco3").unwrap()
+}
+
+fn fixture(name: &str) -> PathBuf {
+    repo_root().join("test/fecs").join(name)
+}
+
+#[test]
+fn it_prints_the_header() {
+    let output = feco3()
+        .arg("header")
+        .arg(fixture("slash_form.fec"))
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let header: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(header["fec_version"], "8.3");
+    assert_eq!(header["report_id"], "FEC-1542500");
+}
+
+#[test]
+fn it_prints_line_code_counts() {
+    let output = feco3()
+        .arg("count")
+        .arg(fixture("slash_form.fec"))
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let counts: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let total = counts
+        .as_object()
+        .unwrap()
+        .values()
+        .map(|n| n.as_u64().unwrap())
+        .sum::<u64>();
+    assert_eq!(total, 8);
+    assert!(counts["SA11AI"].as_u64().unwrap() > 0);
+}
+
+#[test]
+fn it_writes_csvs_and_parquets() {
+    for (command, ext) in [("to-csv", "csv"), ("to-parquet", "parquet")] {
+        let out_dir = std::env::temp_dir().join(format!("feco3_test_cli_{}", ext));
+        let _ = std::fs::remove_dir_all(&out_dir);
+        feco3()
+            .arg(command)
+            .arg(fixture("slash_form.fec"))
+            .arg(&out_dir)
+            .assert()
+            .success();
+        assert!(out_dir.join(format!("SA11AI.{}", ext)).exists());
+    }
+}
+
+#[test]
+fn it_fails_on_missing_files() {
+    feco3()
+        .arg("header")
+        .arg(fixture("does_not_exist.fec"))
+        .assert()
+        .failure();
+}
+
+#[test]
+fn it_reads_from_stdin() {
+    let output = feco3()
+        .arg("count")
+        .arg("-")
+        .pipe_stdin(fixture("slash_form.fec"))
+        .unwrap()
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let counts: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(counts["SA11AI"], 2);
+
+    let out_dir = std::env::temp_dir().join("feco3_test_cli_stdin");
+    let _ = std::fs::remove_dir_all(&out_dir);
+    feco3()
+        .arg("to-csv")
+        .arg("-")
+        .arg(&out_dir)
+        .pipe_stdin(fixture("slash_form.fec.gz"))
+        .unwrap()
+        .assert()
+        .success();
+    assert!(out_dir.join("SA11AI.csv").exists());
+}
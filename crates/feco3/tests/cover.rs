@@ -0,0 +1,210 @@
+use std::path::PathBuf;
+
+use chrono::NaiveDate;
+use feco3::{Cover, FecFile};
+
+fn repo_root() -> PathBuf {
+    PathBuf::from("../..")
+}
+
+fn cover(name: &str) -> Cover {
+    let mut fec = FecFile::from_path(&repo_root().join("test/fecs").join(name)).unwrap();
+    fec.get_cover().unwrap().clone()
+}
+
+fn date(y: i32, m: u32, d: u32) -> Option<NaiveDate> {
+    NaiveDate::from_ymd_opt(y, m, d)
+}
+
+#[test]
+fn it_parses_f3_cover() {
+    let cover = cover("slash_form.fec");
+    assert_eq!(cover.form_type, "F3A");
+    assert_eq!(cover.filer_committee_id, "C00772335");
+    assert_eq!(cover.report_code.as_deref(), Some("Q3"));
+    assert_eq!(cover.coverage_from_date, date(2021, 7, 1));
+    assert_eq!(cover.coverage_through_date, date(2021, 9, 30));
+}
+
+#[test]
+fn it_parses_f3x_cover() {
+    let cover = cover("1550548.fec");
+    assert_eq!(cover.form_type, "F3XA");
+    assert_eq!(cover.filer_committee_id, "C00413955");
+    assert_eq!(cover.report_code.as_deref(), Some("MY"));
+    assert_eq!(cover.coverage_from_date, date(2021, 1, 1));
+    assert_eq!(cover.coverage_through_date, date(2021, 6, 30));
+}
+
+#[test]
+fn it_parses_f3p_cover() {
+    let cover = cover("text.fec");
+    assert_eq!(cover.form_type, "F3PA");
+    assert_eq!(cover.filer_committee_id, "C00580100");
+    assert_eq!(cover.report_code.as_deref(), Some("12G"));
+    assert_eq!(cover.coverage_from_date, date(2016, 10, 1));
+    assert_eq!(cover.coverage_through_date, date(2016, 10, 19));
+}
+
+#[test]
+fn it_gets_the_coverage_range_of_a_quarterly_f3x() {
+    let cover = Cover {
+        form_type: "F3XN".to_string(),
+        report_code: Some("Q1".to_string()),
+        coverage_from_date: date(2022, 1, 1),
+        coverage_through_date: date(2022, 3, 31),
+        ..Default::default()
+    };
+    let (from, through) = cover.coverage_range().unwrap();
+    assert_eq!(
+        (Some(from), Some(through)),
+        (date(2022, 1, 1), date(2022, 3, 31))
+    );
+    assert_eq!((through - from).num_days() + 1, 90);
+    assert!(cover.covers(from));
+    assert!(cover.covers(date(2022, 2, 14).unwrap()));
+    assert!(cover.covers(through));
+    assert!(!cover.covers(date(2021, 12, 31).unwrap()));
+    assert!(!cover.covers(date(2022, 4, 1).unwrap()));
+}
+
+#[test]
+fn it_gets_the_coverage_range_of_a_file() {
+    let cover = cover("1550548.fec");
+    let range = cover.coverage_range().unwrap();
+    assert_eq!(
+        range,
+        (date(2021, 1, 1).unwrap(), date(2021, 6, 30).unwrap())
+    );
+    assert!(cover.covers(date(2021, 3, 15).unwrap()));
+    assert!(!cover.covers(date(2021, 7, 1).unwrap()));
+}
+
+#[test]
+fn it_covers_nothing_without_dates() {
+    let cover = Cover::default();
+    assert_eq!(cover.coverage_range(), None);
+    assert!(!cover.covers(date(2022, 1, 1).unwrap()));
+}
+
+fn with_id(id: &str) -> Cover {
+    Cover {
+        filer_committee_id: id.to_string(),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn it_accepts_valid_committee_ids() {
+    for id in ["C00401224", "P80001571", "H0AK00097", "S0AK00196"] {
+        assert!(with_id(id).is_valid_committee_id(), "{}", id);
+    }
+    assert!(cover("slash_form.fec").is_valid_committee_id());
+}
+
+#[test]
+fn it_flags_malformed_committee_ids() {
+    for id in [
+        "",
+        "C0040122",
+        "C004012245",
+        "X00401224",
+        "c00401224",
+        "CABCDEFGH",
+    ] {
+        assert!(!with_id(id).is_valid_committee_id(), "{}", id);
+    }
+}
+
+#[test]
+fn it_captures_f99_text_verbatim() {
+    let mut fec = FecFile::from_path(&repo_root().join("test/fecs/f99_text.fec")).unwrap();
+    let expected = [
+        "Dear Ms. Adame-Wilson:",
+        "",
+        "The \"Victory Fund\" received, and refunded, two contributions:",
+        "    $ 1,450.12 Embassy Suites",
+        "",
+        "Respectfully submitted,",
+        "James S. Johnson",
+    ]
+    .join("\n");
+    let cover = fec.get_cover().unwrap();
+    assert_eq!(cover.form_type, "F99");
+    assert_eq!(cover.text.as_deref(), Some(expected.as_str()));
+    // The text isn't mistaken for itemizations.
+    assert_eq!(fec.records().count(), 0);
+}
+
+#[test]
+fn it_skips_the_text_of_real_f99_files() {
+    let mut fec = FecFile::from_path(&repo_root().join("test/fecs/f99.fec")).unwrap();
+    let text = fec.get_cover().unwrap().text.clone().unwrap();
+    assert!(text.starts_with("DENTON COUNTY REPUBLICAN VICTORY FUND\n"));
+    assert!(text.ends_with("Treasurer, Denton County Republican Party Victory Fund"));
+    assert_eq!(fec.records().count(), 0);
+}
+
+#[test]
+fn it_parses_just_the_header_and_cover() {
+    let bytes = std::fs::read(repo_root().join("test/fecs/1550548.fec")).unwrap();
+    let mut src = std::io::Cursor::new(bytes.as_slice());
+    let (header, cover) = feco3::parse_cover_from_reader(&mut src).unwrap();
+    assert_eq!(header.fec_version, "8.3");
+    assert_eq!(cover.form_type, "F3XA");
+    assert_eq!(cover.filer_committee_id, "C00413955");
+    assert_eq!(cover.coverage_through_date, date(2021, 6, 30));
+
+    // Only the header and cover lines were consumed.
+    let consumed = src.position() as usize;
+    let second_newline = bytes
+        .iter()
+        .enumerate()
+        .filter(|(_, b)| **b == b'\n')
+        .nth(1)
+        .unwrap()
+        .0;
+    assert_eq!(consumed, second_newline + 1);
+    assert!(bytes[consumed..].starts_with(b"SA11AI"));
+}
+
+#[test]
+fn it_errors_on_files_without_a_cover() {
+    let src: &[u8] = b"HDR\x1cFEC\x1c8.3\x1cFECfile\x1c8.3.0.0(f32)\x1c\x1c\n\n";
+    match feco3::parse_cover_from_reader(src) {
+        Err(feco3::Error::CoverParseError(feco3::CoverParseError::Missing)) => {}
+        other => panic!("expected a missing cover, got {:?}", other),
+    }
+}
+
+#[test]
+fn it_flags_original_filings() {
+    let cover = cover("v5.fec");
+    assert_eq!(cover.form_type, "F3XN");
+    assert!(!cover.is_amendment());
+    assert_eq!(cover.amends_report_id(), None);
+}
+
+#[test]
+fn it_finds_the_report_an_amendment_amends() {
+    let cover = cover("1550548.fec");
+    assert_eq!(cover.form_type, "F3XA");
+    assert!(cover.is_amendment());
+    assert_eq!(cover.amends_report_id().as_deref(), Some("FEC-1531171"));
+}
+
+#[test]
+fn it_only_treats_a_suffixes_as_amendments() {
+    let form = |form_type: &str| Cover {
+        form_type: form_type.to_string(),
+        report_id: Some("FEC-1".to_string()),
+        ..Default::default()
+    };
+    for form_type in ["F3A", "F3XA", "F3PA", "F24A"] {
+        assert!(form(form_type).is_amendment(), "{}", form_type);
+    }
+    for form_type in ["F3N", "F3XN", "F3XT", "F3X", "F99"] {
+        assert!(!form(form_type).is_amendment(), "{}", form_type);
+        assert_eq!(form(form_type).amends_report_id(), None);
+    }
+}
@@ -0,0 +1,61 @@
+use std::path::PathBuf;
+
+use feco3::record::Value;
+use feco3::{diff_filings, FecFile};
+
+fn repo_root() -> PathBuf {
+    PathBuf::from("../..")
+}
+
+fn open(name: &str) -> FecFile {
+    FecFile::from_path(&repo_root().join("test/fecs").join(name)).unwrap()
+}
+
+#[test]
+fn it_diffs_an_amended_amount() {
+    let diff = diff_filings(
+        &mut open("diff_original.fec"),
+        &mut open("diff_amended.fec"),
+    )
+    .unwrap();
+    assert!(diff.added.is_empty());
+    assert!(diff.removed.is_empty());
+    assert_eq!(diff.changed.len(), 1);
+    let change = &diff.changed[0];
+    assert_eq!(change.transaction_id, "SA11AI.4170");
+    assert_eq!(change.fields.len(), 1);
+    assert_eq!(change.fields[0].field, "contribution_amount");
+    assert_eq!(
+        change.fields[0].old,
+        Some(Value::String(Some("1000.00".to_string())))
+    );
+    assert_eq!(
+        change.fields[0].new,
+        Some(Value::String(Some("1500.00".to_string())))
+    );
+}
+
+#[test]
+fn it_diffs_added_and_removed_records() {
+    let mut original = FecFile::builder()
+        .line_filter(vec!["SA".to_string()])
+        .build_from_path(&repo_root().join("test/fecs/diff_original.fec"))
+        .unwrap();
+    let mut amended = FecFile::builder()
+        .line_filter(vec!["SB".to_string()])
+        .build_from_path(&repo_root().join("test/fecs/diff_amended.fec"))
+        .unwrap();
+    let diff = diff_filings(&mut original, &mut amended).unwrap();
+    let ids = |records: &[feco3::record::Record]| {
+        records
+            .iter()
+            .map(|r| r.transaction_id().unwrap().to_string())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(ids(&diff.added), vec!["SB17.4118", "SB17.4135"]);
+    assert_eq!(ids(&diff.removed), vec!["SA11AI.4265", "SA11AI.4170"]);
+    assert!(diff.changed.is_empty());
+
+    let same = diff_filings(&mut open("slash_form.fec"), &mut open("slash_form.fec")).unwrap();
+    assert!(same.is_empty());
+}
@@ -0,0 +1,55 @@
+use std::path::PathBuf;
+
+use feco3::{FecFile, FormType};
+
+fn repo_root() -> PathBuf {
+    PathBuf::from("../..")
+}
+
+#[test]
+fn it_maps_codes_to_forms() {
+    let cases = [
+        ("F3XN", FormType::F3X),
+        ("F3XA", FormType::F3X),
+        ("F3N", FormType::F3),
+        ("F3PS", FormType::F3PS),
+        ("F3P31", FormType::F3P),
+        ("f24n", FormType::F24),
+        ("F99", FormType::F99),
+        ("F1MN", FormType::F1M),
+        ("F56", FormType::F5),
+        ("F132", FormType::F13),
+        ("SA11AI", FormType::SA),
+        ("SB23", FormType::SB),
+        ("SC/10", FormType::SC),
+        ("SC1/10", FormType::SC1),
+        ("SC2/10", FormType::SC2),
+        ("SE", FormType::SE),
+        ("H4", FormType::H4),
+        ("TEXT", FormType::Text),
+    ];
+    for (code, expected) in cases {
+        assert_eq!(FormType::from_code(code), expected, "{}", code);
+    }
+}
+
+#[test]
+fn it_keeps_unknown_codes() {
+    let form = FormType::from_code("ZZ99");
+    assert_eq!(form, FormType::Other("ZZ99".to_string()));
+    assert_eq!(form.to_string(), "ZZ99");
+    assert_eq!(FormType::F3X.to_string(), "F3X");
+}
+
+#[test]
+fn it_gets_the_form_type_of_records() {
+    let path = repo_root().join("test/fecs/slash_form.fec");
+    let forms = FecFile::from_path(&path)
+        .unwrap()
+        .records()
+        .map(|r| r.unwrap().form_type())
+        .collect::<Vec<_>>();
+    assert_eq!(forms[0], FormType::SA);
+    assert!(forms.contains(&FormType::SC));
+    assert!(forms.contains(&FormType::SC2));
+}
@@ -0,0 +1,354 @@
+use feco3::{Error, FecFile, HeaderParseError, Sep};
+
+fn header_error(src: &[u8]) -> HeaderParseError {
+    let mut fec = FecFile::from_bytes(src.to_vec());
+    match fec.get_header() {
+        Err(Error::HeaderParseError(e)) => e,
+        other => panic!("expected a HeaderParseError, got {:?}", other.map(|_| ())),
+    }
+}
+
+#[test]
+fn it_errors_on_empty_file() {
+    assert_eq!(header_error(b""), HeaderParseError::TooShort);
+}
+
+#[test]
+fn it_errors_on_truncated_legacy_header() {
+    let src = b"/* Header\nFEC_Ver_# = 2.02\nSoft_Name = FECfile\n";
+    assert_eq!(header_error(src), HeaderParseError::TooShort);
+}
+
+#[test]
+fn it_errors_on_missing_legacy_field() {
+    let src = b"/* Header\nFEC_Ver_# = 2.02\nSoft_Ver# = 3\n/* End Header\n";
+    assert_eq!(
+        header_error(src),
+        HeaderParseError::MissingField("Soft_Name")
+    );
+}
+
+#[test]
+fn it_errors_on_empty_version() {
+    let src = b"HDR\x1cFEC\x1c\x1cFECfile\n";
+    assert_eq!(
+        header_error(src),
+        HeaderParseError::UnknownVersion("".to_string())
+    );
+}
+
+#[test]
+fn it_errors_on_missing_delimiter() {
+    assert_eq!(header_error(b"HDR\n"), HeaderParseError::InvalidDelimiter);
+}
+
+#[test]
+fn it_parses_header_from_bytes() {
+    let src = b"HDR\x1cFEC\x1c8.3\x1cNGP\x1c8\x1c\x1c\n".to_vec();
+    let mut fec = FecFile::from_bytes(src);
+    let header = fec.get_header().unwrap();
+    assert_eq!(header.fec_version, "8.3");
+    assert_eq!(header.software_name, "NGP");
+    assert_eq!(header.software_version, Some("8".to_string()));
+}
+
+fn open(name: &str) -> FecFile {
+    let path = std::path::PathBuf::from("../../test/fecs").join(name);
+    FecFile::from_path(&path).unwrap()
+}
+
+#[test]
+fn it_parses_v3_comma_files() {
+    let mut fec = open("v3.fec");
+    let header = fec.get_header().unwrap();
+    assert_eq!(header.fec_version, "3.00");
+    assert_eq!(header.software_name, "FECfile");
+    let cover = fec.get_cover().unwrap();
+    assert_eq!(cover.filer_committee_id, "C00101766");
+    assert_eq!(fec.records().count(), 2);
+}
+
+#[test]
+fn it_parses_v5_comma_files() {
+    let mut fec = open("v5.fec");
+    assert_eq!(fec.get_header().unwrap().fec_version, "5.00");
+    let records = fec.records().map(|r| r.unwrap()).collect::<Vec<_>>();
+    assert_eq!(records.len(), 2);
+    assert_eq!(
+        records[0]
+            .get_value("contributor_employer")
+            .unwrap()
+            .to_string(),
+        "Continental Airlines, Inc."
+    );
+}
+
+#[test]
+fn it_guesses_misplaced_versions() {
+    let src = b"HDR,FEC,,3.00,FECfile,3,^,,\n";
+    let mut fec = FecFile::from_bytes(src.to_vec());
+    assert_eq!(fec.get_header().unwrap().fec_version, "3.00");
+}
+
+#[test]
+fn it_errors_when_no_version_found() {
+    let src = b"HDR,FEC,abc,FECfile,3,^,,,\n";
+    assert_eq!(
+        header_error(src),
+        HeaderParseError::UnknownVersion("abc".to_string())
+    );
+}
+
+#[test]
+fn it_exposes_separator_and_version() {
+    let mut fec = open("slash_form.fec");
+    assert_eq!(fec.separator(), None);
+    assert_eq!(fec.version(), None);
+    fec.get_header().unwrap();
+    assert_eq!(fec.separator(), Some(Sep::Ascii28));
+    assert_eq!(fec.version(), Some("8.3"));
+}
+
+#[test]
+fn it_exposes_comma_separator() {
+    let mut fec = open("v5.fec");
+    fec.get_header().unwrap();
+    assert_eq!(fec.separator(), Some(Sep::Comma));
+    assert_eq!(fec.version(), Some("5.00"));
+}
+
+#[test]
+fn it_orders_versions_numerically() {
+    use feco3::FecVersion;
+
+    let v = |s: &str| s.parse::<FecVersion>().unwrap();
+    assert!(v("8.3") < v("8.4"));
+    assert!(v("8.4") < v("8.10"));
+    assert!(v("7.0") < v("8.0"));
+    assert_eq!(v("3.00"), v("3"));
+    assert_eq!(v("P2.6").to_string(), "P2.6");
+    assert!(v("P2.6").paper);
+    assert!("8.x".parse::<FecVersion>().is_err());
+    assert_eq!(
+        open("slash_form.fec").get_header().unwrap().version(),
+        Ok(v("8.3"))
+    );
+}
+
+#[test]
+fn it_skips_a_utf8_bom() {
+    let mut fec = open("bom.fec");
+    let header = fec.get_header().unwrap();
+    assert_eq!(header.fec_version, "8.3");
+    assert_eq!(header.software_name, "FECfile");
+    assert_eq!(fec.records().count(), 8);
+}
+
+#[test]
+fn it_assumes_a_version_for_headerless_files() {
+    let mut fec = open("headerless.fec");
+    let header = fec.get_header().unwrap();
+    assert_eq!(header.fec_version, "unknown");
+    assert_eq!(header.schema_version(), "3.00");
+    assert_eq!(fec.get_cover().unwrap().form_type, "F3XA");
+    let records = fec.records().collect::<Result<Vec<_>, _>>().unwrap();
+    let expected = open("legacy_header.fec")
+        .records()
+        .take(records.len())
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(records.len(), 5);
+    for (record, expected) in records.iter().zip(&expected) {
+        assert_eq!(record.record_code, expected.record_code);
+        // The assumed version's schemas have a few more trailing fields.
+        assert!(record.values.starts_with(&expected.values[..30]));
+    }
+}
+
+#[test]
+fn it_keeps_the_raw_header_bytes() {
+    for name in ["1550548.fec", "v5.fec", "bom.fec"] {
+        let bytes = std::fs::read(std::path::PathBuf::from("../../test/fecs").join(name)).unwrap();
+        let bytes = bytes.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(&bytes);
+        let end = bytes.iter().position(|b| *b == b'\n').unwrap();
+        let mut fec = open(name);
+        assert_eq!(fec.get_header().unwrap().raw(), &bytes[..end], "{}", name);
+    }
+    let mut fec = open("legacy_header.fec");
+    let raw = fec.get_header().unwrap().raw().to_vec();
+    assert!(raw.starts_with(b"/* Header\nFEC_Ver_# = 2.02\n"));
+    assert!(raw.ends_with(b"/* End Header"));
+    assert!(open("headerless.fec")
+        .get_header()
+        .unwrap()
+        .raw()
+        .is_empty());
+}
+
+#[test]
+fn it_returns_an_owned_header() {
+    let header = open("1550548.fec").into_header().unwrap();
+    assert_eq!(header.fec_version, "8.3");
+}
+
+#[test]
+fn it_maps_header_fields_by_version() {
+    // (file, version, software name, software version, report id, report number)
+    let cases = [
+        ("header_v6.fec", "6.4", "NGP", "6.1", "FEC-612345", "2"),
+        // Leaves out the "FEC" field before the version.
+        ("header_v7.fec", "7.0", "Vocus", "7.2.1", "FEC-712345", "1"),
+        (
+            "header_v8.fec",
+            "8.3",
+            "FECfile",
+            "8.3.0.3(f32)",
+            "FEC-812345",
+            "3",
+        ),
+    ];
+    for (name, version, soft_name, soft_ver, report_id, report_number) in cases {
+        let header = open(name).into_header().unwrap();
+        assert_eq!(header.fec_version, version, "{}", name);
+        assert_eq!(header.software_name, soft_name, "{}", name);
+        assert_eq!(
+            header.software_version.as_deref(),
+            Some(soft_ver),
+            "{}",
+            name
+        );
+        assert_eq!(header.report_id.as_deref(), Some(report_id), "{}", name);
+        assert_eq!(
+            header.report_number.as_deref(),
+            Some(report_number),
+            "{}",
+            name
+        );
+    }
+}
+
+#[test]
+fn it_allows_short_headers() {
+    let src = b"HDR\x1cFEC\x1c8.3\x1cNGP\n";
+    let header = FecFile::from_bytes(src.to_vec()).into_header().unwrap();
+    assert_eq!(header.software_name, "NGP");
+    assert_eq!(header.software_version, None);
+    assert_eq!(header.report_id, None);
+}
+
+#[test]
+fn it_splits_on_an_explicit_separator() {
+    let mut fec = open("tabs.fec");
+    fec.set_separator(Sep::Other(b'\t'));
+    assert_eq!(fec.get_header().unwrap().software_name, "FECfile");
+    assert_eq!(fec.separator(), Some(Sep::Other(b'\t')));
+    assert_eq!(fec.get_cover().unwrap().filer_committee_id, "C00772335");
+    let records = fec.records().collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(records.len(), 8);
+    assert_eq!(
+        records[0].get_str("contributor_last_name"),
+        Some("barbariniweil")
+    );
+
+    // Tabs are never detected.
+    assert!(open("tabs.fec").get_header().is_err());
+}
+
+#[test]
+fn it_prefers_the_explicit_separator_over_detection() {
+    // The header has commas in it, but only the tabs separate fields.
+    let src = b"HDR\tFEC\t8.3\tNGP, Inc\t8\n";
+    let mut fec = FecFile::builder()
+        .separator(Sep::Other(b'\t'))
+        .build_from_bytes(src.to_vec());
+    assert_eq!(fec.get_header().unwrap().software_name, "NGP, Inc");
+}
+
+#[test]
+fn it_parses_legacy_schedule_counts() {
+    let header = open("legacy_header.fec").into_header().unwrap();
+    let counts = header
+        .schedule_counts
+        .iter()
+        .map(|(code, count)| (code.as_str(), *count))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        counts,
+        vec![("SA11A1", 139), ("SA17", 1), ("SB23", 8), ("SB29", 3)]
+    );
+    assert!(open("slash_form.fec")
+        .into_header()
+        .unwrap()
+        .schedule_counts
+        .is_empty());
+}
+
+#[test]
+fn it_checks_declared_schedule_counts() {
+    assert_eq!(
+        open("legacy_header_counts.fec")
+            .check_schedule_counts()
+            .unwrap(),
+        vec![]
+    );
+
+    let mismatches = open("legacy_header.fec").check_schedule_counts().unwrap();
+    let mismatches = mismatches
+        .iter()
+        .map(|m| (m.line_code.as_str(), m.declared, m.actual))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        mismatches,
+        vec![("SA11A1", 139, 2), ("SB23", 8, 3), ("SB29", 3, 2)]
+    );
+
+    // Nothing to check against.
+    assert_eq!(
+        open("slash_form.fec").check_schedule_counts().unwrap(),
+        vec![]
+    );
+}
+
+#[test]
+fn it_checks_schedule_counts_whatever_the_line_filter() {
+    let mut fec = open("legacy_header_counts.fec");
+    fec.set_line_filter(vec!["SB".to_string()]);
+    assert_eq!(fec.check_schedule_counts().unwrap(), vec![]);
+}
+
+#[test]
+fn it_only_checks_declared_schedule_counts() {
+    let path = "../../test/fecs/legacy_header_counts.fec";
+    let mut src = std::fs::read(path).unwrap();
+    src.extend_from_slice(b"TEXT,C00101766,SB23,a memo\n");
+    let mut fec = FecFile::from_bytes(src);
+    assert_eq!(fec.check_schedule_counts().unwrap(), vec![]);
+}
+
+#[test]
+fn it_checks_schedule_counts_declared_by_the_cover() {
+    let mut fec = open("f8_creditors.fec");
+    let counts = fec.get_cover().unwrap().schedule_counts.clone();
+    assert_eq!(
+        counts.into_iter().collect::<Vec<_>>(),
+        vec![("F8II".to_string(), 2)]
+    );
+    assert_eq!(fec.check_schedule_counts().unwrap(), vec![]);
+
+    // Drop one of the two creditors in part II.
+    let path = "../../test/fecs/f8_creditors.fec";
+    let src = std::fs::read_to_string(path).unwrap();
+    let src = src
+        .lines()
+        .filter(|line| !line.contains("F8II.2"))
+        .map(|line| format!("{}\n", line))
+        .collect::<String>();
+    let mismatches = FecFile::from_bytes(src.into_bytes())
+        .check_schedule_counts()
+        .unwrap();
+    let mismatches = mismatches
+        .iter()
+        .map(|m| (m.line_code.as_str(), m.declared, m.actual))
+        .collect::<Vec<_>>();
+    assert_eq!(mismatches, vec![("F8II", 2, 1)]);
+}
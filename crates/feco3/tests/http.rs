@@ -0,0 +1,125 @@
+#![cfg(feature = "http")]
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::thread;
+
+fn repo_root() -> PathBuf {
+    PathBuf::from("../..")
+}
+
+/// Read the request line and headers, returning the request path
+/// and the start of the requested range, if any.
+fn read_request(stream: &TcpStream) -> (String, Option<usize>) {
+    let mut reader = BufReader::new(stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).unwrap();
+    let mut range_start = None;
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        if line == "\r\n" || line.is_empty() {
+            break;
+        }
+        if let Some(range) = line.to_lowercase().strip_prefix("range: bytes=") {
+            range_start = Some(range.trim().trim_end_matches('-').parse().unwrap());
+        }
+    }
+    let path = request_line.split_whitespace().nth(1).unwrap().to_string();
+    (path, range_start)
+}
+
+/// Serve a redirect from /redirect to /file.fec, which serves `body`.
+fn serve(body: Vec<u8>) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let (path, _) = read_request(&stream);
+            let resp = match path.as_str() {
+                "/redirect" => {
+                    b"HTTP/1.1 302 Found\r\nLocation: /file.fec\r\nContent-Length: 0\r\n\r\n"
+                        .to_vec()
+                }
+                "/file.fec" => {
+                    let mut resp =
+                        format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n", body.len())
+                            .into_bytes();
+                    resp.extend_from_slice(&body);
+                    resp
+                }
+                _ => b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n".to_vec(),
+            };
+            stream.write_all(&resp).unwrap();
+        }
+    });
+    format!("http://{}", addr)
+}
+
+#[test]
+fn it_streams_from_url() {
+    let body = std::fs::read(repo_root().join("test/fecs/slash_form.fec")).unwrap();
+    let base = serve(body);
+    let mut fec = feco3::FecFile::from_url(&format!("{}/redirect", base)).unwrap();
+    assert_eq!(fec.get_header().unwrap().fec_version, "8.3");
+    assert_eq!(fec.get_cover().unwrap().filer_committee_id, "C00772335");
+}
+
+#[test]
+fn it_surfaces_http_errors() {
+    let base = serve(Vec::new());
+    let result = feco3::FecFile::from_url(&format!("{}/missing.fec", base));
+    assert!(matches!(result, Err(feco3::Error::HttpError(_))));
+}
+
+/// Serve `body`, but drop the connection halfway through the first response.
+/// Later requests must ask for the rest with a Range header.
+fn serve_flaky(body: Vec<u8>) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    thread::spawn(move || {
+        for (i, stream) in listener.incoming().enumerate() {
+            let mut stream = stream.unwrap();
+            let (_, range_start) = read_request(&stream);
+            let head = format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n", body.len());
+            if i == 0 {
+                stream.write_all(head.as_bytes()).unwrap();
+                stream.write_all(&body[..body.len() / 2]).unwrap();
+                // Dropping the stream closes the connection early.
+                continue;
+            }
+            let start = range_start.expect("expected a range request");
+            let rest = &body[start..];
+            let head = format!(
+                "HTTP/1.1 206 Partial Content\r\nContent-Length: {}\r\nContent-Range: bytes {}-{}/{}\r\n\r\n",
+                rest.len(),
+                start,
+                body.len() - 1,
+                body.len()
+            );
+            stream.write_all(head.as_bytes()).unwrap();
+            stream.write_all(rest).unwrap();
+        }
+    });
+    format!("http://{}", addr)
+}
+
+#[test]
+fn it_resumes_dropped_connections() {
+    let body = std::fs::read(repo_root().join("test/fecs/1550548.fec")).unwrap();
+    let base = serve_flaky(body);
+    let mut fec = feco3::FecFile::from_url(&format!("{}/file.fec", base)).unwrap();
+    let counts = fec.count_by_line_code().unwrap();
+    assert_eq!(counts["SB23"], 35);
+}
+
+#[test]
+fn it_gives_up_after_max_retries() {
+    let body = std::fs::read(repo_root().join("test/fecs/1550548.fec")).unwrap();
+    let base = serve_flaky(body);
+    let mut fec = feco3::FecFile::from_url_with_retries(&format!("{}/file.fec", base), 0).unwrap();
+    let result = fec.count_by_line_code();
+    assert!(matches!(result, Err(feco3::Error::HttpError(_))));
+}
@@ -0,0 +1,67 @@
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use feco3::FecFile;
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+fn repo_root() -> PathBuf {
+    PathBuf::from("../..")
+}
+
+/// Collects every log message, so the tests can check what was logged.
+struct TestLogger {
+    messages: Mutex<Vec<(Level, String)>>,
+}
+
+impl Log for TestLogger {
+    fn enabled(&self, _metadata: &Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &Record) {
+        let message = (record.level(), record.args().to_string());
+        self.messages.lock().unwrap().push(message);
+    }
+
+    fn flush(&self) {}
+}
+
+static LOGGER: TestLogger = TestLogger {
+    messages: Mutex::new(Vec::new()),
+};
+
+/// Read all the records of `name` with the given mismatch log level,
+/// and return the messages about schema mismatches that were logged.
+fn mismatch_messages(name: &str, level: LevelFilter) -> Vec<(Level, String)> {
+    // The logger can only be set once, so later calls just reuse it.
+    let _ = log::set_logger(&LOGGER);
+    log::set_max_level(LevelFilter::Trace);
+    LOGGER.messages.lock().unwrap().clear();
+    let mut fec = FecFile::from_path(&repo_root().join("test/fecs").join(name)).unwrap();
+    fec.set_mismatch_log_level(level);
+    for record in fec.records() {
+        record.unwrap();
+    }
+    let messages = LOGGER.messages.lock().unwrap();
+    messages
+        .iter()
+        .filter(|(_, message)| message.contains("values"))
+        .cloned()
+        .collect()
+}
+
+// Only one test, since the logger is shared by all the tests in a binary.
+#[test]
+fn it_logs_one_summary_of_schema_mismatches() {
+    let messages = mismatch_messages("too_many_fields.fec", LevelFilter::Warn);
+    assert_eq!(messages.len(), 1, "{:?}", messages);
+    assert_eq!(messages[0].0, Level::Warn);
+    assert!(messages[0].1.contains("had more values"), "{:?}", messages);
+
+    let messages = mismatch_messages("too_many_fields.fec", LevelFilter::Off);
+    assert!(messages.is_empty(), "{:?}", messages);
+
+    // Nothing is logged at error level, even by default.
+    let messages = mismatch_messages("too_many_fields.fec", LevelFilter::Debug);
+    assert!(messages.iter().all(|(level, _)| *level == Level::Debug));
+}
@@ -1,5 +1,6 @@
 use std::path::PathBuf;
 
+#[allow(clippy::single_component_path_imports)]
 use feco3;
 
 fn repo_root() -> PathBuf {
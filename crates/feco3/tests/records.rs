@@ -0,0 +1,1325 @@
+use std::path::PathBuf;
+
+use feco3::FecFile;
+
+fn repo_root() -> PathBuf {
+    PathBuf::from("../..")
+}
+
+fn open(name: &str) -> FecFile {
+    FecFile::from_path(&repo_root().join("test/fecs").join(name)).unwrap()
+}
+
+#[test]
+fn it_counts_records() {
+    let mut fec = open("slash_form.fec");
+    assert_eq!(fec.records().count(), 8);
+}
+
+#[test]
+fn it_filters_records_by_line_code() {
+    let mut fec = open("slash_form.fec");
+    let codes = fec
+        .records()
+        .map(|r| r.unwrap())
+        .filter(|r| r.record_code.starts_with("SC"))
+        .map(|r| r.record_code)
+        .collect::<Vec<_>>();
+    assert_eq!(codes, vec!["SC/10", "SC/10", "SC2/10"]);
+}
+
+#[test]
+fn it_skips_lines_not_in_the_line_filter() {
+    let mut fec = open("slash_form.fec");
+    fec.set_line_filter(vec!["SA".to_string(), "SD".to_string()]);
+    let codes = fec
+        .records()
+        .map(|r| r.unwrap().record_code)
+        .collect::<Vec<_>>();
+    assert_eq!(codes, vec!["SA11AI", "SA11AI", "SD10"]);
+    assert_eq!(fec.get_cover().unwrap().form_type, "F3A");
+}
+
+#[test]
+fn it_matches_line_filter_by_prefix() {
+    let mut fec = open("1550548.fec");
+    fec.set_line_filter(vec!["SB".to_string()]);
+    let records = fec.records().map(|r| r.unwrap()).collect::<Vec<_>>();
+    assert_eq!(records.len(), 42);
+    assert!(records.iter().any(|r| r.record_code == "SB21B"));
+    assert!(records.iter().any(|r| r.record_code == "SB23"));
+}
+
+#[test]
+fn it_filters_records_by_value() {
+    let mut fec = open("1550548.fec");
+    fec.set_line_filter(vec!["SA".to_string()]);
+    fec.set_record_filter(Box::new(|record| {
+        let amount = record.get_value("contribution_amount").unwrap().to_string();
+        amount.parse::<f64>().is_ok_and(|amount| amount > 2000.0)
+    }));
+    let records = fec.records().map(|r| r.unwrap()).collect::<Vec<_>>();
+    assert_eq!(records.len(), 22);
+    assert!(records.iter().all(|r| r.record_code.starts_with("SA")));
+    let counted = fec
+        .stats()
+        .by_line_code
+        .values()
+        .map(|s| s.records)
+        .sum::<u64>();
+    assert_eq!(counted, 22);
+}
+
+#[test]
+fn it_reads_gzipped_files() {
+    let mut fec = open("slash_form.fec.gz");
+    assert_eq!(fec.get_header().unwrap().fec_version, "8.3");
+    assert_eq!(fec.records().count(), 8);
+}
+
+#[test]
+fn it_fails_fast_on_corrupt_rows_by_default() {
+    let mut fec = open("corrupt_row.fec");
+    let results = fec.records().collect::<Vec<_>>();
+    assert!(results[2].is_err());
+}
+
+#[test]
+fn it_collects_corrupt_rows() {
+    let mut fec = open("corrupt_row.fec");
+    fec.set_error_mode(feco3::ErrorMode::Collect);
+    let records = fec.records().map(|r| r.unwrap()).collect::<Vec<_>>();
+    assert_eq!(records.len(), 7);
+    let errors = fec.errors();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].row_index, 2);
+    assert_eq!(errors[0].raw, b"ZZ99\x1cthis row is corrupt\x1c\x1c");
+    assert!(matches!(errors[0].error, feco3::Error::SchemaError(_, _)));
+}
+
+#[test]
+fn it_reads_concatenated_filings() {
+    let mut fec = open("multi_filing.fec");
+    fec.set_multi_filing(true);
+    let pairs = fec
+        .filing_records()
+        .map(|r| r.unwrap())
+        .map(|(i, r)| (i, r.record_code))
+        .collect::<Vec<_>>();
+    assert_eq!(pairs.iter().filter(|(i, _)| *i == 0).count(), 8);
+    assert_eq!(pairs.iter().filter(|(i, _)| *i == 1).count(), 3);
+    assert_eq!(pairs.last().unwrap(), &(1, "SA17A".to_string()));
+    assert_eq!(fec.get_header().unwrap().fec_version, "8.1");
+    assert_eq!(fec.get_cover().unwrap().filer_committee_id, "C00580100");
+}
+
+#[test]
+fn it_reads_single_filings_in_multi_filing_mode() {
+    let mut fec = open("slash_form.fec");
+    fec.set_multi_filing(true);
+    let indexes = fec
+        .filing_records()
+        .map(|r| r.unwrap().0)
+        .collect::<Vec<_>>();
+    assert_eq!(indexes, vec![0; 8]);
+}
+
+#[test]
+fn it_reports_progress_in_bytes() {
+    use std::sync::{Arc, Mutex};
+
+    let reported = Arc::new(Mutex::new(Vec::new()));
+    let reported2 = reported.clone();
+    let mut fec = open("1550548.fec");
+    fec.set_progress_callback(Box::new(move |n| reported2.lock().unwrap().push(n)));
+    fec.records().for_each(|r| {
+        r.unwrap();
+    });
+
+    let reported = reported.lock().unwrap();
+    let size = std::fs::metadata(repo_root().join("test/fecs/1550548.fec"))
+        .unwrap()
+        .len();
+    assert!(reported.len() > 1);
+    assert!(reported.windows(2).all(|w| w[0] < w[1]));
+    assert_eq!(*reported.last().unwrap(), size);
+}
+
+#[test]
+fn it_uses_registered_schemas() {
+    use feco3::record::{FieldSchema, RecordSchema, Value, ValueType};
+
+    let field = |name: &str| FieldSchema {
+        name: name.to_string(),
+        typ: ValueType::String,
+        label: None,
+    };
+    let schema = RecordSchema {
+        code: "ZZ99".to_string(),
+        fields: vec![field("note"), field("a"), field("b")],
+        repeating: 0,
+    };
+    let mut fec = open("corrupt_row.fec");
+    fec.register_schema("8.3", schema);
+    let records = fec.records().collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(records.len(), 8);
+    let custom = &records[2];
+    assert_eq!(custom.record_code, "ZZ99");
+    assert_eq!(
+        custom.get_value("note"),
+        Some(&Value::String(Some("this row is corrupt".to_string())))
+    );
+    assert_eq!(custom.get_value("b"), Some(&Value::String(None)));
+}
+
+#[test]
+fn it_counts_extra_fields() {
+    let src = std::fs::read_to_string(repo_root().join("test/fecs/slash_form.fec")).unwrap();
+    let mut lines = src.lines().map(String::from).collect::<Vec<_>>();
+    // The first itemization, after the header and cover.
+    lines[2].push_str("\x1cunexpected1\x1cunexpected2");
+    let mut fec = FecFile::from_bytes((lines.join("\n") + "\n").into_bytes());
+    let records = fec.records().collect::<Result<Vec<_>, _>>().unwrap();
+
+    let first = &records[0];
+    assert!(first.had_extra_fields());
+    assert_eq!(first.extra_fields, 2);
+    assert_eq!(first.values.len(), first.schema.fields.len() + 2);
+    assert_eq!(first.values.last().unwrap().to_string(), "unexpected2");
+    assert!(records[1..].iter().all(|r| !r.had_extra_fields()));
+}
+
+#[test]
+fn it_keeps_extra_fields_of_real_files() {
+    let mut fec = open("too_many_fields.fec");
+    let records = fec.records().collect::<Result<Vec<_>, _>>().unwrap();
+    assert!(records.iter().any(|r| r.had_extra_fields()));
+}
+
+#[test]
+fn it_counts_lines_by_code() {
+    use std::collections::HashMap;
+
+    let mut expected: HashMap<String, u64> = HashMap::new();
+    for record in open("1550548.fec").records() {
+        *expected.entry(record.unwrap().record_code).or_default() += 1;
+    }
+    let counts = open("1550548.fec").count_by_line_code().unwrap();
+    assert_eq!(counts, expected);
+    assert_eq!(counts["SB23"], 35);
+}
+
+#[test]
+fn it_errors_on_a_file_truncated_mid_line() {
+    let mut fec = open("truncated.fec");
+    assert_eq!(fec.next_record().unwrap().unwrap().record_code, "SA11AI");
+    assert!(matches!(
+        fec.next_record(),
+        Some(Err(feco3::Error::TruncatedFile))
+    ));
+    assert!(fec.next_record().is_none());
+
+    // A complete last line without a newline after it is fine.
+    let bytes = std::fs::read(repo_root().join("test/fecs/slash_form.fec")).unwrap();
+    let mut fec = FecFile::from_bytes(bytes.trim_ascii_end().to_vec());
+    assert_eq!(fec.records().filter(|r| r.is_ok()).count(), 8);
+}
+
+#[test]
+fn it_scans_line_codes_in_order() {
+    let mut fec = open("slash_form.fec");
+    fec.next_record().unwrap().unwrap();
+    let codes = fec.scan_line_codes().unwrap();
+    assert_eq!(codes, vec!["SA11AI", "SB17", "SC/10", "SC2/10", "SD10"]);
+    // The scan rewinds, so every record can still be read.
+    assert_eq!(fec.records().count(), 8);
+
+    let bytes = std::fs::read(repo_root().join("test/fecs/slash_form.fec")).unwrap();
+    let mut fec = FecFile::from_reader(Box::new(std::io::Cursor::new(bytes)));
+    fec.next_record().unwrap().unwrap();
+    assert!(fec.scan_line_codes().is_err());
+}
+
+#[test]
+fn it_shares_schemas_between_records() {
+    use std::sync::Arc;
+
+    let records = open("slash_form.fec")
+        .records()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    let sb17 = records
+        .iter()
+        .filter(|r| r.record_code == "SB17")
+        .collect::<Vec<_>>();
+    assert_eq!(sb17.len(), 2);
+    assert!(Arc::ptr_eq(&sb17[0].schema, &sb17[1].schema));
+    assert!(!Arc::ptr_eq(&records[0].schema, &sb17[0].schema));
+}
+
+#[test]
+fn it_tracks_row_index_and_byte_offset() {
+    let raw = std::fs::read(repo_root().join("test/fecs/1550548.fec")).unwrap();
+    let records = open("1550548.fec")
+        .records()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    for (i, record) in records.iter().enumerate() {
+        assert_eq!(record.row_index, i);
+        let offset = record.byte_offset.unwrap() as usize;
+        assert!(raw[offset..].starts_with(record.record_code.as_bytes()));
+    }
+    assert!(records
+        .windows(2)
+        .all(|w| w[0].byte_offset < w[1].byte_offset));
+}
+
+#[test]
+fn it_keeps_row_indices_of_filtered_lines() {
+    let mut fec = open("slash_form.fec");
+    fec.set_line_filter(vec!["SB".to_string()]);
+    let indices = fec
+        .records()
+        .map(|r| r.unwrap().row_index)
+        .collect::<Vec<_>>();
+    assert_eq!(indices, vec![2, 3]);
+}
+
+fn claiming_version(version: &str) -> FecFile {
+    let src = std::fs::read_to_string(repo_root().join("test/fecs/slash_form.fec")).unwrap();
+    let src = src.replacen("\x1c8.3\x1c", &format!("\x1c{}\x1c", version), 1);
+    FecFile::from_bytes(src.into_bytes())
+}
+
+#[test]
+fn it_errors_on_unknown_versions_by_default() {
+    let mut fec = claiming_version("99.0");
+    assert!(fec.get_header().is_err());
+}
+
+#[test]
+fn it_falls_back_to_the_latest_schema() {
+    let mut fec = claiming_version("99.0");
+    fec.set_schema_fallback(true);
+    assert_eq!(fec.get_header().unwrap().fec_version, "99.0");
+    let cover = fec.get_cover().unwrap();
+    assert_eq!(cover.form_type, "F3A");
+    assert_eq!(cover.filer_committee_id, "C00772335");
+    let records = fec.records().collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(records.len(), 8);
+    assert_eq!(
+        records[0].schema.fields[0].name,
+        "filer_committee_id_number"
+    );
+}
+
+#[test]
+fn it_returns_typed_record_errors() {
+    use feco3::{Error, RecordParseError};
+
+    let err = feco3::record::ValueType::Integer
+        .parse_to_value(Some(&"12x".to_string()))
+        .unwrap_err();
+    assert!(matches!(err, Error::RecordParseError(_)));
+    let boxed: Box<dyn std::error::Error> = Box::new(err);
+    let source = boxed.source().unwrap();
+    match source.downcast_ref::<RecordParseError>() {
+        Some(RecordParseError::InvalidValue { raw, .. }) => assert_eq!(raw, "12x"),
+        other => panic!("expected InvalidValue, got {:?}", other),
+    }
+}
+
+#[test]
+fn it_returns_typed_cover_errors() {
+    use feco3::{CoverParseError, Error};
+
+    let mut fec = FecFile::from_bytes(b"HDR\x1cFEC\x1c8.3\x1cNGP\x1c8\x1c\x1c\n".to_vec());
+    match fec.get_cover() {
+        Err(Error::CoverParseError(e)) => assert_eq!(e, CoverParseError::Missing),
+        other => panic!("expected a CoverParseError, got {:?}", other.map(|_| ())),
+    }
+
+    let src = b"HDR\x1cFEC\x1c8.3\x1cNGP\x1c8\x1c\x1c\nF3A\n".to_vec();
+    match FecFile::from_bytes(src).get_cover() {
+        Err(Error::CoverParseError(e)) => assert_eq!(
+            e,
+            CoverParseError::MissingField("filer_committee_id_number".to_string())
+        ),
+        other => panic!("expected a CoverParseError, got {:?}", other.map(|_| ())),
+    }
+}
+
+fn raw_lines(name: &str) -> Vec<Vec<String>> {
+    open(name).lines().map(|l| l.unwrap()).collect()
+}
+
+#[test]
+fn it_does_not_unquote_ascii28_fields() {
+    let original = raw_lines("slash_form.fec");
+    let quoted = raw_lines("quotes_fs.fec");
+    assert_eq!(quoted.len(), original.len());
+    for (q, o) in quoted.iter().zip(original.iter()) {
+        assert_eq!(q.len(), o.len());
+    }
+    assert_eq!(quoted[0][8], "\"Dale\" the Man");
+    assert_eq!(quoted[1][7], "\"Torres");
+}
+
+#[test]
+fn it_unquotes_comma_fields_with_commas_and_newlines() {
+    let original = raw_lines("v5.fec");
+    let quoted = raw_lines("quotes_comma.fec");
+    assert_eq!(quoted.len(), original.len());
+    for (q, o) in quoted.iter().zip(original.iter()) {
+        assert_eq!(q.len(), o.len());
+    }
+    assert_eq!(quoted[0][11], "Continental \"Airlines\",\nInc.");
+}
+
+#[test]
+fn it_resyncs_after_an_unterminated_quote() {
+    let path = repo_root().join("test/fecs/unterminated_quote.fec");
+    let bytes = std::fs::read(&path).unwrap();
+    let mut fec = open("unterminated_quote.fec");
+    fec.set_error_mode(feco3::ErrorMode::Collect);
+    let records = fec.records().map(|r| r.unwrap()).collect::<Vec<_>>();
+    let ids = records
+        .iter()
+        .map(|r| r.get_value("transaction_id").unwrap().to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(ids, vec!["SA11A1.0", "SA11A1.2"]);
+    // The offsets still point at the lines in the file.
+    let offset = records[1].byte_offset.unwrap() as usize;
+    assert!(bytes[offset..].starts_with(b"SA11A1,C00101766,IND,Smith^Jane"));
+    let errors = fec.errors();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].row_index, 1);
+    assert!(matches!(
+        errors[0].error,
+        feco3::Error::RecordParseError(feco3::RecordParseError::Malformed(_))
+    ));
+}
+
+#[test]
+fn it_gathers_repeating_fields_into_lists() {
+    use feco3::record::{FieldSchema, RecordSchema, Value, ValueType};
+
+    let field = |name: &str| FieldSchema {
+        name: name.to_string(),
+        typ: ValueType::String,
+        label: None,
+    };
+    // Each affiliated committee is a name and an ID.
+    let schema = RecordSchema {
+        code: "F1S".to_string(),
+        fields: vec![
+            field("filer_committee_id_number"),
+            field("affiliated_committee_name"),
+            field("affiliated_committee_id_number"),
+        ],
+        repeating: 2,
+    };
+    let mut fec = open("f1_affiliates.fec");
+    fec.register_schema("8.3", schema);
+    assert_eq!(fec.get_cover().unwrap().form_type, "F1N");
+    let records = fec.records().collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(records.len(), 2);
+    let strings = |values: &[&str]| {
+        let items = values.iter().map(|v| Value::String(Some(v.to_string())));
+        Value::List(items.collect())
+    };
+    assert_eq!(
+        records[0].get_value("affiliated_committee_name"),
+        Some(&strings(&[
+            "AFFILIATE ONE",
+            "AFFILIATE TWO",
+            "AFFILIATE THREE"
+        ]))
+    );
+    assert_eq!(
+        records[0].get_value("affiliated_committee_id_number"),
+        Some(&strings(&["C00000001", "C00000002", "C00000003"]))
+    );
+    assert_eq!(records[0].extra_fields, 0);
+    assert_eq!(
+        records[1].get_value("affiliated_committee_id_number"),
+        Some(&strings(&["C00000004"]))
+    );
+}
+
+#[test]
+fn it_errors_on_registered_schemas_repeating_too_many_fields() {
+    use feco3::record::{FieldSchema, RecordParseError, RecordSchema, ValueType};
+
+    let schema = RecordSchema {
+        code: "F1S".to_string(),
+        fields: vec![FieldSchema {
+            name: "filer_committee_id_number".to_string(),
+            typ: ValueType::String,
+            label: None,
+        }],
+        repeating: 2,
+    };
+    let mut fec = open("f1_affiliates.fec");
+    fec.register_schema("8.3", schema);
+    let err = fec.records().find_map(Result::err).unwrap();
+    assert!(matches!(
+        err,
+        feco3::Error::RecordParseError(RecordParseError::InvalidRepeating {
+            repeating: 2,
+            fields: 1
+        })
+    ));
+}
+
+#[test]
+fn it_builds_configured_files() {
+    use feco3::record::{FieldSchema, RecordSchema, ValueType};
+    use feco3::ErrorMode;
+
+    let schema = RecordSchema {
+        code: "ZZ99".to_string(),
+        fields: vec![FieldSchema {
+            name: "note".to_string(),
+            typ: ValueType::String,
+            label: None,
+        }],
+        repeating: 0,
+    };
+    let path = repo_root().join("test/fecs/corrupt_row.fec");
+    let mut fec = FecFile::builder()
+        .line_filter(vec!["ZZ".to_string(), "SD".to_string()])
+        .register_schema("8.3", schema)
+        .error_mode(ErrorMode::Collect)
+        .build_from_path(&path)
+        .unwrap();
+    let codes = fec
+        .records()
+        .map(|r| r.unwrap().record_code)
+        .collect::<Vec<_>>();
+    assert_eq!(codes, vec!["ZZ99", "SD10"]);
+    assert!(fec.errors().is_empty());
+}
+
+#[test]
+fn it_reads_f3x_summaries() {
+    use feco3::schemas::{CoercingLineParser, LineParser};
+
+    let text = std::fs::read_to_string(repo_root().join("test/fecs/1550548.fec")).unwrap();
+    let cover_line = text.lines().nth(1).unwrap();
+    let fields = cover_line
+        .split('\x1c')
+        .map(|s| s.to_string())
+        .collect::<Vec<_>>();
+    let record = CoercingLineParser::new()
+        .parse_line("8.3", &mut fields.iter())
+        .unwrap();
+    let summary = record.as_f3x().unwrap();
+    assert_eq!(summary.total_receipts, Some(104171.78));
+    assert_eq!(summary.total_disbursements, Some(95313.87));
+    assert_eq!(summary.cash_on_hand_close_of_period, Some(115269.65));
+    assert_eq!(
+        summary.coverage_through_date,
+        chrono::NaiveDate::from_ymd_opt(2021, 6, 30)
+    );
+
+    let mut fec = open("1550548.fec");
+    let itemization = fec.next_record().unwrap().unwrap();
+    assert!(itemization.as_f3x().is_none());
+}
+
+#[test]
+fn it_transforms_field_values() {
+    use feco3::record::Value;
+
+    let src = std::fs::read(repo_root().join("test/fecs/slash_form.fec")).unwrap();
+    let src = String::from_utf8(src)
+        .unwrap()
+        .replace("\x1cbarbariniweil\x1c", "\x1c  barbariniweil \x1c");
+    let mut fec = FecFile::from_bytes(src.into_bytes());
+    let trim = |v: Value| match v {
+        Value::String(Some(s)) => Value::String(Some(s.trim().to_string())),
+        v => v,
+    };
+    let upper = |v: Value| match v {
+        Value::String(Some(s)) => Value::String(Some(s.to_uppercase())),
+        v => v,
+    };
+    fec.add_field_transform("SA11AI", "contributor_last_name", Box::new(trim));
+    fec.add_field_transform("sa11ai", "contributor_last_name", Box::new(upper));
+    let record = fec.next_record().unwrap().unwrap();
+    assert_eq!(
+        record.get_value("contributor_last_name"),
+        Some(&Value::String(Some("BARBARINIWEIL".to_string())))
+    );
+    // Other fields are left alone.
+    assert_eq!(
+        record.get_value("contributor_first_name"),
+        Some(&Value::String(Some("dale".to_string())))
+    );
+}
+
+#[test]
+fn it_signs_amounts_for_summing() {
+    let src = std::fs::read_to_string(repo_root().join("test/fecs/1550548.fec")).unwrap();
+    let lines = src.lines().collect::<Vec<_>>();
+    let contribution = lines[2].to_string();
+    let with_field = |line: &str, index: usize, value: &str| {
+        let mut fields = line.split('\x1c').collect::<Vec<_>>();
+        fields[index] = value;
+        fields.join("\x1c")
+    };
+    // Field 20 is the contribution amount, 42 the memo code.
+    let memo = with_field(&contribution, 42, "X");
+    let refund = with_field(&contribution, 20, "-250.00");
+    let disbursement = lines.iter().find(|l| l.starts_with("SB23")).unwrap();
+    let src = [
+        lines[0],
+        lines[1],
+        &contribution,
+        &memo,
+        &refund,
+        disbursement,
+    ]
+    .join("\n");
+    let mut fec = FecFile::from_bytes(src.into_bytes());
+    let amounts = fec
+        .records()
+        .map(|r| r.unwrap().signed_amount())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        amounts,
+        vec![Some(1000.0), None, Some(-250.0), Some(-5000.0)]
+    );
+}
+
+#[test]
+fn it_flags_or_skips_duplicate_transaction_ids() {
+    use feco3::DuplicateMode;
+
+    let ids = |mode: DuplicateMode| {
+        let mut fec = open("duplicate_transaction.fec");
+        fec.set_duplicate_mode(mode);
+        fec.records()
+            .map(|r| r.unwrap())
+            .map(|r| (r.transaction_id().unwrap().to_string(), r.is_duplicate))
+            .collect::<Vec<_>>()
+    };
+    let flagged = ids(DuplicateMode::Flag);
+    assert_eq!(flagged.len(), 9);
+    assert_eq!(flagged[0], ("SA11AI.4265".to_string(), false));
+    assert_eq!(flagged[2], ("SA11AI.4265".to_string(), true));
+    assert_eq!(flagged.iter().filter(|(_, dupe)| *dupe).count(), 1);
+
+    let skipped = ids(DuplicateMode::Skip);
+    assert_eq!(skipped.len(), 8);
+    assert!(skipped.iter().all(|(_, dupe)| !dupe));
+
+    // Nothing is tracked by default.
+    assert!(ids(DuplicateMode::Allow).iter().all(|(_, dupe)| !dupe));
+}
+
+#[test]
+fn it_overrides_field_types() {
+    use feco3::record::{Value, ValueType};
+
+    let mut fec = open("slash_form.fec");
+    fec.set_field_type("sa11ai", "contributor_zip_code", ValueType::Integer);
+    let record = fec.next_record().unwrap().unwrap();
+    assert_eq!(record.record_code, "SA11AI");
+    assert_eq!(
+        record.get_value("contributor_zip_code"),
+        Some(&Value::Integer(Some(10021)))
+    );
+    assert_eq!(
+        record.get_value("contributor_state"),
+        Some(&Value::String(Some("NY".to_string())))
+    );
+}
+
+#[test]
+fn it_rewinds_seekable_files() {
+    for name in ["slash_form.fec", "slash_form.fec.gz"] {
+        let mut fec = open(name);
+        let first = fec.records().count();
+        assert!(first > 0);
+        fec.rewind().unwrap();
+        assert_eq!(fec.get_header().unwrap().fec_version, "8.3");
+        assert_eq!(fec.records().count(), first);
+    }
+}
+
+#[test]
+fn it_cannot_rewind_plain_readers() {
+    let file = std::fs::File::open(repo_root().join("test/fecs/slash_form.fec")).unwrap();
+    let mut fec = FecFile::from_reader(Box::new(file));
+    fec.records().count();
+    match fec.rewind() {
+        Err(feco3::Error::IoError(e)) => {
+            assert_eq!(e.kind(), std::io::ErrorKind::Unsupported)
+        }
+        other => panic!("expected an io error, got {:?}", other),
+    }
+}
+
+#[test]
+fn it_keeps_raw_fields_when_asked() {
+    let mut fec = open("slash_form.fec");
+    assert!(fec.next_record().unwrap().unwrap().raw.is_none());
+
+    let path = repo_root().join("test/fecs/slash_form.fec");
+    let mut fec = FecFile::builder()
+        .keep_raw(true)
+        .build_from_path(&path)
+        .unwrap();
+    for record in fec.records() {
+        let record = record.unwrap();
+        let raw = record.raw.as_ref().unwrap();
+        assert_eq!(raw[0], record.record_code);
+        // The raw fields include the line code, the values don't.
+        assert_eq!(raw.len() - 1, record.values.len());
+    }
+}
+
+#[test]
+fn it_tallies_schema_deviations() {
+    use feco3::record::ValueType;
+
+    let mut fec = open("too_many_fields.fec");
+    fec.records().for_each(|r| drop(r.unwrap()));
+    let stats = &fec.stats().by_line_code["SA11D"];
+    assert_eq!(stats.records, 3);
+    assert_eq!(stats.fewer_fields, 0);
+    assert_eq!(stats.more_fields, 2);
+    assert_eq!(stats.coercion_failures, 0);
+
+    let src = std::fs::read_to_string(repo_root().join("test/fecs/too_few_fields.fec")).unwrap();
+    let mut lines = src.lines().map(String::from).collect::<Vec<_>>();
+    // Drop the last two fields of the first itemization.
+    for _ in 0..2 {
+        let end = lines[2].rfind('\x1c').unwrap();
+        lines[2].truncate(end);
+    }
+    let mut fec = FecFile::from_bytes((lines.join("\n") + "\n").into_bytes());
+    fec.set_field_type("SA11D", "contributor_state", ValueType::Integer);
+    fec.records().for_each(|r| drop(r.unwrap()));
+    let stats = &fec.stats().by_line_code["SA11D"];
+    assert_eq!(stats.records, 3);
+    assert_eq!(stats.fewer_fields, 1);
+    assert_eq!(stats.more_fields, 0);
+    assert_eq!(stats.coercion_failures, 3);
+}
+
+#[test]
+fn it_puts_f99_text_in_the_text_field() {
+    use feco3::record::Value;
+
+    let mut src = std::fs::read(repo_root().join("test/fecs/slash_form.fec")).unwrap();
+    src.extend_from_slice(
+        b"F99\x1cC00772335\x1c\x1c\x1c\x1c\x1c\x1c\x1c\x1c\x1c\x1c\x1c\x1c\x1c\x1c\n",
+    );
+    src.extend_from_slice(b"[BEGINTEXT]\nfirst line\n\n  indented\x1cline\n[ENDTEXT]\n");
+    let mut fec = FecFile::from_bytes(src);
+    let records = fec.records().collect::<Result<Vec<_>, _>>().unwrap();
+    let f99 = records.last().unwrap();
+    assert_eq!(f99.record_code, "F99");
+    assert_eq!(
+        f99.get_value("text"),
+        Some(&Value::String(Some(
+            "first line\n\n  indented\x1cline".to_string()
+        )))
+    );
+    assert_eq!(f99.extra_fields, 0);
+}
+
+#[test]
+fn it_fails_on_latin1_fields_by_default() {
+    let mut fec = open("latin1.fec");
+    match fec.next_record().unwrap() {
+        Err(feco3::Error::RecordParseError(feco3::RecordParseError::Malformed(msg))) => {
+            assert!(msg.contains("UTF-8"), "{}", msg)
+        }
+        other => panic!("expected a malformed record, got {:?}", other),
+    }
+}
+
+#[test]
+fn it_decodes_latin1_fields_when_asked() {
+    let mut fec = open("latin1.fec");
+    fec.set_windows_1252_fallback(true);
+    let first = fec.next_record().unwrap().unwrap();
+    assert_eq!(
+        first
+            .get_value("contributor_last_name")
+            .unwrap()
+            .to_string(),
+        "Peña"
+    );
+    assert_eq!(
+        first
+            .get_value("contributor_first_name")
+            .unwrap()
+            .to_string(),
+        "José"
+    );
+    assert_eq!(fec.records().count(), 7);
+}
+
+#[test]
+fn it_nulls_type_mismatches_in_lenient_mode() {
+    use feco3::record::{Value, ValueType};
+
+    let mut fec = open("slash_form.fec");
+    fec.set_field_type("SA11AI", "contributor_state", ValueType::Integer);
+    let first = fec.next_record().unwrap().unwrap();
+    assert_eq!(
+        first.get_value("contributor_state"),
+        Some(&Value::Integer(None))
+    );
+}
+
+#[test]
+fn it_sums_decimal_amounts_exactly() {
+    use feco3::record::{Value, ValueType};
+    use rust_decimal::Decimal;
+
+    let mut fec = open("1550548.fec");
+    fec.set_field_type("SA11AI", "contribution_amount", ValueType::Decimal);
+    let mut total = Decimal::ZERO;
+    for record in fec.records() {
+        let record = record.unwrap();
+        if let Some(Value::Decimal(Some(amount))) = record.get_value("contribution_amount") {
+            total += amount;
+        }
+    }
+    assert_eq!(total, "103071.78".parse::<Decimal>().unwrap());
+}
+
+#[test]
+fn it_names_the_field_and_line_of_invalid_values() {
+    use feco3::record::ValueType;
+    use feco3::ParseMode;
+
+    let mut fec = open("slash_form.fec");
+    fec.set_field_type("SA11AI", "contributor_state", ValueType::Float);
+    fec.set_parse_mode(ParseMode::Strict);
+    let message = fec.next_record().unwrap().unwrap_err().to_string();
+    assert!(
+        message
+            .contains("failed to parse field 'contributor_state' of line 'SA11AI' as Float: 'NY'"),
+        "{}",
+        message
+    );
+}
+
+#[test]
+fn it_fails_type_mismatches_in_strict_mode() {
+    use feco3::record::ValueType;
+    use feco3::{ErrorMode, ParseMode, RecordParseError};
+
+    let mut fec = open("slash_form.fec");
+    fec.set_field_type("SA11AI", "contributor_state", ValueType::Integer);
+    fec.set_parse_mode(ParseMode::Strict);
+    match fec.next_record().unwrap() {
+        Err(feco3::Error::RecordParseError(RecordParseError::InvalidValue {
+            typ, raw, ..
+        })) => {
+            assert_eq!(typ, ValueType::Integer);
+            assert_eq!(raw, "NY");
+        }
+        other => panic!("expected an invalid value, got {:?}", other),
+    }
+
+    let mut fec = open("too_many_fields.fec");
+    fec.set_parse_mode(ParseMode::Strict);
+    fec.set_error_mode(ErrorMode::Collect);
+    assert_eq!(fec.records().count(), 1);
+    assert_eq!(fec.errors().len(), 2);
+    assert!(matches!(
+        fec.errors()[0].error,
+        feco3::Error::RecordParseError(RecordParseError::TooManyValues { .. })
+    ));
+}
+
+#[test]
+fn it_fails_invalid_dates_in_strict_mode() {
+    use feco3::record::{Value, ValueType};
+    use feco3::{ParseMode, RecordParseError};
+
+    let mut fec = open("slash_form.fec");
+    fec.set_field_type("SA11AI", "contributor_state", ValueType::Date);
+    let record = fec.next_record().unwrap().unwrap();
+    assert_eq!(
+        record.get_value("contributor_state"),
+        Some(&Value::InvalidDate("NY".to_string()))
+    );
+
+    let mut fec = open("slash_form.fec");
+    fec.set_field_type("SA11AI", "contributor_state", ValueType::Date);
+    fec.set_parse_mode(ParseMode::Strict);
+    match fec.next_record().unwrap() {
+        Err(feco3::Error::RecordParseError(RecordParseError::InvalidValue {
+            typ,
+            raw,
+            field,
+            ..
+        })) => {
+            assert_eq!(typ, ValueType::Date);
+            assert_eq!(raw, "NY");
+            assert_eq!(field.as_deref(), Some("contributor_state"));
+        }
+        other => panic!("expected an invalid value, got {:?}", other),
+    }
+}
+
+#[test]
+fn it_round_trips_records_through_json() {
+    let mut fec = open("slash_form.fec");
+    for record in fec.records() {
+        let record = record.unwrap();
+        let json = serde_json::to_string(&record).unwrap();
+        let back: feco3::record::Record = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.record_code, record.record_code);
+        assert_eq!(back.values, record.values);
+        assert_eq!(back.schema.fields.len(), record.schema.fields.len());
+        assert_eq!(back.row_index, record.row_index);
+        assert_eq!(back.byte_offset, record.byte_offset);
+    }
+}
+
+#[test]
+fn it_hashes_the_content() {
+    let hash = |name: &str| {
+        let mut fec = open(name);
+        fec.enable_content_hash();
+        fec.records().for_each(drop);
+        fec.content_hash().unwrap()
+    };
+    let first = hash("slash_form.fec");
+    assert_eq!(first, hash("slash_form.fec"));
+    assert_ne!(first, hash("v5.fec"));
+    // The same as `sha256sum test/fecs/slash_form.fec`.
+    assert_eq!(
+        first,
+        "c7114fb728624cd821d159f73a99c9e9c24ec79cc9e49f0046d756ca14fb7466"
+    );
+    assert_eq!(open("slash_form.fec").content_hash(), None);
+}
+
+#[test]
+fn it_validates_every_row() {
+    let mut fec = open("corrupt_row.fec");
+    let report = fec.validate().unwrap();
+    assert!(!report.is_valid());
+    assert_eq!(report.records, 7);
+    assert_eq!(report.errors.len(), 1);
+    assert_eq!(report.errors[0].row_index, 2);
+    assert!(matches!(
+        report.errors[0].error,
+        feco3::Error::SchemaError(_, _)
+    ));
+    let counts = report.records_by_line_code();
+    assert_eq!(counts.values().sum::<u64>(), 7);
+    assert!(!counts.contains_key("ZZ99"));
+    assert_eq!(report.stats, *fec.stats());
+    // The errors were moved into the report.
+    assert!(fec.errors().is_empty());
+    assert!(open("slash_form.fec").validate().unwrap().is_valid());
+}
+
+#[test]
+fn it_skips_to_a_row() {
+    let key = |r: &feco3::record::Record| (r.row_index, r.values.clone());
+    let all = open("1550548.fec")
+        .records()
+        .map(|r| key(&r.unwrap()))
+        .collect::<Vec<_>>();
+    let mut fec = open("1550548.fec");
+    fec.skip_to(5).unwrap();
+    let record = fec.next_record().unwrap().unwrap();
+    assert_eq!(record.row_index, 5);
+    assert_eq!(key(&record), all[5]);
+    // Shards of the file cover every record exactly once.
+    let mut fec = open("1550548.fec");
+    fec.skip_to(10).unwrap();
+    let shard = fec
+        .records()
+        .take(10)
+        .map(|r| key(&r.unwrap()))
+        .collect::<Vec<_>>();
+    assert_eq!(shard, all[10..20]);
+    // Skipping backwards does nothing.
+    fec.skip_to(0).unwrap();
+    assert_eq!(key(&fec.next_record().unwrap().unwrap()), all[20]);
+}
+
+#[test]
+fn it_samples_every_nth_record() {
+    let total = open("1550548.fec").records().count();
+    let mut fec = open("1550548.fec");
+    let sampled = fec
+        .sample_every(3)
+        .map(|r| r.unwrap().row_index)
+        .collect::<Vec<_>>();
+    assert_eq!(sampled.len(), total.div_ceil(3));
+    assert!(sampled.iter().all(|i| i % 3 == 0));
+    assert!(fec.get_cover().is_ok());
+}
+
+#[test]
+fn it_zips_values_with_their_field_types() {
+    use feco3::record::ValueType;
+
+    let mut fec = open("too_many_fields.fec");
+    let records = fec.records().collect::<Result<Vec<_>, _>>().unwrap();
+    let record = records.iter().find(|r| r.had_extra_fields()).unwrap();
+    let fields = &record.schema.fields;
+    let typed = record.typed_fields().collect::<Vec<_>>();
+    assert_eq!(typed.len(), record.values.len());
+    for ((name, typ, val), field) in typed.iter().zip(fields) {
+        assert_eq!(name, &field.name);
+        assert_eq!(*typ, field.typ);
+        assert_eq!(val.typ(), field.typ);
+    }
+    let (name, typ, _) = &typed[fields.len()];
+    assert_eq!(name, "extra_1");
+    assert_eq!(*typ, ValueType::String);
+}
+
+#[test]
+fn it_loads_schemas_from_a_directory() {
+    use feco3::record::Value;
+
+    let dir = repo_root().join("test/schemas");
+    let overrides = feco3::schemas::load_from_dir(&dir).unwrap();
+    assert_eq!(overrides.get("8.3", "zz99").unwrap().fields.len(), 3);
+
+    let mut fec = open("corrupt_row.fec");
+    fec.register_schemas_from_dir(&dir).unwrap();
+    let records = fec.records().collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(records.len(), 8);
+    let custom = &records[2];
+    assert_eq!(custom.record_code, "ZZ99");
+    assert_eq!(
+        custom.get_value("note"),
+        Some(&Value::String(Some("this row is corrupt".to_string())))
+    );
+    assert_eq!(custom.get_value("b"), Some(&Value::Integer(None)));
+}
+
+#[test]
+fn it_errors_on_invalid_schema_files() {
+    let dir = std::env::temp_dir().join("feco3_test_invalid_schemas");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("bad.json"), r#"{"8.3": [{"code": "ZZ99"}]}"#).unwrap();
+    let result = feco3::schemas::load_from_dir(&dir);
+    assert!(matches!(result, Err(feco3::Error::IoError(_))));
+}
+
+#[test]
+fn it_errors_on_schema_files_repeating_too_many_fields() {
+    let dir = std::env::temp_dir().join("feco3_test_bad_repeating_schemas");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let schema = r#"{"8.3": [{"code": "F1S", "fields": [], "repeating": 2}]}"#;
+    std::fs::write(dir.join("bad.json"), schema).unwrap();
+    let result = feco3::schemas::load_from_dir(&dir);
+    assert!(matches!(result, Err(feco3::Error::IoError(_))));
+}
+
+#[test]
+fn it_counts_expected_and_received_fields() {
+    use feco3::record::{FieldSchema, RecordSchema, ValueType};
+
+    let src = std::fs::read_to_string(repo_root().join("test/fecs/too_few_fields.fec")).unwrap();
+    let mut lines = src.lines().map(String::from).collect::<Vec<_>>();
+    // Drop the last three fields of the first itemization.
+    for _ in 0..3 {
+        let end = lines[2].rfind('\x1c').unwrap();
+        lines[2].truncate(end);
+    }
+    let mut fec = FecFile::from_bytes((lines.join("\n") + "\n").into_bytes());
+    let records = fec.records().collect::<Result<Vec<_>, _>>().unwrap();
+    let short = &records[0];
+    assert_eq!(short.expected_field_count(), short.schema.fields.len());
+    assert_eq!(
+        short.received_field_count(),
+        short.expected_field_count() - 3
+    );
+    let full = &records[1];
+    assert_eq!(full.received_field_count(), full.expected_field_count());
+
+    let mut fec = open("too_many_fields.fec");
+    let records = fec.records().collect::<Result<Vec<_>, _>>().unwrap();
+    let long = records.iter().find(|r| r.had_extra_fields()).unwrap();
+    assert_eq!(long.received_field_count(), long.values.len());
+    assert!(long.received_field_count() > long.expected_field_count());
+
+    // Every value of a repeating group counts, not just one of each field.
+    let field = |name: &str| FieldSchema {
+        name: name.to_string(),
+        typ: ValueType::String,
+        label: None,
+    };
+    let schema = RecordSchema {
+        code: "F1S".to_string(),
+        fields: vec![
+            field("filer_committee_id_number"),
+            field("affiliated_committee_name"),
+            field("affiliated_committee_id_number"),
+        ],
+        repeating: 2,
+    };
+    let mut fec = open("f1_affiliates.fec");
+    fec.register_schema("8.3", schema);
+    let records = fec.records().collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(records[0].expected_field_count(), 3);
+    assert_eq!(records[0].received_field_count(), 7);
+    assert_eq!(records[1].received_field_count(), 3);
+}
+
+#[cfg(feature = "mmap")]
+#[test]
+fn it_reads_memory_mapped_files() {
+    let path = repo_root().join("test/fecs/1550548.fec");
+    let key = |r: feco3::record::Record| (r.record_code, r.values, r.byte_offset);
+    let expected = open("1550548.fec")
+        .records()
+        .map(|r| key(r.unwrap()))
+        .collect::<Vec<_>>();
+    let mut fec = FecFile::from_mmap(&path).unwrap();
+    let records = fec.records().map(|r| key(r.unwrap())).collect::<Vec<_>>();
+    assert_eq!(records, expected);
+    // The mapping can be read again.
+    fec.rewind().unwrap();
+    assert_eq!(fec.records().count(), expected.len());
+}
+
+#[test]
+fn it_renames_the_schema_of_aliased_line_codes() {
+    let mut fec = open("slash_form.fec");
+    fec.alias_line_code("SA11AI", "SA");
+    let record = fec.next_record().unwrap().unwrap();
+    assert_eq!(record.record_code, "SA11AI");
+    assert_eq!(record.schema.code, "SA");
+    let expected = open("slash_form.fec").next_record().unwrap().unwrap();
+    assert_eq!(record.schema.fields.len(), expected.schema.fields.len());
+    assert_eq!(record.values, expected.values);
+}
+
+#[test]
+fn it_rejects_aliases_with_different_fields() {
+    let mut fec = open("slash_form.fec");
+    fec.alias_line_code("SC/10", "SC");
+    fec.alias_line_code("SC2/10", "SC");
+    let errors: Vec<_> = fec
+        .records()
+        .filter_map(|r| r.err())
+        .map(|e| e.to_string())
+        .collect();
+    assert_eq!(errors.len(), 3);
+    assert!(errors[0].contains("SC/10 and SC2/10"), "{}", errors[0]);
+    assert!(errors[2].contains("SC2/10 and SC/10"), "{}", errors[2]);
+
+    // Aliases with the same fields are fine.
+    let mut fec = open("slash_form.fec");
+    fec.alias_line_code("SA11AI", "SA");
+    fec.alias_line_code("SA11AII", "SA");
+    assert!(fec.records().all(|r| r.is_ok()));
+}
+
+#[test]
+fn it_applies_field_overrides_to_aliased_line_codes() {
+    use feco3::record::{Value, ValueType};
+
+    // By the code in the file.
+    let mut fec = open("slash_form.fec");
+    fec.alias_line_code("SA11AI", "SA");
+    fec.set_field_type("SA11AI", "contributor_zip_code", ValueType::Integer);
+    let record = fec.next_record().unwrap().unwrap();
+    assert_eq!(
+        record.get_value("contributor_zip_code"),
+        Some(&Value::Integer(Some(10021)))
+    );
+
+    // By the canonical code, unless the code in the file has its own.
+    let mut fec = open("slash_form.fec");
+    fec.alias_line_code("SA11AI", "SA");
+    fec.set_field_type("sa", "contributor_zip_code", ValueType::Integer);
+    fec.add_field_transform(
+        "SA",
+        "contributor_state",
+        Box::new(|_| Value::String(Some("canonical".to_string()))),
+    );
+    fec.add_field_transform(
+        "SA11AI",
+        "contributor_state",
+        Box::new(|v| Value::String(Some(format!("own {}", v)))),
+    );
+    let record = fec.next_record().unwrap().unwrap();
+    assert_eq!(
+        record.get_value("contributor_zip_code"),
+        Some(&Value::Integer(Some(10021)))
+    );
+    assert_eq!(
+        record.get_value("contributor_state"),
+        Some(&Value::String(Some("own NY".to_string())))
+    );
+}
+
+#[test]
+fn it_extracts_referenced_ids() {
+    use feco3::record::IdKind;
+
+    let src = std::fs::read_to_string(repo_root().join("test/fecs/too_few_fields.fec")).unwrap();
+    let mut lines = src.lines().map(String::from).collect::<Vec<_>>();
+    // Give the first contribution a donor committee too.
+    let mut fields = lines[2].split('\x1c').map(String::from).collect::<Vec<_>>();
+    fields[25] = "C00401224".to_string();
+    lines[2] = fields.join("\x1c");
+    let mut fec = FecFile::from_bytes((lines.join("\n") + "\n").into_bytes());
+    let record = fec.next_record().unwrap().unwrap();
+    assert_eq!(
+        record.referenced_ids(),
+        vec![
+            (IdKind::Committee, "C00710822".to_string()),
+            (IdKind::Committee, "C00401224".to_string()),
+            (IdKind::Candidate, "S0AK00196".to_string()),
+        ]
+    );
+    // Transaction IDs aren't committee or candidate IDs.
+    let record = open("slash_form.fec").next_record().unwrap().unwrap();
+    assert_eq!(
+        record.referenced_ids(),
+        vec![(IdKind::Committee, "C00772335".to_string())]
+    );
+}
+
+#[test]
+fn it_groups_records_by_line_code() {
+    let groups = open("1550548.fec").into_grouped().unwrap();
+    let mut sizes = groups
+        .iter()
+        .map(|(code, records)| (code.as_str(), records.len()))
+        .collect::<Vec<_>>();
+    sizes.sort();
+    assert_eq!(sizes, vec![("SA11AI", 76), ("SB21B", 7), ("SB23", 35)]);
+    assert!(groups["SB23"].iter().all(|r| r.record_code == "SB23"));
+
+    let mut fec = open("slash_form.fec");
+    let runs = fec
+        .group_sorted()
+        .map(|g| g.map(|(code, records)| (code, records.len())))
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(
+        runs,
+        vec![
+            ("SA11AI".to_string(), 2),
+            ("SB17".to_string(), 2),
+            ("SC/10".to_string(), 2),
+            ("SC2/10".to_string(), 1),
+            ("SD10".to_string(), 1),
+        ]
+    );
+}
+
+#[test]
+fn it_yields_the_group_before_an_error_in_it() {
+    let src = std::fs::read_to_string(repo_root().join("test/fecs/slash_form.fec")).unwrap();
+    let mut lines = src.lines().map(String::from).collect::<Vec<_>>();
+    // Between the two SA11AI lines.
+    lines.insert(3, "ZZ99\x1cthis row is corrupt".to_string());
+    let mut fec = FecFile::from_bytes((lines.join("\n") + "\n").into_bytes());
+    let mut groups = fec.group_sorted();
+    let (code, records) = groups.next().unwrap().unwrap();
+    assert_eq!((code.as_str(), records.len()), ("SA11AI", 1));
+    assert_eq!(records[0].transaction_id(), Some("SA11AI.4265"));
+    assert!(matches!(
+        groups.next(),
+        Some(Err(feco3::Error::SchemaError(_, _)))
+    ));
+    let (code, records) = groups.next().unwrap().unwrap();
+    assert_eq!((code.as_str(), records.len()), ("SA11AI", 1));
+    assert_eq!(records[0].transaction_id(), Some("SA11AI.4170"));
+}
+
+#[test]
+fn it_gets_typed_values_by_field_name() {
+    let record = open("slash_form.fec").next_record().unwrap().unwrap();
+    assert_eq!(
+        record.get_str("contributor_last_name"),
+        Some("barbariniweil")
+    );
+    assert_eq!(record.get_i64("contributor_zip_code"), Some(10021));
+    assert_eq!(record.get_f64("contribution_amount"), Some(1000.0));
+    assert_eq!(
+        record.get_date("contribution_date"),
+        chrono::NaiveDate::from_ymd_opt(2021, 8, 5)
+    );
+    // Values that aren't of the asked for type.
+    assert_eq!(record.get_i64("contribution_amount"), None);
+    assert_eq!(record.get_f64("contributor_last_name"), None);
+    assert_eq!(record.get_date("contribution_amount"), None);
+    // Empty and missing fields.
+    assert_eq!(record.get_str("contributor_prefix"), None);
+    assert_eq!(record.get_str("no_such_field"), None);
+
+    let mut fec = open("slash_form.fec");
+    fec.set_field_type(
+        "SA11AI",
+        "contribution_amount",
+        feco3::record::ValueType::Float,
+    );
+    let record = fec.next_record().unwrap().unwrap();
+    assert_eq!(record.get_str("contribution_amount"), None);
+    assert_eq!(record.get_f64("contribution_amount"), Some(1000.0));
+}
+
+#[test]
+fn it_tags_records_with_their_source_id() {
+    let mut fec = FecFile::builder()
+        .source_id("1550548")
+        .build_from_path(&repo_root().join("test/fecs/1550548.fec"))
+        .unwrap();
+    assert_eq!(fec.source_id(), Some("1550548"));
+    for record in fec.records() {
+        assert_eq!(record.unwrap().source_id.as_deref(), Some("1550548"));
+    }
+    let record = open("slash_form.fec").next_record().unwrap().unwrap();
+    assert_eq!(record.source_id, None);
+}
+
+#[test]
+fn it_parses_formatted_amounts_only_when_lenient() {
+    use feco3::record::{Value, ValueType};
+    use feco3::schemas::ParseMode;
+
+    let src = std::fs::read_to_string(repo_root().join("test/fecs/slash_form.fec")).unwrap();
+    let src = src.replacen("\x1c1000.00\x1c", "\x1c$1,000.00\x1c", 1);
+    let first_amount = |lenient: bool, mode: ParseMode| {
+        let mut fec = FecFile::builder()
+            .field_type("SA11AI", "contribution_amount", ValueType::Float)
+            .lenient_numbers(lenient)
+            .parse_mode(mode)
+            .build_from_bytes(src.clone().into_bytes());
+        let record = fec.next_record().unwrap()?;
+        Ok::<_, feco3::Error>(record.get_value("contribution_amount").unwrap().clone())
+    };
+    assert_eq!(
+        first_amount(true, ParseMode::Lenient).unwrap(),
+        Value::Float(Some(1000.0))
+    );
+    assert_eq!(
+        first_amount(false, ParseMode::Lenient).unwrap(),
+        Value::Float(None)
+    );
+    // Strict mode rejects them even with lenient numbers.
+    match first_amount(true, ParseMode::Strict) {
+        Err(feco3::Error::RecordParseError(feco3::RecordParseError::InvalidValue {
+            raw, ..
+        })) => assert_eq!(raw, "$1,000.00"),
+        other => panic!("expected an invalid value, got {:?}", other),
+    }
+}
@@ -0,0 +1,58 @@
+use feco3::schemas::{documented_field_type, field_names, line_codes_for_version, resolve_version};
+
+#[test]
+fn it_lists_line_codes_for_a_version() {
+    let codes = line_codes_for_version("8.0");
+    assert!(codes.contains(&"^f3[a|n|t]".to_string()));
+    assert!(codes.contains(&"^sa[^3]".to_string()));
+    // Only in version 5 and 6
+    assert!(!codes.contains(&"(^f8$)|(^f8[an])".to_string()));
+    assert!(line_codes_for_version("5.0").contains(&"(^f8$)|(^f8[an])".to_string()));
+}
+
+#[test]
+fn it_gets_field_names() {
+    // The line code of an F3 cover is always suffixed, eg "F3N" for a new filing.
+    let fields = field_names("8.0", "F3N").unwrap();
+    assert_eq!(fields[0], "filer_committee_id_number");
+    assert_eq!(fields[1], "committee_name");
+    assert!(fields.contains(&"coverage_from_date".to_string()));
+    assert_eq!(field_names("8.0", "ZZ99"), None);
+}
+
+#[test]
+fn it_falls_back_to_the_nearest_lower_minor_version() {
+    let expected = field_names("8.4", "SA11AI").unwrap();
+    assert_eq!(field_names("8.5", "SA11AI"), Some(expected.clone()));
+    assert_eq!(field_names("8.12", "SA11AI"), Some(expected));
+    // Don't guess across major versions.
+    assert_eq!(field_names("9.0", "SA11AI"), None);
+}
+
+#[test]
+fn it_resolves_versions_to_bundled_schema_versions() {
+    // Exact
+    assert_eq!(resolve_version("8.3"), Some("8.3".to_string()));
+    assert_eq!(resolve_version("P3.2"), Some("P3.2".to_string()));
+    // Within the range of a known version, once normalized
+    assert_eq!(resolve_version("3.00"), Some("3.0".to_string()));
+    assert_eq!(resolve_version(" 8.1 "), Some("8.1".to_string()));
+    // Falls back to the nearest lower minor version
+    assert_eq!(resolve_version("8.5"), Some("8.4".to_string()));
+    assert_eq!(resolve_version("8.12"), Some("8.4".to_string()));
+    assert_eq!(resolve_version("P3.9"), Some("P3.4".to_string()));
+    // Nothing to fall back to
+    assert_eq!(resolve_version("9.0"), None);
+    assert_eq!(resolve_version("4.0"), None);
+    assert_eq!(resolve_version("garbage"), None);
+}
+
+#[test]
+fn it_looks_up_documented_field_types() {
+    use feco3::record::ValueType;
+
+    let typ = |field| documented_field_type("8.3", "SA11AI", field);
+    assert_eq!(typ("contribution_date"), Some(ValueType::Date));
+    assert_eq!(typ("contribution_amount"), Some(ValueType::Decimal));
+    assert_eq!(typ("contributor_last_name"), None);
+}
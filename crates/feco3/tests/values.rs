@@ -0,0 +1,159 @@
+use chrono::NaiveDate;
+use feco3::record::{Value, ValueType};
+
+fn parse(typ: ValueType, raw: &str) -> Value {
+    typ.parse_to_value(Some(&raw.to_string())).unwrap()
+}
+
+#[test]
+fn it_parses_compact_dates() {
+    let expected = NaiveDate::from_ymd_opt(2020, 1, 15);
+    assert_eq!(parse(ValueType::Date, "20200115"), Value::Date(expected));
+}
+
+#[test]
+fn it_parses_slash_dates() {
+    let expected = NaiveDate::from_ymd_opt(2020, 1, 15);
+    assert_eq!(parse(ValueType::Date, "01/15/2020"), Value::Date(expected));
+}
+
+#[test]
+fn it_parses_empty_dates_as_missing() {
+    assert_eq!(parse(ValueType::Date, ""), Value::Date(None));
+}
+
+#[test]
+fn it_keeps_invalid_dates_raw() {
+    let val = parse(ValueType::Date, "not a date");
+    assert_eq!(val, Value::InvalidDate("not a date".to_string()));
+    assert_eq!(val.typ(), ValueType::Date);
+}
+
+#[test]
+fn it_parses_decimals_exactly() {
+    use rust_decimal::Decimal;
+
+    let decimal = |raw| match parse(ValueType::Decimal, raw) {
+        Value::Decimal(Some(d)) => d,
+        other => panic!("expected a decimal, got {:?}", other),
+    };
+    let sum = decimal("0.1") + decimal("0.2");
+    assert_eq!(sum, "0.3".parse::<Decimal>().unwrap());
+    assert_eq!(parse(ValueType::Decimal, "1000.00").to_string(), "1000.00");
+    assert_eq!(parse(ValueType::Decimal, ""), Value::Decimal(None));
+    assert!(ValueType::Decimal
+        .parse_to_value(Some(&"12x".to_string()))
+        .is_err());
+}
+
+#[test]
+fn it_parses_empty_values_as_typed_nulls() {
+    assert_eq!(parse(ValueType::String, ""), Value::String(None));
+    assert_eq!(parse(ValueType::Integer, ""), Value::Integer(None));
+    assert_eq!(parse(ValueType::Float, ""), Value::Float(None));
+    assert_eq!(parse(ValueType::Date, ""), Value::Date(None));
+    // An empty checkbox is unchecked, but a missing field is still null.
+    assert_eq!(parse(ValueType::Boolean, ""), Value::Boolean(Some(false)));
+    assert_eq!(
+        ValueType::Boolean.parse_to_value(None).unwrap(),
+        Value::Boolean(None)
+    );
+}
+
+#[test]
+fn it_parses_fec_booleans() {
+    for raw in ["X", "x", "Y", "y", "1", "true", "TRUE", " X "] {
+        assert_eq!(parse(ValueType::Boolean, raw), Value::Boolean(Some(true)));
+    }
+    for raw in ["N", "n", "0", "false", "False", "", " "] {
+        assert_eq!(parse(ValueType::Boolean, raw), Value::Boolean(Some(false)));
+    }
+}
+
+#[test]
+fn it_errors_on_unknown_booleans() {
+    for raw in ["maybe", "2", "YES"] {
+        let result = ValueType::Boolean.parse_to_value(Some(&raw.to_string()));
+        assert!(result.is_err(), "{:?} should not parse", raw);
+    }
+}
+
+#[test]
+fn it_renders_nulls_as_empty_cells_and_json_null() {
+    use feco3::writers::ndjson::value_to_json;
+
+    for typ in [ValueType::Integer, ValueType::Float, ValueType::Boolean] {
+        let val = typ.parse_to_value(None).unwrap();
+        assert_eq!(val.to_string(), "");
+        assert_eq!(value_to_json(&val), serde_json::Value::Null);
+    }
+}
+
+#[test]
+fn it_still_errors_on_garbage_integers() {
+    let result = ValueType::Integer.parse_to_value(Some(&"12x".to_string()));
+    assert!(result.is_err());
+}
+
+#[test]
+fn it_round_trips_values_through_json() {
+    let values = vec![
+        Value::String(Some("Smith, \"Bud\"".to_string())),
+        Value::String(None),
+        Value::Integer(Some(-42)),
+        Value::Integer(None),
+        Value::Float(Some(1000.5)),
+        Value::Float(None),
+        Value::Date(NaiveDate::from_ymd_opt(2020, 1, 15)),
+        Value::Date(None),
+        Value::InvalidDate("2020-13-45".to_string()),
+        Value::Boolean(Some(true)),
+        Value::Boolean(None),
+    ];
+    for value in values {
+        let json = serde_json::to_string(&value).unwrap();
+        let back: Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, value, "{}", json);
+    }
+}
+
+#[test]
+fn it_serializes_dates_as_iso_strings() {
+    let value = Value::Date(NaiveDate::from_ymd_opt(2020, 1, 15));
+    assert_eq!(
+        serde_json::to_value(&value).unwrap(),
+        serde_json::json!({"type": "Date", "value": "2020-01-15"})
+    );
+}
+
+#[test]
+fn it_strips_dollar_signs_and_thousands_separators_when_lenient() {
+    let lenient = |typ: ValueType, raw: &str| typ.parse_to_value_lenient(Some(&raw.to_string()));
+    assert_eq!(
+        lenient(ValueType::Float, "$1,234.56").unwrap(),
+        Value::Float(Some(1234.56))
+    );
+    assert_eq!(
+        lenient(ValueType::Float, "1234.56").unwrap(),
+        Value::Float(Some(1234.56))
+    );
+    assert_eq!(
+        lenient(ValueType::Integer, "-$1,000,000").unwrap(),
+        Value::Integer(Some(-1_000_000))
+    );
+    assert_eq!(
+        lenient(ValueType::Decimal, "$0.10").unwrap().to_string(),
+        "0.10"
+    );
+    // Commas that aren't thousands separators.
+    assert!(lenient(ValueType::Float, "1,23").is_err());
+    // Strings are left alone.
+    assert_eq!(
+        lenient(ValueType::String, "$1,234.56").unwrap(),
+        Value::String(Some("$1,234.56".to_string()))
+    );
+    // Without the lenient parsing, these are rejected.
+    assert!(ValueType::Float
+        .parse_to_value(Some(&"$1,234.56".to_string()))
+        .is_err());
+}
@@ -0,0 +1,1054 @@
+use std::path::PathBuf;
+
+use feco3::writers::ndjson::NdjsonProcessor;
+
+fn repo_root() -> PathBuf {
+    PathBuf::from("../..")
+}
+
+#[test]
+fn it_writes_ndjson() {
+    let fec_path = repo_root().join("test/fecs/slash_form.fec");
+    let mut fec = feco3::FecFile::from_path(&fec_path).unwrap();
+    let mut processor = NdjsonProcessor::new(Vec::new());
+    processor.process(&mut fec).unwrap();
+    let out = String::from_utf8(processor.into_inner()).unwrap();
+
+    let objs = out
+        .lines()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(objs.len(), 8);
+    let first = objs[0].as_object().unwrap();
+    assert_eq!(first["form_type"], "SA11AI");
+    assert_eq!(first["filer_committee_id_number"], "C00772335");
+    assert_eq!(first["transaction_id"], "SA11AI.4265");
+    assert_eq!(objs[4]["form_type"], "SC/10");
+}
+
+#[test]
+fn it_writes_the_source_id_of_merged_files() {
+    use feco3::writers::base::RecordWriter;
+    use feco3::writers::csv::CSVSingleFileWriter;
+    use feco3::writers::ndjson::NdjsonWriter;
+
+    let mut ndjson = NdjsonWriter::new(Vec::new()).with_source_id(true);
+    let mut csv = CSVSingleFileWriter::new(Vec::new()).with_source_id(true);
+    for name in ["slash_form.fec", "1550548.fec"] {
+        let mut fec = feco3::FecFile::builder()
+            .source_id(name)
+            .build_from_path(&repo_root().join("test/fecs").join(name))
+            .unwrap();
+        for record in fec.records() {
+            let record = record.unwrap();
+            ndjson.write_record(&record).unwrap();
+            csv.write_record(&record).unwrap();
+        }
+    }
+    ndjson.finish().unwrap();
+    csv.finish().unwrap();
+
+    let out = String::from_utf8(ndjson.into_inner()).unwrap();
+    let objs = out
+        .lines()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(objs.len(), 8 + 118);
+    assert_eq!(objs[0]["source_id"], "slash_form.fec");
+    assert_eq!(objs[8]["source_id"], "1550548.fec");
+
+    let out = csv.into_inner();
+    let mut reader = csv::Reader::from_reader(out.as_slice());
+    let headers = reader.headers().unwrap().clone();
+    assert_eq!(&headers[0], "line_code");
+    assert_eq!(&headers[1], "source_id");
+    let rows = reader.records().map(|r| r.unwrap()).collect::<Vec<_>>();
+    assert_eq!(&rows[0][1], "slash_form.fec");
+    assert_eq!(&rows[8][1], "1550548.fec");
+}
+
+#[test]
+fn it_writes_a_json_array() {
+    use feco3::writers::base::RecordWriter;
+    use feco3::writers::json::JsonArrayWriter;
+
+    let fec_path = repo_root().join("test/fecs/slash_form.fec");
+    let mut fec = feco3::FecFile::from_path(&fec_path).unwrap();
+    let mut writer = JsonArrayWriter::new(Vec::new());
+    for record in fec.records() {
+        writer.write_record(&record.unwrap()).unwrap();
+    }
+    writer.finish().unwrap();
+    let out = String::from_utf8(writer.into_inner()).unwrap();
+
+    let objs = serde_json::from_str::<Vec<serde_json::Value>>(&out).unwrap();
+    assert_eq!(objs.len(), 8);
+    assert_eq!(objs[0]["form_type"], "SA11AI");
+    assert_eq!(objs[4]["form_type"], "SC/10");
+}
+
+#[test]
+fn it_writes_an_empty_json_array() {
+    use feco3::writers::base::RecordWriter;
+    use feco3::writers::json::JsonArrayWriter;
+
+    let mut writer = JsonArrayWriter::new(Vec::new());
+    writer.finish().unwrap();
+    let out = String::from_utf8(writer.into_inner()).unwrap();
+    assert_eq!(out, "[]");
+    assert!(serde_json::from_str::<Vec<serde_json::Value>>(&out)
+        .unwrap()
+        .is_empty());
+}
+
+#[cfg(feature = "sqlite")]
+#[test]
+fn it_writes_sqlite_tables_per_line_code() {
+    use feco3::writers::base::RecordWriter;
+    use feco3::writers::sqlite::SqliteWriter;
+
+    let fec_path = repo_root().join("test/fecs/slash_form.fec");
+    let mut fec = feco3::FecFile::from_path(&fec_path).unwrap();
+    let db_path = std::env::temp_dir().join("feco3_test_sqlite.db");
+    let _ = std::fs::remove_file(&db_path);
+    // A tiny batch size so we exercise committing mid-stream.
+    let mut writer = SqliteWriter::open(&db_path, Some(3)).unwrap();
+    for record in fec.records() {
+        writer.write_record(&record.unwrap()).unwrap();
+    }
+    writer.finish().unwrap();
+
+    let count = |table: &str| -> i64 {
+        let sql = format!("SELECT COUNT(*) FROM \"{}\"", table);
+        writer
+            .connection()
+            .query_row(&sql, [], |row| row.get(0))
+            .unwrap()
+    };
+    assert_eq!(count("SA11AI"), 2);
+    assert_eq!(count("SB17"), 2);
+    assert_eq!(count("SC/10"), 2);
+    assert_eq!(count("SC2/10"), 1);
+    assert_eq!(count("SD10"), 1);
+}
+
+#[cfg(feature = "sqlite")]
+#[test]
+fn it_writes_aliased_line_codes_to_one_sqlite_table() {
+    use feco3::writers::base::RecordWriter;
+    use feco3::writers::sqlite::SqliteWriter;
+
+    let fec_path = repo_root().join("test/fecs/slash_form.fec");
+    let mut fec = feco3::FecFile::from_path(&fec_path).unwrap();
+    fec.alias_line_code("SA11AI", "SA");
+    let db_path = std::env::temp_dir().join("feco3_test_sqlite_aliases.db");
+    let _ = std::fs::remove_file(&db_path);
+    let mut writer = SqliteWriter::open(&db_path, None).unwrap();
+    for record in fec.records() {
+        writer.write_record(&record.unwrap()).unwrap();
+    }
+    writer.finish().unwrap();
+
+    let tables: Vec<String> = writer
+        .connection()
+        .prepare("SELECT name FROM sqlite_master WHERE type = 'table'")
+        .unwrap()
+        .query_map([], |row| row.get(0))
+        .unwrap()
+        .map(|name| name.unwrap())
+        .collect();
+    assert!(tables.contains(&"SA".to_string()));
+    assert!(!tables.contains(&"SA11AI".to_string()));
+}
+
+#[test]
+fn it_writes_parquet_row_groups() {
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+
+    let out_dir = std::env::temp_dir().join("feco3_test_row_groups");
+    let _ = std::fs::remove_dir_all(&out_dir);
+    let fec_path = repo_root().join("test/fecs/1550548.fec");
+    let mut fec = feco3::FecFile::from_path(&fec_path).unwrap();
+    let mut processor =
+        feco3::writers::parquet::ParquetProcessor::with_row_group_size(out_dir.clone(), 10);
+    processor.process(&mut fec).unwrap();
+
+    let file = std::fs::File::open(out_dir.join("SB23.parquet")).unwrap();
+    let metadata = SerializedFileReader::new(file).unwrap().metadata().clone();
+    // 35 SB23 records in groups of 10
+    assert_eq!(metadata.num_row_groups(), 4);
+    assert_eq!(metadata.file_metadata().num_rows(), 35);
+}
+
+#[test]
+fn it_flushes_parquet_row_groups_early_over_the_memory_budget() {
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+
+    let out_dir = std::env::temp_dir().join("feco3_test_memory_budget");
+    let _ = std::fs::remove_dir_all(&out_dir);
+    let fec_path = repo_root().join("test/fecs/1550548.fec");
+    let mut fec = feco3::FecFile::from_path(&fec_path).unwrap();
+    feco3::writers::parquet::ParquetProcessor::with_row_group_size(out_dir.clone(), 1000)
+        .with_memory_budget(2000)
+        .process(&mut fec)
+        .unwrap();
+
+    let file = std::fs::File::open(out_dir.join("SB23.parquet")).unwrap();
+    let metadata = SerializedFileReader::new(file).unwrap().metadata().clone();
+    // Without the budget, all 35 would fit in one row group.
+    assert!(metadata.num_row_groups() > 1);
+    assert_eq!(metadata.file_metadata().num_rows(), 35);
+}
+
+#[test]
+fn it_returns_arrow_batches_early_over_the_memory_budget() {
+    use std::collections::HashMap;
+
+    let count_batches = |processor: &mut feco3::writers::arrow::RecordBatchProcessor| {
+        let fec_path = repo_root().join("test/fecs/1550548.fec");
+        let mut fec = feco3::FecFile::from_path(&fec_path).unwrap();
+        let mut rows: HashMap<String, usize> = HashMap::new();
+        let mut batches = 0;
+        while let Some(batch) = processor.next_batch(&mut fec).unwrap() {
+            *rows.entry(batch.record_code).or_default() += batch.record_batch.num_rows();
+            batches += 1;
+        }
+        (batches, rows)
+    };
+    let (unlimited, expected) =
+        count_batches(&mut feco3::writers::arrow::RecordBatchProcessor::new(1000));
+    let mut processor =
+        feco3::writers::arrow::RecordBatchProcessor::new(1000).with_memory_budget(2000);
+    let (limited, rows) = count_batches(&mut processor);
+    assert_eq!(unlimited, expected.len());
+    assert!(limited > unlimited);
+    assert_eq!(rows, expected);
+}
+
+#[test]
+fn it_only_writes_a_valid_parquet_file_on_finish() {
+    use feco3::writers::base::RecordWriter;
+    use feco3::writers::parquet::ParquetWriter;
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+
+    let fec_path = repo_root().join("test/fecs/1550548.fec");
+    let records = feco3::FecFile::from_path(&fec_path)
+        .unwrap()
+        .records()
+        .map(|r| r.unwrap())
+        .filter(|r| r.record_code == "SB23")
+        .collect::<Vec<_>>();
+    let path = std::env::temp_dir().join("feco3_test_parquet_finish.parquet");
+    let file = std::fs::File::create(&path).unwrap();
+    let mut writer = ParquetWriter::new(file, &records[0].schema, None).unwrap();
+    for record in &records {
+        writer.write_record(record).unwrap();
+    }
+    assert!(SerializedFileReader::new(std::fs::File::open(&path).unwrap()).is_err());
+
+    writer.finish().unwrap();
+    let reader = SerializedFileReader::new(std::fs::File::open(&path).unwrap()).unwrap();
+    assert_eq!(reader.metadata().file_metadata().num_rows(), 35);
+    // Finishing twice is harmless.
+    writer.finish().unwrap();
+}
+
+#[test]
+fn it_writes_aliased_line_codes_to_one_csv() {
+    let out_dir = std::env::temp_dir().join("feco3_test_csv_aliases");
+    let _ = std::fs::remove_dir_all(&out_dir);
+    let src = std::fs::read(repo_root().join("test/fecs/slash_form.fec")).unwrap();
+    let src = String::from_utf8(src).unwrap();
+    // Make the second SA11AI line an SA11AII instead.
+    let second = src.match_indices("\nSA11AI\x1c").nth(1).unwrap().0;
+    let src = format!("{}\nSA11AII{}", &src[..second], &src[second + 7..]);
+    let mut fec = feco3::FecFile::builder()
+        .alias_line_code("SA11AI", "SA11")
+        .alias_line_code("sa11aii", "SA11")
+        .build_from_bytes(src.into_bytes());
+    feco3::writers::csv::CSVProcessor::new(out_dir.clone())
+        .process(&mut fec)
+        .unwrap();
+
+    assert!(!out_dir.join("SA11AI.csv").exists());
+    assert!(!out_dir.join("SA11AII.csv").exists());
+    let mut reader = csv::Reader::from_path(out_dir.join("SA11.csv")).unwrap();
+    assert_eq!(reader.records().count(), 2);
+    assert!(out_dir.join("SB17.csv").exists());
+}
+
+#[test]
+fn it_writes_csv_metadata_sidecar() {
+    let out_dir = std::env::temp_dir().join("feco3_test_csv_metadata");
+    let _ = std::fs::remove_dir_all(&out_dir);
+    let fec_path = repo_root().join("test/fecs/slash_form.fec");
+    let mut fec = feco3::FecFile::from_path(&fec_path).unwrap();
+    let mut processor = feco3::writers::csv::CSVProcessor::with_metadata(out_dir.clone(), true);
+    processor.process(&mut fec).unwrap();
+
+    let contents = std::fs::read_to_string(out_dir.join("_metadata.json")).unwrap();
+    let metadata: serde_json::Value = serde_json::from_str(&contents).unwrap();
+    assert_eq!(metadata["cover"]["filer_committee_id"], "C00772335");
+    assert_eq!(metadata["header"]["fec_version"], "8.3");
+}
+
+#[test]
+fn it_skips_csv_metadata_sidecar_by_default() {
+    let out_dir = std::env::temp_dir().join("feco3_test_csv_no_metadata");
+    let _ = std::fs::remove_dir_all(&out_dir);
+    let fec_path = repo_root().join("test/fecs/slash_form.fec");
+    let mut fec = feco3::FecFile::from_path(&fec_path).unwrap();
+    feco3::writers::csv::CSVProcessor::new(out_dir.clone())
+        .process(&mut fec)
+        .unwrap();
+    assert!(!out_dir.join("_metadata.json").exists());
+}
+
+#[test]
+fn it_writes_latin1_csvs() {
+    use feco3::writers::csv::{CsvEncoding, Unrepresentable};
+
+    let out_dir = std::env::temp_dir().join("feco3_test_csv_latin1");
+    let _ = std::fs::remove_dir_all(&out_dir);
+    let mut fec = feco3::FecFile::builder()
+        .windows_1252_fallback(true)
+        .build_from_path(&repo_root().join("test/fecs/latin1.fec"))
+        .unwrap();
+    feco3::writers::csv::CSVProcessor::new(out_dir.clone())
+        .with_encoding(CsvEncoding::Latin1, Unrepresentable::Replace)
+        .process(&mut fec)
+        .unwrap();
+
+    let bytes = std::fs::read(out_dir.join("SA11AI.csv")).unwrap();
+    let contains = |needle: &[u8]| bytes.windows(needle.len()).any(|w| w == needle);
+    assert!(contains(b"Pe\xf1a,Jos\xe9"));
+    assert!(!contains("Peña".as_bytes()));
+}
+
+#[test]
+fn it_replaces_or_drops_unrepresentable_csv_characters() {
+    use feco3::writers::base::RecordWriter;
+    use feco3::writers::csv::{CSVSingleFileWriter, CsvEncoding, Unrepresentable};
+
+    let src = std::fs::read_to_string(repo_root().join("test/fecs/slash_form.fec")).unwrap();
+    let src = src.replacen("barbariniweil", "Caf\u{e9} \u{20ac}5", 1);
+    let write = |encoding, unrepresentable| {
+        let mut fec = feco3::FecFile::from_bytes(src.clone().into_bytes());
+        let record = fec.next_record().unwrap().unwrap();
+        let mut writer =
+            CSVSingleFileWriter::new(Vec::new()).with_encoding(encoding, unrepresentable);
+        writer.write_record(&record).unwrap();
+        writer.finish().unwrap();
+        writer.into_inner()
+    };
+    let name = |bytes: Vec<u8>| {
+        let start = bytes.windows(3).position(|w| w == b"Caf").unwrap();
+        let end = start + bytes[start..].iter().position(|b| *b == b',').unwrap();
+        bytes[start..end].to_vec()
+    };
+    assert_eq!(
+        name(write(CsvEncoding::Latin1, Unrepresentable::Replace)),
+        b"Caf\xe9 ?5"
+    );
+    assert_eq!(
+        name(write(CsvEncoding::Latin1, Unrepresentable::Drop)),
+        b"Caf\xe9 5"
+    );
+    // Windows-1252 has the euro sign.
+    assert_eq!(
+        name(write(CsvEncoding::Windows1252, Unrepresentable::Drop)),
+        b"Caf\xe9 \x805"
+    );
+    assert_eq!(
+        name(write(CsvEncoding::Utf8, Unrepresentable::Drop)),
+        "Caf\u{e9} \u{20ac}5".as_bytes()
+    );
+}
+
+#[test]
+fn it_batches_records_into_arrow() {
+    use arrow::datatypes::DataType;
+    use std::collections::HashMap;
+
+    let fec_path = repo_root().join("test/fecs/1550548.fec");
+    let mut fec = feco3::FecFile::from_path(&fec_path).unwrap();
+    let mut processor = feco3::writers::arrow::RecordBatchProcessor::new(10);
+    let mut rows: HashMap<String, Vec<usize>> = HashMap::new();
+    while let Some(batch) = processor.next_batch(&mut fec).unwrap() {
+        let schema = batch.record_batch.schema();
+        assert_eq!(schema.field(0).name(), "filer_committee_id_number");
+        assert_eq!(schema.field(0).data_type(), &DataType::Utf8);
+        rows.entry(batch.record_code)
+            .or_default()
+            .push(batch.record_batch.num_rows());
+    }
+    assert_eq!(rows["SB23"], vec![10, 10, 10, 5]);
+    assert!(rows.values().flatten().all(|n| *n <= 10));
+}
+
+#[test]
+fn it_labels_arrow_batches_of_aliased_line_codes_with_the_canonical_code() {
+    let fec_path = repo_root().join("test/fecs/slash_form.fec");
+    let mut fec = feco3::FecFile::from_path(&fec_path).unwrap();
+    fec.alias_line_code("SA11AI", "SA");
+    // Full batches and the leftover batches at the end.
+    for max_batch_size in [1, 100] {
+        fec.rewind().unwrap();
+        let mut processor = feco3::writers::arrow::RecordBatchProcessor::new(max_batch_size);
+        let mut codes = Vec::new();
+        while let Some(batch) = processor.next_batch(&mut fec).unwrap() {
+            codes.push(batch.record_code);
+        }
+        assert!(codes.contains(&"SA".to_string()), "{:?}", codes);
+        assert!(!codes.contains(&"SA11AI".to_string()), "{:?}", codes);
+    }
+}
+
+#[test]
+fn it_writes_repeating_fields_as_lists() {
+    use arrow::array::{Array, ListArray, StringArray};
+    use arrow::datatypes::DataType;
+    use feco3::record::{FieldSchema, RecordSchema, ValueType};
+
+    let field = |name: &str| FieldSchema {
+        name: name.to_string(),
+        typ: ValueType::String,
+        label: None,
+    };
+    let schema = RecordSchema {
+        code: "F1S".to_string(),
+        fields: vec![
+            field("filer_committee_id_number"),
+            field("affiliated_committee_name"),
+            field("affiliated_committee_id_number"),
+        ],
+        repeating: 2,
+    };
+    let fec_path = repo_root().join("test/fecs/f1_affiliates.fec");
+    let mut fec = feco3::FecFile::from_path(&fec_path).unwrap();
+    fec.register_schema("8.3", schema.clone());
+    let mut processor = feco3::writers::arrow::RecordBatchProcessor::new(10);
+    let batch = processor
+        .next_batch(&mut fec)
+        .unwrap()
+        .unwrap()
+        .record_batch;
+    assert_eq!(batch.num_rows(), 2);
+    assert_eq!(batch.schema().field(0).data_type(), &DataType::Utf8);
+    assert!(matches!(
+        batch.schema().field(2).data_type(),
+        DataType::List(_)
+    ));
+    let ids = batch
+        .column(2)
+        .as_any()
+        .downcast_ref::<ListArray>()
+        .unwrap();
+    let first = ids.value(0);
+    let first = first.as_any().downcast_ref::<StringArray>().unwrap();
+    assert_eq!(first.len(), 3);
+    assert_eq!(first.value(2), "C00000003");
+    assert_eq!(ids.value(1).len(), 1);
+
+    let mut fec = feco3::FecFile::from_path(&fec_path).unwrap();
+    fec.register_schema("8.3", schema);
+    let mut processor = NdjsonProcessor::new(Vec::new());
+    processor.process(&mut fec).unwrap();
+    let out = String::from_utf8(processor.into_inner()).unwrap();
+    let last = serde_json::from_str::<serde_json::Value>(out.lines().last().unwrap()).unwrap();
+    assert_eq!(
+        last["affiliated_committee_name"],
+        serde_json::json!(["AFFILIATE FOUR"])
+    );
+}
+
+#[test]
+fn it_drops_extra_fields_from_arrow_batches() {
+    let fec_path = repo_root().join("test/fecs/too_many_fields.fec");
+    let mut fec = feco3::FecFile::from_path(&fec_path).unwrap();
+    let mut processor = feco3::writers::arrow::RecordBatchProcessor::new(10);
+    let batch = processor.next_batch(&mut fec).unwrap().unwrap();
+    assert_eq!(batch.record_code, "SA11D");
+    assert_eq!(batch.record_batch.num_rows(), 3);
+}
+
+#[test]
+fn it_writes_a_single_wide_csv() {
+    use feco3::writers::base::RecordWriter;
+    use feco3::writers::csv::CSVSingleFileWriter;
+
+    let fec_path = repo_root().join("test/fecs/slash_form.fec");
+    let mut fec = feco3::FecFile::from_path(&fec_path).unwrap();
+    let mut writer = CSVSingleFileWriter::new(Vec::new());
+    for record in fec.records() {
+        writer.write_record(&record.unwrap()).unwrap();
+    }
+    writer.finish().unwrap();
+
+    let out = writer.into_inner();
+    let mut reader = csv::Reader::from_reader(out.as_slice());
+    let header = reader.headers().unwrap().clone();
+    assert_eq!(&header[0], "line_code");
+    let rows = reader.records().map(|r| r.unwrap()).collect::<Vec<_>>();
+    assert_eq!(rows.len(), 8);
+    assert!(rows.iter().all(|r| r.len() == header.len()));
+    assert_eq!(&rows[0][0], "SA11AI");
+    assert_eq!(&rows[2][0], "SB17");
+    // SA11AI has a contributor, SB17 has a payee, so each has a blank in
+    // the other's columns.
+    let col = |name: &str| header.iter().position(|h| h == name).unwrap();
+    assert_eq!(&rows[0][col("contributor_last_name")], "barbariniweil");
+    assert_eq!(&rows[2][col("contributor_last_name")], "");
+    assert_eq!(&rows[2][col("payee_organization_name")], "Ace Specialities");
+    assert_eq!(&rows[0][col("payee_organization_name")], "");
+}
+
+fn read_feather(path: &std::path::Path) -> Vec<arrow::record_batch::RecordBatch> {
+    let file = std::fs::File::open(path).unwrap();
+    arrow::ipc::reader::FileReader::try_new(file, None)
+        .unwrap()
+        .map(|b| b.unwrap())
+        .collect()
+}
+
+#[test]
+fn it_writes_columns_of_overridden_field_types() {
+    use arrow::array::{Array, Int64Array};
+    use arrow::datatypes::DataType;
+    use feco3::record::ValueType;
+    use feco3::writers::arrow::RecordBatchProcessor;
+    use feco3::writers::parquet::ParquetProcessor;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    let open = || {
+        let fec_path = repo_root().join("test/fecs/slash_form.fec");
+        let mut fec = feco3::FecFile::from_path(&fec_path).unwrap();
+        fec.set_field_type("SA11AI", "contributor_zip_code", ValueType::Integer);
+        fec
+    };
+    let mut fec = open();
+    let mut processor = RecordBatchProcessor::new(1000);
+    let batch = std::iter::from_fn(|| processor.next_batch(&mut fec).unwrap())
+        .find(|batch| batch.record_code == "SA11AI")
+        .unwrap()
+        .record_batch;
+    let zips = batch
+        .column_by_name("contributor_zip_code")
+        .unwrap()
+        .as_any()
+        .downcast_ref::<Int64Array>()
+        .unwrap();
+    assert_eq!(zips.value(0), 10021);
+
+    let out_dir = std::env::temp_dir().join("feco3_test_overridden_types");
+    let _ = std::fs::remove_dir_all(&out_dir);
+    ParquetProcessor::new(out_dir.join("parquet"), None)
+        .process(&mut open())
+        .unwrap();
+    let file = std::fs::File::open(out_dir.join("parquet/SA11AI.parquet")).unwrap();
+    let reader = ParquetRecordBatchReaderBuilder::try_new(file).unwrap();
+    let field = reader
+        .schema()
+        .field_with_name("contributor_zip_code")
+        .unwrap();
+    assert_eq!(field.data_type(), &DataType::Int64);
+
+    feco3::writers::feather::FeatherProcessor::with_batch_size(out_dir.join("feather"), 10)
+        .process(&mut open())
+        .unwrap();
+    let batches = read_feather(&out_dir.join("feather/SA11AI.feather"));
+    let schema = batches[0].schema();
+    let field = schema.field_with_name("contributor_zip_code").unwrap();
+    assert_eq!(field.data_type(), &DataType::Int64);
+}
+
+#[test]
+fn it_writes_feather_files() {
+    use arrow::datatypes::DataType;
+
+    let out_dir = std::env::temp_dir().join("feco3_test_feather");
+    let _ = std::fs::remove_dir_all(&out_dir);
+    let fec_path = repo_root().join("test/fecs/1550548.fec");
+    let mut fec = feco3::FecFile::from_path(&fec_path).unwrap();
+    feco3::writers::feather::FeatherProcessor::with_batch_size(out_dir.clone(), 10)
+        .process(&mut fec)
+        .unwrap();
+
+    let batches = read_feather(&out_dir.join("SB23.feather"));
+    assert_eq!(batches.len(), 4);
+    assert_eq!(batches.iter().map(|b| b.num_rows()).sum::<usize>(), 35);
+    let schema = batches[0].schema();
+    assert_eq!(schema.field(0).name(), "filer_committee_id_number");
+    assert_eq!(schema.field(0).data_type(), &DataType::Utf8);
+}
+
+#[test]
+fn it_writes_typed_feather_columns() {
+    use arrow::datatypes::DataType;
+    use feco3::record::{FieldSchema, Record, RecordSchema, Value, ValueType};
+    use feco3::writers::base::RecordWriter;
+    use feco3::writers::feather::FeatherWriter;
+
+    let field = |name: &str, typ| FieldSchema {
+        name: name.to_string(),
+        typ,
+        label: None,
+    };
+    let schema = RecordSchema {
+        code: "ZZ".to_string(),
+        fields: vec![
+            field("name", ValueType::String),
+            field("date", ValueType::Date),
+            field("count", ValueType::Integer),
+            field("amount", ValueType::Float),
+            field("flag", ValueType::Boolean),
+        ],
+        repeating: 0,
+    };
+    let record = Record {
+        record_code: "ZZ".to_string(),
+        schema: std::sync::Arc::new(schema.clone()),
+        values: vec![
+            Value::String(Some("a".to_string())),
+            Value::Date(chrono::NaiveDate::from_ymd_opt(2020, 1, 15)),
+            Value::Integer(Some(3)),
+            Value::Float(None),
+            Value::Boolean(Some(true)),
+        ],
+        extra_fields: 0,
+        missing_fields: 0,
+        received_fields: 5,
+        row_index: 0,
+        byte_offset: None,
+        raw: None,
+        is_duplicate: false,
+        source_id: None,
+    };
+    let path = std::env::temp_dir().join("feco3_test_typed.feather");
+    {
+        let file = std::fs::File::create(&path).unwrap();
+        let mut writer = FeatherWriter::new(file, &schema, 100).unwrap();
+        writer.write_record(&record).unwrap();
+        writer.write_record(&record).unwrap();
+        // Finalized on drop.
+    }
+
+    let batches = read_feather(&path);
+    assert_eq!(batches[0].num_rows(), 2);
+    let types = batches[0]
+        .schema()
+        .fields()
+        .iter()
+        .map(|f| f.data_type().clone())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        types,
+        vec![
+            DataType::Utf8,
+            DataType::Date32,
+            DataType::Int64,
+            DataType::Float64,
+            DataType::Boolean
+        ]
+    );
+}
+
+#[cfg(feature = "parallel")]
+#[test]
+fn it_writes_the_same_parquet_in_parallel() {
+    use feco3::writers::parquet::ParquetProcessor;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    let read_dir = |dir: &PathBuf| {
+        let mut files = std::fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect::<Vec<_>>();
+        files.sort();
+        files
+            .into_iter()
+            .map(|path| {
+                let file = std::fs::File::open(&path).unwrap();
+                let batches = ParquetRecordBatchReaderBuilder::try_new(file)
+                    .unwrap()
+                    .build()
+                    .unwrap()
+                    .map(|b| b.unwrap())
+                    .collect::<Vec<_>>();
+                (path.file_name().unwrap().to_owned(), batches)
+            })
+            .collect::<Vec<_>>()
+    };
+    let fec_path = repo_root().join("test/fecs/1550548.fec");
+    let sequential_dir = std::env::temp_dir().join("feco3_test_parquet_sequential");
+    let parallel_dir = std::env::temp_dir().join("feco3_test_parquet_parallel");
+    let _ = std::fs::remove_dir_all(&sequential_dir);
+    let _ = std::fs::remove_dir_all(&parallel_dir);
+
+    let mut fec = feco3::FecFile::from_path(&fec_path).unwrap();
+    ParquetProcessor::with_row_group_size(sequential_dir.clone(), 10)
+        .process_sequential(&mut fec)
+        .unwrap();
+    let mut fec = feco3::FecFile::from_path(&fec_path).unwrap();
+    ParquetProcessor::with_row_group_size(parallel_dir.clone(), 10)
+        .process_parallel(&mut fec)
+        .unwrap();
+
+    let sequential = read_dir(&sequential_dir);
+    assert!(sequential.len() > 1);
+    assert_eq!(read_dir(&parallel_dir), sequential);
+}
+
+#[test]
+fn it_names_csv_files_with_a_closure() {
+    let out_dir = std::env::temp_dir().join("feco3_test_csv_naming");
+    let _ = std::fs::remove_dir_all(&out_dir);
+    let fec_path = repo_root().join("test/fecs/slash_form.fec");
+    let mut fec = feco3::FecFile::from_path(&fec_path).unwrap();
+    feco3::writers::csv::CSVProcessor::new(out_dir.clone())
+        .with_naming(Box::new(|schema| {
+            let name = schema.code.replace('/', "-").to_lowercase();
+            PathBuf::from(format!("{}.csv", name))
+        }))
+        .process(&mut fec)
+        .unwrap();
+
+    let mut reader = csv::Reader::from_path(out_dir.join("sa11ai.csv")).unwrap();
+    let header = reader.headers().unwrap().clone();
+    assert_eq!(&header[0], "filer_committee_id_number");
+    assert!(reader.records().count() > 0);
+    assert!(out_dir.join("sb17.csv").exists());
+    assert!(!out_dir.join("SA11AI.csv").exists());
+}
+
+#[test]
+fn it_groups_csv_files_with_the_same_name() {
+    let out_dir = std::env::temp_dir().join("feco3_test_csv_grouping");
+    let _ = std::fs::remove_dir_all(&out_dir);
+    let fec_path = repo_root().join("test/fecs/slash_form.fec");
+    let mut fec = feco3::FecFile::from_path(&fec_path).unwrap();
+    feco3::writers::csv::CSVProcessor::new(out_dir.clone())
+        .with_naming(Box::new(|_| PathBuf::from("all.csv")))
+        .process(&mut fec)
+        .unwrap();
+
+    let mut reader = csv::Reader::from_path(out_dir.join("all.csv")).unwrap();
+    let header = reader.headers().unwrap().clone();
+    assert_eq!(&header[0], "line_code");
+    let rows = reader.records().map(|r| r.unwrap()).collect::<Vec<_>>();
+    assert_eq!(rows.len(), 8);
+    assert!(rows.iter().all(|r| r.len() == header.len()));
+    // The rows streamed before a second line code arrived are kept.
+    let first = rows
+        .iter()
+        .filter(|r| &r[0] == "SA11AI")
+        .collect::<Vec<_>>();
+    assert_eq!(first.len(), 2);
+    assert_eq!(&first[0][1], "C00772335");
+    assert_eq!(&first[1][2], "SA11AI.4170");
+}
+
+#[test]
+fn it_streams_csv_files_with_one_line_code_like_the_default() {
+    let out_dir = std::env::temp_dir().join("feco3_test_csv_naming_streams");
+    let _ = std::fs::remove_dir_all(&out_dir);
+    let fec_path = repo_root().join("test/fecs/slash_form.fec");
+    let mut fec = feco3::FecFile::from_path(&fec_path).unwrap();
+    feco3::writers::csv::CSVProcessor::new(out_dir.join("default"))
+        .process(&mut fec)
+        .unwrap();
+    let mut fec = feco3::FecFile::from_path(&fec_path).unwrap();
+    feco3::writers::csv::CSVProcessor::new(out_dir.join("named"))
+        .with_naming(Box::new(|schema| {
+            PathBuf::from(format!("{}.csv", schema.code.replace('/', "-")))
+        }))
+        .process(&mut fec)
+        .unwrap();
+
+    for name in ["SA11AI.csv", "SB17.csv", "SC-10.csv"] {
+        let default = std::fs::read(out_dir.join("default").join(name)).unwrap();
+        let named = std::fs::read(out_dir.join("named").join(name)).unwrap();
+        assert_eq!(default, named, "{}", name);
+    }
+}
+
+#[test]
+fn it_writes_csv_headers_with_labels() {
+    use feco3::record::{FieldSchema, RecordSchema, ValueType};
+
+    let out_dir = std::env::temp_dir().join("feco3_test_csv_labels");
+    let _ = std::fs::remove_dir_all(&out_dir);
+    let fec_path = repo_root().join("test/fecs/corrupt_row.fec");
+    let mut fec = feco3::FecFile::from_path(&fec_path).unwrap();
+    let schema = RecordSchema {
+        code: "ZZ99".to_string(),
+        fields: vec![FieldSchema {
+            name: "note".to_string(),
+            typ: ValueType::String,
+            label: Some("Reviewer's Note".to_string()),
+        }],
+        repeating: 0,
+    };
+    fec.register_schema("8.3", schema);
+    feco3::writers::csv::CSVProcessor::new(out_dir.clone())
+        .with_labels(true)
+        .process(&mut fec)
+        .unwrap();
+
+    let header = |name: &str| {
+        let mut reader = csv::Reader::from_path(out_dir.join(name)).unwrap();
+        reader.headers().unwrap().clone()
+    };
+    let sa = header("SA11AI.csv");
+    assert_eq!(&sa[0], "Filer Committee Id Number");
+    assert!(sa.iter().any(|h| h == "Contributor Last Name"));
+    assert_eq!(&header("ZZ99.csv")[0], "Reviewer's Note");
+
+    // Names are still the default.
+    let _ = std::fs::remove_dir_all(&out_dir);
+    let fec_path = repo_root().join("test/fecs/slash_form.fec");
+    let mut fec = feco3::FecFile::from_path(&fec_path).unwrap();
+    feco3::writers::csv::CSVProcessor::new(out_dir.clone())
+        .process(&mut fec)
+        .unwrap();
+    assert_eq!(&header("SA11AI.csv")[0], "filer_committee_id_number");
+}
+
+#[test]
+fn it_groups_line_codes_into_one_parquet_file() {
+    use arrow::array::{Array, StringArray};
+    use feco3::writers::parquet::ParquetProcessor;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    let out_dir = std::env::temp_dir().join("feco3_test_parquet_groups");
+    let _ = std::fs::remove_dir_all(&out_dir);
+    let fec_path = repo_root().join("test/fecs/multi_filing.fec");
+    let mut fec = feco3::FecFile::from_path(&fec_path).unwrap();
+    ParquetProcessor::new(out_dir.clone(), None)
+        .with_group("SA", &["SA"])
+        .process(&mut fec)
+        .unwrap();
+
+    assert!(!out_dir.join("SA11AI.parquet").exists());
+    assert!(!out_dir.join("SA17A.parquet").exists());
+    assert!(out_dir.join("SB17.parquet").exists());
+    let file = std::fs::File::open(out_dir.join("SA.parquet")).unwrap();
+    let batches = ParquetRecordBatchReaderBuilder::try_new(file)
+        .unwrap()
+        .build()
+        .unwrap()
+        .map(|b| b.unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(batches.len(), 1);
+    let batch = &batches[0];
+    assert_eq!(batch.schema().field(0).name(), "line_code");
+    let line_codes = batch
+        .column(0)
+        .as_any()
+        .downcast_ref::<StringArray>()
+        .unwrap();
+    let line_codes = line_codes.iter().flatten().collect::<Vec<_>>();
+    assert_eq!(line_codes, vec!["SA11AI", "SA11AI", "SA17A", "SA17A"]);
+    let last_names = batch
+        .column_by_name("contributor_last_name")
+        .unwrap()
+        .as_any()
+        .downcast_ref::<StringArray>()
+        .unwrap();
+    assert_eq!(last_names.value(0), "barbariniweil");
+    assert_eq!(last_names.value(2), "AANENSON");
+}
+
+#[test]
+fn it_writes_decimals_exactly() {
+    use arrow::array::{Array, Decimal128Array};
+    use feco3::record::ValueType;
+    use feco3::writers::arrow::RecordBatchProcessor;
+
+    let open = || {
+        let fec_path = repo_root().join("test/fecs/slash_form.fec");
+        let mut fec = feco3::FecFile::from_path(&fec_path).unwrap();
+        let mut schema = (*feco3::schemas::lookup_schema("8.3", "SA11AI").unwrap()).clone();
+        for field in schema.fields.iter_mut() {
+            if field.name == "contribution_amount" {
+                field.typ = ValueType::Decimal;
+            }
+        }
+        fec.register_schema("8.3", schema);
+        fec
+    };
+    let mut fec = open();
+    let mut processor = RecordBatchProcessor::new(1000);
+    let batch = std::iter::from_fn(|| processor.next_batch(&mut fec).unwrap())
+        .find(|batch| batch.record_code == "SA11AI")
+        .unwrap();
+    let amounts = batch
+        .record_batch
+        .column_by_name("contribution_amount")
+        .unwrap()
+        .as_any()
+        .downcast_ref::<Decimal128Array>()
+        .unwrap();
+    assert_eq!(amounts.value_as_string(0), "1000.000000");
+
+    let mut processor = NdjsonProcessor::new(Vec::new());
+    processor.process(&mut open()).unwrap();
+    let out = String::from_utf8(processor.into_inner()).unwrap();
+    let first: serde_json::Value = serde_json::from_str(out.lines().next().unwrap()).unwrap();
+    assert_eq!(first["contribution_amount"], "1000.00");
+}
+
+#[test]
+fn it_drives_a_file_into_a_boxed_record_writer() {
+    use feco3::record::Record;
+    use feco3::writers::base::RecordWriter;
+    use std::sync::{Arc, Mutex};
+
+    /// Counts the records it is given, and whether it was finished.
+    struct Counter(Arc<Mutex<(usize, bool)>>);
+
+    impl RecordWriter for Counter {
+        fn write_record(&mut self, _record: &Record) -> std::io::Result<()> {
+            self.0.lock().unwrap().0 += 1;
+            Ok(())
+        }
+
+        fn finish(&mut self) -> Result<(), feco3::Error> {
+            self.0.lock().unwrap().1 = true;
+            Ok(())
+        }
+    }
+
+    let state = Arc::new(Mutex::new((0, false)));
+    let mut writer: Box<dyn RecordWriter> = Box::new(Counter(state.clone()));
+    let fec_path = repo_root().join("test/fecs/slash_form.fec");
+    let mut fec = feco3::FecFile::from_path(&fec_path).unwrap();
+    writer.write_all(&mut fec).unwrap();
+    assert_eq!(*state.lock().unwrap(), (8, true));
+}
+
+#[test]
+fn it_writes_typed_date_and_amount_parquet_columns() {
+    use feco3::writers::parquet::ParquetProcessor;
+    use parquet::basic::{LogicalType, Type as PhysicalType};
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+
+    let columns = |typed: bool| {
+        let out_dir = std::env::temp_dir().join(format!("feco3_test_parquet_typed_{}", typed));
+        let _ = std::fs::remove_dir_all(&out_dir);
+        let fec_path = repo_root().join("test/fecs/1550548.fec");
+        let mut fec = feco3::FecFile::from_path(&fec_path).unwrap();
+        ParquetProcessor::new(out_dir.clone(), None)
+            .with_typed_columns(typed)
+            .process(&mut fec)
+            .unwrap();
+        let file = std::fs::File::open(out_dir.join("SA11AI.parquet")).unwrap();
+        let metadata = SerializedFileReader::new(file).unwrap().metadata().clone();
+        let schema = metadata.file_metadata().schema_descr_ptr();
+        let column = |name: &str| {
+            let column = schema.columns().iter().find(|c| c.name() == name).unwrap();
+            (column.physical_type(), column.logical_type())
+        };
+        (column("contribution_date"), column("contribution_amount"))
+    };
+
+    let (date, amount) = columns(true);
+    assert_eq!(date.1, Some(LogicalType::Date));
+    assert_eq!(amount.0, PhysicalType::FIXED_LEN_BYTE_ARRAY);
+    assert_eq!(
+        amount.1,
+        Some(LogicalType::Decimal {
+            scale: feco3::writers::arrow::DECIMAL_SCALE as i32,
+            precision: 38
+        })
+    );
+    let (date, amount) = columns(false);
+    assert_eq!(date.1, Some(LogicalType::String));
+    assert_eq!(amount.1, Some(LogicalType::String));
+}
+
+#[test]
+fn it_parses_formatted_amounts_in_typed_parquet_columns_only_when_lenient() {
+    use arrow::array::{Array, Decimal128Array};
+    use feco3::writers::parquet::ParquetProcessor;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    let src = std::fs::read_to_string(repo_root().join("test/fecs/slash_form.fec")).unwrap();
+    let src = src.replacen("\x1c1000.00\x1c", "\x1c$1,000.00\x1c", 1);
+    let first_amount = |lenient: bool| {
+        let out_dir = std::env::temp_dir().join(format!("feco3_test_parquet_lenient_{}", lenient));
+        let _ = std::fs::remove_dir_all(&out_dir);
+        let mut fec = feco3::FecFile::builder()
+            .lenient_numbers(lenient)
+            .build_from_bytes(src.clone().into_bytes());
+        ParquetProcessor::new(out_dir.clone(), None)
+            .process(&mut fec)
+            .unwrap();
+        let file = std::fs::File::open(out_dir.join("SA11AI.parquet")).unwrap();
+        let batch = ParquetRecordBatchReaderBuilder::try_new(file)
+            .unwrap()
+            .build()
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap();
+        let amounts = batch
+            .column_by_name("contribution_amount")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<Decimal128Array>()
+            .unwrap()
+            .clone();
+        let failures = fec.stats().by_line_code["SA11AI"].coercion_failures;
+        (amounts, failures)
+    };
+
+    let (amounts, failures) = first_amount(true);
+    assert_eq!(amounts.value_as_string(0), "1000.000000");
+    assert_eq!(failures, 0);
+    let (amounts, failures) = first_amount(false);
+    assert!(amounts.is_null(0));
+    assert_eq!(failures, 1);
+}
+
+#[test]
+fn it_tees_records_to_several_writers() {
+    use feco3::record::Record;
+    use feco3::writers::base::{RecordWriter, TeeWriter};
+    use feco3::writers::csv::CSVSingleFileWriter;
+    use feco3::writers::ndjson::NdjsonWriter;
+
+    let mut ndjson = NdjsonWriter::new(Vec::new());
+    let mut csv = CSVSingleFileWriter::new(Vec::new());
+    let fec_path = repo_root().join("test/fecs/slash_form.fec");
+    let mut fec = feco3::FecFile::from_path(&fec_path).unwrap();
+    TeeWriter::new(vec![Box::new(&mut ndjson), Box::new(&mut csv)])
+        .write_all(&mut fec)
+        .unwrap();
+    let ndjson = String::from_utf8(ndjson.into_inner()).unwrap();
+    assert_eq!(ndjson.lines().count(), 8);
+    let csv = String::from_utf8(csv.into_inner()).unwrap();
+    assert_eq!(
+        csv::Reader::from_reader(csv.as_bytes()).records().count(),
+        8
+    );
+
+    struct Failing;
+    impl RecordWriter for Failing {
+        fn write_record(&mut self, _record: &Record) -> std::io::Result<()> {
+            Err(std::io::Error::other("disk full"))
+        }
+    }
+    let mut ndjson = NdjsonWriter::new(Vec::new());
+    let mut fec = feco3::FecFile::from_path(&fec_path).unwrap();
+    let record = fec.next_record().unwrap().unwrap();
+    let mut tee = TeeWriter::new(vec![Box::new(Failing), Box::new(&mut ndjson)])
+        .with_writer(Box::new(Failing));
+    let err = tee.write_record(&record).unwrap_err();
+    assert_eq!(err.to_string(), "2 writers failed: disk full; disk full");
+    drop(tee);
+    assert_eq!(
+        ndjson.into_inner().iter().filter(|&&b| b == b'\n').count(),
+        1
+    );
+}
@@ -0,0 +1,31 @@
+#![cfg(feature = "zip")]
+
+use std::path::PathBuf;
+
+use feco3::FecFile;
+
+fn repo_root() -> PathBuf {
+    PathBuf::from("../..")
+}
+
+#[test]
+fn it_lists_fec_entries() {
+    let path = repo_root().join("test/fecs/filings.zip");
+    let entries = feco3::zip::list_fec_entries(&path).unwrap();
+    assert_eq!(entries, vec!["slash_form.fec", "nested/v5.FEC"]);
+}
+
+#[test]
+fn it_reads_an_entry() {
+    let path = repo_root().join("test/fecs/filings.zip");
+    let mut fec = FecFile::from_zip(&path, "slash_form.fec").unwrap();
+    assert_eq!(fec.get_header().unwrap().fec_version, "8.3");
+    assert_eq!(fec.records().count(), 8);
+}
+
+#[test]
+fn it_errors_on_a_missing_entry() {
+    let path = repo_root().join("test/fecs/filings.zip");
+    let result = FecFile::from_zip(&path, "missing.fec");
+    assert!(matches!(result, Err(feco3::Error::IoError(_))));
+}
@@ -1,8 +1,9 @@
 use arrow::pyarrow::PyArrowType;
 use arrow::record_batch::RecordBatch;
 use pyo3::{
-    exceptions::{PyIOError, PyValueError},
+    exceptions::{PyEOFError, PyIOError, PyKeyError, PyValueError},
     prelude::*,
+    types::{PyDate, PyDict},
 };
 use std::path::PathBuf;
 
@@ -51,6 +52,110 @@ impl Cover {
     fn filer_committee_id(&self) -> PyResult<String> {
         Ok(self.0.filer_committee_id.clone())
     }
+
+    #[getter]
+    fn report_code(&self) -> PyResult<Option<String>> {
+        Ok(self.0.report_code.clone())
+    }
+
+    #[getter]
+    fn coverage_from_date<'py>(&self, py: Python<'py>) -> PyResult<Option<&'py PyDate>> {
+        to_py_date(py, self.0.coverage_from_date)
+    }
+
+    #[getter]
+    fn coverage_through_date<'py>(&self, py: Python<'py>) -> PyResult<Option<&'py PyDate>> {
+        to_py_date(py, self.0.coverage_through_date)
+    }
+}
+
+fn to_py_date(py: Python<'_>, date: Option<chrono::NaiveDate>) -> PyResult<Option<&PyDate>> {
+    use chrono::Datelike;
+    match date {
+        None => Ok(None),
+        Some(d) => Ok(Some(PyDate::new(
+            py,
+            d.year(),
+            d.month() as u8,
+            d.day() as u8,
+        )?)),
+    }
+}
+
+/// Python's `decimal.Decimal`, made from the exact string representation.
+fn to_py_decimal(py: Python<'_>, d: Option<&str>) -> PyResult<PyObject> {
+    match d {
+        None => Ok(py.None()),
+        Some(d) => Ok(py
+            .import("decimal")?
+            .getattr("Decimal")?
+            .call1((d,))?
+            .to_object(py)),
+    }
+}
+
+#[pyclass]
+struct Record(feco3::record::Record);
+
+#[pymethods]
+impl Record {
+    #[getter]
+    fn line_code(&self) -> PyResult<String> {
+        Ok(self.0.record_code.clone())
+    }
+
+    #[getter]
+    fn extra_fields(&self) -> PyResult<usize> {
+        Ok(self.0.extra_fields)
+    }
+
+    #[getter]
+    fn row_index(&self) -> PyResult<usize> {
+        Ok(self.0.row_index)
+    }
+
+    #[getter]
+    fn byte_offset(&self) -> PyResult<Option<u64>> {
+        Ok(self.0.byte_offset)
+    }
+
+    fn __getitem__(&self, py: Python<'_>, field_name: &str) -> PyResult<PyObject> {
+        match self.0.get_value(field_name) {
+            Some(value) => to_py_value(py, value),
+            None => Err(PyErr::new::<PyKeyError, _>(field_name.to_string())),
+        }
+    }
+
+    fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<&'py PyDict> {
+        let dict = PyDict::new(py);
+        let fields = &self.0.schema.fields;
+        for (i, value) in self.0.values.iter().enumerate() {
+            let key = match fields.get(i) {
+                Some(field) => field.name.clone(),
+                None => format!("extra_{}", i - fields.len() + 1),
+            };
+            dict.set_item(key, to_py_value(py, value)?)?;
+        }
+        Ok(dict)
+    }
+}
+
+fn to_py_value(py: Python<'_>, value: &feco3::record::Value) -> PyResult<PyObject> {
+    use feco3::record::Value;
+    Ok(match value {
+        Value::String(s) => s.to_object(py),
+        Value::Integer(i) => i.to_object(py),
+        Value::Float(f) => f.to_object(py),
+        Value::Decimal(d) => to_py_decimal(py, d.map(|d| d.to_string()).as_deref())?,
+        Value::Boolean(b) => b.to_object(py),
+        Value::Date(d) => to_py_date(py, *d)?.to_object(py),
+        Value::InvalidDate(s) => s.to_object(py),
+        Value::List(items) => items
+            .iter()
+            .map(|item| to_py_value(py, item))
+            .collect::<PyResult<Vec<_>>>()?
+            .to_object(py),
+    })
 }
 
 #[pyclass]
@@ -89,6 +194,15 @@ impl FecFile {
             Err(e) => Err(to_py_err(e)),
         }
     }
+
+    // Gets the next record, or None if there are no more records.
+    fn next_record(&mut self) -> PyResult<Option<Record>> {
+        match self.0.next_record() {
+            Some(Ok(record)) => Ok(Some(Record(record))),
+            Some(Err(e)) => Err(to_py_err(e)),
+            None => Ok(None),
+        }
+    }
 }
 
 #[pyclass]
@@ -97,9 +211,12 @@ struct ParquetProcessor(feco3::writers::parquet::ParquetProcessor);
 #[pymethods]
 impl ParquetProcessor {
     #[new]
-    fn new(out_dir: PathBuf) -> Self {
-        let writer_props = None;
-        let processor = feco3::writers::parquet::ParquetProcessor::new(out_dir, writer_props);
+    #[pyo3(signature = (out_dir, row_group_size=None))]
+    fn new(out_dir: PathBuf, row_group_size: Option<usize>) -> Self {
+        let processor = match row_group_size {
+            Some(n) => feco3::writers::parquet::ParquetProcessor::with_row_group_size(out_dir, n),
+            None => feco3::writers::parquet::ParquetProcessor::new(out_dir, None),
+        };
         Self(processor)
     }
 
@@ -160,6 +277,9 @@ fn _feco3(_py: Python, m: &PyModule) -> PyResult<()> {
     // before calling any Rust functions that may log.
     // See https://pyo3.rs/v0.18.3/ecosystem/logging
     pyo3_log::init();
+    m.add_class::<Header>()?;
+    m.add_class::<Cover>()?;
+    m.add_class::<Record>()?;
     m.add_class::<FecFile>()?;
     m.add_class::<ParquetProcessor>()?;
     m.add_class::<CsvProcessor>()?;
@@ -169,14 +289,27 @@ fn _feco3(_py: Python, m: &PyModule) -> PyResult<()> {
 
 fn to_py_err(e: feco3::Error) -> PyErr {
     match e {
-        feco3::Error::HeaderParseError(e) => PyErr::new::<PyValueError, _>(e.to_string()),
+        feco3::Error::HeaderParseError(e) => header_to_py_err(e),
         feco3::Error::RecordParseError(e) => PyErr::new::<PyValueError, _>(e.to_string()),
         feco3::Error::IoError(e) => PyErr::new::<PyIOError, _>(e.to_string()),
         feco3::Error::SchemaError(e, f) => PyErr::new::<PyValueError, _>(format!(
             "Failed to find schema for fec version {} and line code {}",
             e, f
         )),
+        feco3::Error::AliasConflict(..) => PyErr::new::<PyValueError, _>(e.to_string()),
         feco3::Error::CoverParseError(e) => PyErr::new::<PyValueError, _>(e.to_string()),
         feco3::Error::HttpError(e) => PyErr::new::<PyIOError, _>(e.to_string()),
+        feco3::Error::TruncatedFile => PyErr::new::<PyIOError, _>(e.to_string()),
+    }
+}
+
+fn header_to_py_err(e: feco3::HeaderParseError) -> PyErr {
+    use feco3::HeaderParseError::*;
+    match e {
+        TooShort => PyErr::new::<PyEOFError, _>(e.to_string()),
+        Io(_) => PyErr::new::<PyIOError, _>(e.to_string()),
+        UnknownVersion(_) | MissingField(_) | InvalidDelimiter | Malformed(_) => {
+            PyErr::new::<PyValueError, _>(e.to_string())
+        }
     }
 }
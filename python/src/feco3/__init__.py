@@ -2,14 +2,16 @@
 
 from __future__ import annotations
 
+import datetime
 import os
 from functools import cached_property
 from pathlib import Path
-from typing import TYPE_CHECKING, NamedTuple
+from typing import TYPE_CHECKING, Any, Iterator, NamedTuple
 
 from . import _feco3, _version
 
 if TYPE_CHECKING:
+    import polars as pl
     import pyarrow as pa
 
 __version__ = _version.get_version()
@@ -44,10 +46,62 @@ class Cover(NamedTuple):
         form_type: The form type of the filing, eg. "F3"
         filer_committee_id: The FEC-assigned ID of the committee that filed the report,
             eg "C00618371"
+        report_code: Which report this is, eg "Q1", "M10", or "12G".
+            Not all forms have this, eg F99.
+        coverage_from_date: The first day of the period this report covers.
+        coverage_through_date: The last day of the period this report covers.
     """
 
     form_type: str
     filer_committee_id: str
+    report_code: str | None
+    coverage_from_date: datetime.date | None
+    coverage_through_date: datetime.date | None
+
+
+class Record:
+    """A single itemization line of an [FecFile][feco3.FecFile].
+
+    Look up values by field name, eg ``record["contribution_amount"]``.
+    Values are converted to native python types
+    (str, int, float, decimal.Decimal, bool, datetime.date,
+    or None if missing). The fields of a repeating group are lists
+    of those, with one item per repetition.
+    """
+
+    def __init__(self, wrapped: _feco3.Record) -> None:
+        self._wrapped = wrapped
+
+    @property
+    def line_code(self) -> str:
+        """The code at the start of the line, eg "SA11AI"."""
+        return self._wrapped.line_code
+
+    @property
+    def extra_fields(self) -> int:
+        """How many values the line had past the end of its schema."""
+        return self._wrapped.extra_fields
+
+    @property
+    def row_index(self) -> int:
+        """The index of the line among the itemizations, starting at 0."""
+        return self._wrapped.row_index
+
+    @property
+    def byte_offset(self) -> int | None:
+        """Where the line starts in the (decompressed) file, in bytes."""
+        return self._wrapped.byte_offset
+
+    def __getitem__(self, field_name: str) -> Any:
+        """Get the value of a field by name. Raises KeyError if not present."""
+        return self._wrapped[field_name]
+
+    def to_dict(self) -> dict[str, Any]:
+        """All the values of this record, keyed by field name."""
+        return self._wrapped.to_dict()
+
+    def __repr__(self) -> str:
+        return f"{self.__class__.__name__}(line_code={self.line_code!r})"
 
 
 class FecFile:
@@ -99,19 +153,55 @@ class FecFile:
         return Cover(
             form_type=c.form_type,
             filer_committee_id=c.filer_committee_id,
+            report_code=c.report_code,
+            coverage_from_date=c.coverage_from_date,
+            coverage_through_date=c.coverage_through_date,
         )
 
-    def to_parquets(self, out_dir: str | os.PathLike) -> None:
+    def records(self) -> Iterator[Record]:
+        """Lazily iterate over the itemization [Record][feco3.Record]s.
+
+        The header and cover are parsed first if needed, and are still
+        available afterwards. Everything happens in a single pass over the
+        file, so reading the [header][feco3.FecFile.header] first and then
+        iterating the records doesn't re-read or re-open anything.
+
+        Records are consumed as they are yielded, so calling this again
+        picks up where the last iteration left off.
+        """
+        while True:
+            record = self._wrapped.next_record()
+            if record is None:
+                return
+            yield Record(record)
+
+    def to_parquets(
+        self, out_dir: str | os.PathLike, row_group_size: int | None = None
+    ) -> None:
         """Write all itemizations in this FEC file to parquet files.
 
+        Only the records that haven't already been read with
+        [records][feco3.FecFile.records] are written.
+
         There will be one parquet file for each record type, eg. ``sa11.parquet``.
+
+        Args:
+            out_dir: The directory to write the parquet files to.
+            row_group_size: The max number of rows per parquet row group.
+                Records are buffered in memory until this many of a record type
+                have accumulated, so larger values compress better but use
+                more memory. Defaults to 1024 * 1024, which is what rust
+                parquet uses.
         """
-        parser = _feco3.ParquetProcessor(out_dir)
+        parser = _feco3.ParquetProcessor(out_dir, row_group_size)
         parser.process(self._wrapped)
 
     def to_csvs(self, out_dir: str | os.PathLike) -> None:
         """Write all itemizations in this FEC file to CSV files.
 
+        Only the records that haven't already been read with
+        [records][feco3.FecFile.records] are written.
+
         There will be one CSV file for each record type, eg. ``sa11.csv``.
         """
         parser = _feco3.CsvProcessor(out_dir)
@@ -167,3 +257,43 @@ class PyarrowBatcher:
             raise StopIteration
         code, batch = pair
         return ItemizationBatch(code, batch)
+
+
+class DataFrameProcessor:
+    """Reads the itemizations of an [FecFile][feco3.FecFile] into one
+    [polars.DataFrame][polars.DataFrame] per itemization type, in memory.
+
+    This needs polars and pyarrow installed. The batches of a
+    [PyarrowBatcher][feco3.PyarrowBatcher] are handed to polars without
+    writing anything to disk, but every itemization is held in memory,
+    so for huge files prefer [to_parquets][feco3.FecFile.to_parquets].
+    """
+
+    def __init__(self, max_batch_size: int | None = None) -> None:
+        """Create a new DataFrameProcessor.
+
+        Args:
+            max_batch_size: The max rows per batch read from the file,
+                see [PyarrowBatcher][feco3.PyarrowBatcher].
+        """
+        self._max_batch_size = max_batch_size
+
+    def process(self, fec_file: FecFile) -> dict[str, pl.DataFrame]:
+        """Read the itemizations of `fec_file` into DataFrames.
+
+        Only the records that haven't already been read are included.
+
+        Returns:
+            A dict from the itemization type, eg "SA11AI", to a DataFrame
+            of its itemizations, with one column per field.
+        """
+        import polars as pl
+        import pyarrow as pa
+
+        batches: dict[str, list[pa.RecordBatch]] = {}
+        for batch in PyarrowBatcher(fec_file, self._max_batch_size):
+            batches.setdefault(batch.code, []).append(batch.records)
+        return {
+            code: pl.from_arrow(pa.Table.from_batches(code_batches))
+            for code, code_batches in batches.items()
+        }
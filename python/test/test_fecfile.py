@@ -1,3 +1,5 @@
+import datetime
+
 import pytest
 import feco3
 
@@ -53,3 +55,40 @@ def test_repr():
 def test_header(src, header):
     fec = feco3.FecFile(common.get_case_path(src))
     assert fec.header == header
+
+
+def test_cover():
+    fec = feco3.FecFile(common.get_case_path("1550548.fec"))
+    assert fec.cover == feco3.Cover(
+        form_type="F3XA",
+        filer_committee_id="C00413955",
+        report_code="MY",
+        coverage_from_date=datetime.date(2021, 1, 1),
+        coverage_through_date=datetime.date(2021, 6, 30),
+    )
+
+
+def test_records():
+    fec = feco3.FecFile(common.get_case_path("slash_form.fec"))
+    records = list(fec.records())
+    assert len(records) == 8
+    first = records[0]
+    assert first.line_code == "SA11AI"
+    assert first["contribution_amount"] == "1000.00"
+    d = first.to_dict()
+    assert d["transaction_id"] == "SA11AI.4265"
+    with pytest.raises(KeyError):
+        first["not_a_field"]
+
+
+def test_header_then_records():
+    fec = feco3.FecFile(common.get_case_path("slash_form.fec"))
+    assert fec.header.fec_version == "8.3"
+    records = fec.records()
+    first = next(records)
+    assert first.line_code == "SA11AI"
+    assert first.row_index == 0
+    # The header and cover are still available mid-iteration.
+    assert fec.header.fec_version == "8.3"
+    assert fec.cover.form_type == "F3A"
+    assert len(list(records)) == 7
@@ -2,6 +2,7 @@ from pathlib import Path
 
 import feco3
 import pyarrow as pa
+import pytest
 
 from . import common
 
@@ -40,3 +41,35 @@ def test_parquets(tmp_path: Path):
     fec = feco3.FecFile(path)
     fec.to_parquets(tmp_path)
     assert len(list(tmp_path.glob("*.parquet"))) == 5
+
+
+def test_parquets_row_group_size(tmp_path: Path):
+    import pyarrow.parquet as pq
+
+    path = common.get_case_path("1550548.fec")
+    fec = feco3.FecFile(path)
+    fec.to_parquets(tmp_path, row_group_size=10)
+    metadata = pq.ParquetFile(tmp_path / "SB23.parquet").metadata
+    assert metadata.num_row_groups == 4
+    assert metadata.num_rows == 35
+
+
+def test_dataframes():
+    pl = pytest.importorskip("polars")
+
+    path = common.get_case_path("slash_form.fec")
+    fec = feco3.FecFile(path)
+    dfs = feco3.DataFrameProcessor().process(fec)
+    assert set(dfs) == {"SA11AI", "SD10", "SC2/10", "SC/10", "SB17"}
+    sa = dfs["SA11AI"]
+    assert isinstance(sa, pl.DataFrame)
+    assert sa.height == 2
+    for column in [
+        "filer_committee_id_number",
+        "transaction_id",
+        "contributor_last_name",
+        "contribution_date",
+        "contribution_amount",
+    ]:
+        assert column in sa.columns
+    assert sa["transaction_id"].to_list() == ["SA11AI.4265", "SA11AI.4170"]